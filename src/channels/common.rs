@@ -1,21 +1,9 @@
 //! Channel common utilities - shared code across all channels.
 //!
 //! Provides:
-//! - User authorization helpers
 //! - Common configuration traits
 //! - Message parsing helpers
 
-/// Trait for types that have allowed users list
-pub trait HasAllowedUsers {
-    /// Get reference to allowed users list
-    fn allowed_users(&self) -> &[String];
-
-    /// Check if a user is allowed
-    fn is_allowed_user(&self, user_id: &str) -> bool {
-        self.allowed_users().is_empty() || self.allowed_users().contains(&user_id.to_string())
-    }
-}
-
 /// Trait for types that have enabled flag
 pub trait IsEnabled {
     /// Check if this is enabled
@@ -54,35 +42,6 @@ pub fn has_credentials(api_key: &str) -> bool {
 mod tests {
     use super::*;
 
-    #[derive(Debug, Clone)]
-    struct TestConfig {
-        allowed_users: Vec<String>,
-    }
-
-    impl HasAllowedUsers for TestConfig {
-        fn allowed_users(&self) -> &[String] {
-            &self.allowed_users
-        }
-    }
-
-    #[test]
-    fn test_is_allowed_with_users() {
-        let config = TestConfig {
-            allowed_users: vec!["user1".to_string(), "user2".to_string()]
-        };
-        assert!(config.is_allowed_user("user1"));
-        assert!(config.is_allowed_user("user2"));
-        assert!(!config.is_allowed_user("user3"));
-    }
-
-    #[test]
-    fn test_is_allowed_empty() {
-        let config = TestConfig {
-            allowed_users: vec![]
-        };
-        assert!(config.is_allowed_user("anyone"));
-    }
-
     #[test]
     fn test_validate_api_key_short() {
         let mut errors = Vec::new();
@@ -3,10 +3,12 @@
 //! This module provides a complete Discord bot implementation that connects
 //! to Discord's Gateway via WebSocket and handles messages.
 
-use crate::channels::Channel;
-use crate::channels::common::HasAllowedUsers;
-use crate::config::Discord as DiscordConfig;
+use crate::channels::{inbox, voice, Channel};
+use crate::config::{AttachmentsConfig, Discord as DiscordConfig};
 use crate::core::bus::MessageBus;
+use crate::core::permissions::Permissions;
+use crate::llm::providers::TranscriptionProvider;
+use crate::tools::approval;
 use crate::types::{InboundMessage, OutboundMessage};
 use anyhow::{Context, Result};
 use chrono::Utc;
@@ -21,6 +23,9 @@ use tokio::time::{interval, sleep, Duration};
 use tracing::{debug, error, info, warn};
 use tokio_tungstenite::tungstenite::protocol::Message;
 
+/// Largest file a bot may upload to a server without boosts
+const MAX_UPLOAD: u64 = 10 * inbox::MB;
+
 /// Type alias for WebSocket sender
 type WsSender = futures_util::stream::SplitSink<
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
@@ -78,28 +83,50 @@ struct GatewayMessage {
 #[derive(Clone)]
 pub struct DiscordChannel {
     config: DiscordConfig,
+    permissions: Permissions,
     running: Arc<Mutex<bool>>,
     session_id: Arc<Mutex<Option<String>>>,
     sequence: Arc<Mutex<Option<u64>>>,
     heartbeat_interval: Arc<Mutex<u64>>,
+    /// Gateway and delivery tasks, aborted by `stop`
+    tasks: Arc<Mutex<Vec<tokio::task::JoinHandle<()>>>>,
+    /// Speech to text for voice messages; without one they are attached as files
+    transcriber: Option<Arc<dyn TranscriptionProvider>>,
+    /// Limits on the files saved from messages
+    attachments: AttachmentsConfig,
 }
 
 impl DiscordChannel {
     /// Create a new Discord channel
-    pub fn new(config: DiscordConfig) -> Self {
+    pub fn new(config: DiscordConfig, permissions: Permissions) -> Self {
         Self {
             config,
+            permissions,
             running: Arc::new(Mutex::new(false)),
             session_id: Arc::new(Mutex::new(None)),
             sequence: Arc::new(Mutex::new(None)),
             heartbeat_interval: Arc::new(Mutex::new(0)),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            transcriber: None,
+            attachments: AttachmentsConfig::default(),
         }
     }
 
-    /// Check if user is allowed
-    fn is_allowed(&self, user_id: &str) -> bool {
-        self.config.allowed_users.is_empty()
-            || self.config.allowed_users.iter().any(|u| u == user_id)
+    /// Transcribe voice messages with this provider
+    pub fn with_transcriber(mut self, transcriber: Option<Arc<dyn TranscriptionProvider>>) -> Self {
+        self.transcriber = transcriber;
+        self
+    }
+
+    /// Save attachments under these limits
+    pub fn with_attachments(mut self, attachments: AttachmentsConfig) -> Self {
+        self.attachments = attachments;
+        self
+    }
+
+    /// Check if user has a role in this channel
+    fn is_allowed(&self, user_id: &str, channel_id: &str) -> bool {
+        self.permissions.is_allowed("discord", user_id, channel_id)
     }
 
     /// Connect to Gateway and handle events
@@ -305,6 +332,11 @@ impl DiscordChannel {
                                     self.handle_message(d, bus).await;
                                 }
                             }
+                            "INTERACTION_CREATE" => {
+                                if let Some(d) = &msg.d {
+                                    self.handle_interaction(d, bus).await;
+                                }
+                            }
                             "INVALID_SESSION" => {
                                 warn!("Invalid session received");
                                 if let Some(d) = &msg.d {
@@ -451,26 +483,11 @@ impl DiscordChannel {
 
         info!("Cleaned message content: '{}'", content);
 
-        if content.is_empty() {
-            info!("Empty content, skipping");
-            return;
-        }
-
         let channel_id = message.get("channel_id").and_then(|v| v.as_str()).unwrap_or("");
         let sender_id = message.get("author").and_then(|a| a.get("id")).and_then(|v| v.as_str()).unwrap_or("");
+        let allowed = self.is_allowed(sender_id, channel_id);
 
-        info!("Channel: {}, Sender: {}", channel_id, sender_id);
-
-        // Check if user is allowed
-        if !self.is_allowed(sender_id) {
-            debug!("User {} not in allowed list", sender_id);
-            return;
-        }
-
-        // Send immediate acknowledgment to prevent Discord timeout
-        let _ = self.send_message(channel_id, "正在思考...").await;
-
-        let inbound = InboundMessage {
+        let mut inbound = InboundMessage {
             channel: "discord".to_string(),
             sender_id: sender_id.to_string(),
             chat_id: channel_id.to_string(),
@@ -479,11 +496,99 @@ impl DiscordChannel {
             media: vec![],
             metadata: HashMap::new(),
         };
+        // Unknown users' attachments are not downloaded
+        if allowed {
+            self.save_attachments(message, &mut inbound).await;
+        }
 
-        info!("Discord message from {}: {}", sender_id, content);
+        if inbound.content.is_empty() && inbound.media.is_empty() {
+            info!("Empty content, skipping");
+            return;
+        }
+
+        info!("Channel: {}, Sender: {}", channel_id, sender_id);
+
+        // Send immediate acknowledgment to prevent Discord timeout. Unknown
+        // users are still forwarded so the gateway can ask for a pairing code.
+        if allowed {
+            let _ = self.send_message(channel_id, "正在思考...").await;
+        }
+
+        info!("Discord message from {}: {}", sender_id, inbound.content);
         bus.publish_inbound(inbound).await;
     }
 
+    /// Download attachments into the inbox and transcribe voice messages
+    async fn save_attachments(&self, message: &serde_json::Value, inbound: &mut InboundMessage) {
+        let attachments = message.get("attachments").and_then(|v| v.as_array());
+        for attachment in attachments.into_iter().flatten() {
+            let content_type = attachment.get("content_type").and_then(|v| v.as_str()).unwrap_or("");
+            let (Some(url), Some(name)) = (
+                attachment.get("url").and_then(|v| v.as_str()),
+                attachment.get("filename").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let size = attachment.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
+            if let Err(e) = inbox::check(&self.attachments, name, content_type, size) {
+                warn!("Skipping Discord attachment: {}", e);
+                continue;
+            }
+
+            let request = reqwest::Client::new().get(url);
+            let path = match inbox::download(request, &self.attachments, "discord", &inbound.chat_id, name).await {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("Failed to download Discord attachment {}: {}", name, e);
+                    continue;
+                }
+            };
+            // Audio is transcribed when possible, and attached otherwise
+            let transcribed = match &self.transcriber {
+                Some(transcriber) if voice::audio_extension(content_type).is_some() => {
+                    voice::transcribe_into(inbound, transcriber.as_ref(), &path).await
+                }
+                _ => false,
+            };
+            if !transcribed {
+                inbound.media.push(path.display().to_string());
+            }
+        }
+    }
+
+    /// Handle a button press on an approval prompt
+    async fn handle_interaction(&self, interaction: &serde_json::Value, bus: &MessageBus) {
+        let Some(custom_id) = interaction.pointer("/data/custom_id").and_then(|v| v.as_str()) else {
+            return;
+        };
+        let channel_id = interaction.get("channel_id").and_then(|v| v.as_str()).unwrap_or("");
+        // Guild interactions carry the user under `member`, DMs under `user`
+        let sender_id = interaction
+            .pointer("/member/user/id")
+            .or_else(|| interaction.pointer("/user/id"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        // Acknowledge so Discord does not show "interaction failed"
+        let id = interaction.get("id").and_then(|v| v.as_str()).unwrap_or("");
+        let token = interaction.get("token").and_then(|v| v.as_str()).unwrap_or("");
+        let ack = reqwest::Client::new()
+            .post(format!("https://discord.com/api/v10/interactions/{}/{}/callback", id, token))
+            .json(&json!({ "type": 6 }))
+            .send()
+            .await;
+        if let Err(e) = ack {
+            warn!("Failed to acknowledge interaction: {}", e);
+        }
+
+        if !self.is_allowed(sender_id, channel_id) {
+            return;
+        }
+        if let Some(answer) = approval::button_answer("discord", sender_id, channel_id, custom_id) {
+            bus.publish_inbound(answer).await;
+        }
+    }
+
     /// Send a message to Discord channel via REST API
     async fn send_message(&self, channel_id: &str, content: &str) -> Result<()> {
         if self.config.token.is_empty() {
@@ -533,16 +638,17 @@ impl Channel for DiscordChannel {
         let bus_for_gateway = bus.clone();
 
         // Start gateway connection in background
-        tokio::spawn(async move {
+        let gateway = tokio::spawn(async move {
             channel.connect_gateway(&bus_for_gateway).await;
         });
 
         // Start outbound message handler - subscribe to MessageBus outbound channel
         let outbound_rx = bus.subscribe_outbound();
         let config = self.config.clone();
-        tokio::spawn(async move {
+        let outbound = tokio::spawn(async move {
             Self::handle_outbound_messages(outbound_rx, &config).await;
         });
+        self.tasks.lock().await.extend([gateway, outbound]);
 
         Ok(())
     }
@@ -550,6 +656,9 @@ impl Channel for DiscordChannel {
     async fn stop(&mut self) -> Result<()> {
         info!("Discord channel stopping...");
         *self.running.lock().await = false;
+        for task in self.tasks.lock().await.drain(..) {
+            task.abort();
+        }
         Ok(())
     }
 
@@ -566,31 +675,78 @@ impl DiscordChannel {
     ) {
         while let Ok(msg) = rx.recv().await {
             if msg.channel == "discord" {
-                if let Err(e) = Self::send_message_impl(config, &msg.chat_id, &msg.content).await {
+                let (files, mut notes) = inbox::uploadable(&msg.media, MAX_UPLOAD);
+                let mut body = Self::message_body(&msg);
+                body["content"] = json!(inbox::with_notes(&msg.content, &notes));
+                let Err(e) = Self::send_message_impl(config, &msg.chat_id, body.clone(), &files).await else {
+                    continue;
+                };
+                error!("Failed to send outbound message: {}", e);
+                if files.is_empty() {
+                    continue;
+                }
+                // Send the text again, naming the files that did not upload
+                notes.extend(files.iter().map(|f| inbox::unsent_note(std::path::Path::new(f), "upload failed")));
+                body["content"] = json!(inbox::with_notes(&msg.content, &notes));
+                if let Err(e) = Self::send_message_impl(config, &msg.chat_id, body, &[]).await {
                     error!("Failed to send outbound message: {}", e);
                 }
             }
         }
     }
 
-    async fn send_message_impl(config: &DiscordConfig, channel_id: &str, content: &str) -> Result<()> {
+    /// Request body for an outbound message, with Approve/Deny buttons on approval prompts
+    fn message_body(msg: &OutboundMessage) -> serde_json::Value {
+        match msg.metadata.get(approval::APPROVAL_ID) {
+            Some(id) => json!({
+                "content": msg.content,
+                "components": [{
+                    "type": 1,
+                    "components": [
+                        { "type": 2, "style": 3, "label": "Approve", "custom_id": approval::button_data(id, true) },
+                        { "type": 2, "style": 4, "label": "Deny", "custom_id": approval::button_data(id, false) }
+                    ]
+                }]
+            }),
+            None => json!({ "content": msg.content }),
+        }
+    }
+
+    /// Send a message, uploading `files` as attachments
+    async fn send_message_impl(config: &DiscordConfig, channel_id: &str, mut body: serde_json::Value, files: &[String]) -> Result<()> {
         if config.token.is_empty() {
             warn!("Discord token not configured");
             return Ok(());
         }
 
         let client = reqwest::Client::new();
-        let response = client
+        let request = client
             .post(&format!(
                 "https://discord.com/api/v10/channels/{}/messages",
                 channel_id
             ))
-            .header("Authorization", format!("Bot {}", config.token))
-            .header("Content-Type", "application/json")
-            .json(&json!({ "content": content }))
-            .send()
-            .await
-            .context("Failed to send Discord message")?;
+            .header("Authorization", format!("Bot {}", config.token));
+
+        let request = if files.is_empty() {
+            request.json(&body)
+        } else {
+            // Files go in `files[n]` parts, described by `attachments` in the JSON part
+            let mut form = reqwest::multipart::Form::new();
+            let mut attachments = Vec::new();
+            for (id, file) in files.iter().enumerate() {
+                let path = std::path::Path::new(file);
+                let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("file").to_string();
+                let bytes = tokio::fs::read(path).await.with_context(|| format!("Failed to read {}", file))?;
+                let part = reqwest::multipart::Part::bytes(bytes)
+                    .file_name(name.clone())
+                    .mime_str(inbox::mime_type(path))?;
+                form = form.part(format!("files[{}]", id), part);
+                attachments.push(json!({ "id": id, "filename": name }));
+            }
+            body["attachments"] = json!(attachments);
+            request.multipart(form.text("payload_json", body.to_string()))
+        };
+        let response = request.send().await.context("Failed to send Discord message")?;
 
         if !response.status().is_success() {
             let error_text = response.text().await.unwrap_or_default();
@@ -619,7 +775,7 @@ mod tests {
     #[test]
     fn test_discord_channel_is_enabled() {
         let config = DiscordConfig::default_with_gateway();
-        let channel = DiscordChannel::new(config.clone());
+        let channel = DiscordChannel::new(config.clone(), test_permissions());
         assert!(!channel.is_enabled());
 
         let config_enabled = DiscordConfig {
@@ -627,24 +783,42 @@ mod tests {
             token: "test-token".to_string(),
             ..DiscordConfig::default_with_gateway()
         };
-        let channel_enabled = DiscordChannel::new(config_enabled);
+        let channel_enabled = DiscordChannel::new(config_enabled, test_permissions());
         assert!(channel_enabled.is_enabled());
     }
 
+    fn test_permissions() -> Permissions {
+        Permissions::new(&crate::config::Config::default())
+    }
+
     #[test]
     fn test_discord_is_allowed() {
-        let config = DiscordConfig {
-            allowed_users: vec!["123".to_string(), "456".to_string()],
-            ..DiscordConfig::default_with_gateway()
-        };
-        let channel = DiscordChannel::new(config.clone());
-
-        assert!(allowed_users_check(&config, "123"));
-        assert!(allowed_users_check(&config, "456"));
-        assert!(!allowed_users_check(&config, "789"));
+        let mut config = crate::config::Config::default();
+        config.channels.discord.allowed_users = vec!["123".to_string(), "456".to_string()];
+        let channel = DiscordChannel::new(config.channels.discord.clone(), Permissions::new(&config));
+
+        assert!(channel.is_allowed("123", "c"));
+        assert!(channel.is_allowed("456", "c"));
+        assert!(!channel.is_allowed("789", "c"));
+
+        // Unknown users are only let in when a default role is configured
+        let closed = DiscordChannel::new(DiscordConfig::default_with_gateway(), test_permissions());
+        assert!(!closed.is_allowed("anyone", "c"));
+        let mut config = crate::config::Config::default();
+        config.permissions.default_role = "member".to_string();
+        let open = DiscordChannel::new(DiscordConfig::default_with_gateway(), Permissions::new(&config));
+        assert!(open.is_allowed("anyone", "c"));
+    }
 
-        let config_empty = DiscordConfig::default_with_gateway();
-        assert!(allowed_users_check(&config_empty, "anyone"));
+    #[test]
+    fn test_approval_prompt_has_buttons() {
+        let mut msg = OutboundMessage::new("discord", "1", "Approve?");
+        assert!(DiscordChannel::message_body(&msg).get("components").is_none());
+
+        msg.metadata.insert(approval::APPROVAL_ID.to_string(), "abc".to_string());
+        let body = DiscordChannel::message_body(&msg);
+        assert_eq!(body["components"][0]["components"][0]["custom_id"], "approval:abc:yes");
+        assert_eq!(body["components"][0]["components"][1]["custom_id"], "approval:abc:no");
     }
 
     #[test]
@@ -657,7 +831,3 @@ mod tests {
         assert_eq!(OpCode::from_i64(99), None);
     }
 }
-
-fn allowed_users_check(config: &DiscordConfig, user_id: &str) -> bool {
-    config.allowed_users.is_empty() || config.allowed_users.iter().any(|u| u == user_id)
-}
@@ -0,0 +1,428 @@
+//! Inbox - attachments received from chat channels.
+//!
+//! Files are saved under `workspace/inbox/<channel>/<chat>/` so tools can
+//! read them, and their paths go in `InboundMessage.media`. The agent names
+//! them in the user's message and sends images among them to vision models.
+//! `channels.attachments` limits their size and types. Files the agent
+//! sends, such as voice replies, are kept in `workspace/outbox/<channel>/<chat>/`
+//! for `channels.attachments.outbox_retention_days`.
+//!
+//! Channels upload outbound files natively. Files over a channel's size
+//! limit, or that fail to upload, are named in a text note instead.
+
+use crate::config::AttachmentsConfig;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// One megabyte, for channel upload limits
+pub const MB: u64 = 1024 * 1024;
+
+/// Directory holding a chat's attachments
+pub fn inbox_dir(channel: &str, chat_id: &str) -> PathBuf {
+    crate::config::workspace_path()
+        .join("inbox")
+        .join(safe_name(channel))
+        .join(safe_name(chat_id))
+}
+
+/// Directory holding files sent to chats
+pub fn outbox_root() -> PathBuf {
+    crate::config::workspace_path().join("outbox")
+}
+
+/// Directory holding files sent to a chat
+pub fn outbox_dir(channel: &str, chat_id: &str) -> PathBuf {
+    outbox_root()
+        .join(safe_name(channel))
+        .join(safe_name(chat_id))
+}
+
+/// A name with path separators and unusual characters replaced
+fn safe_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_.@".contains(c) { c } else { '_' })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => "file".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// Save an attachment. The name gets a timestamp prefix so repeated uploads
+/// of `image.png` do not overwrite each other, and images sent without an
+/// image extension get one.
+pub async fn save(channel: &str, chat_id: &str, file_name: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    save_in(&inbox_dir(channel, chat_id), file_name, bytes).await
+}
+
+/// Save a file in `dir`, named as `save` does
+pub async fn save_in(dir: &Path, file_name: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    let path = stamped_path(dir, file_name, bytes).await?;
+    tokio::fs::write(&path, bytes)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Create `dir` and pick the path `save` would use for a file starting with `head`
+async fn stamped_path(dir: &Path, file_name: &str, head: &[u8]) -> Result<PathBuf, String> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let mut name = safe_name(file_name);
+    if image_mime(Path::new(&name)).is_none()
+        && let Some(extension) = sniff_image(head).and_then(image_extension)
+    {
+        name = format!("{}.{}", name, extension);
+    }
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    Ok(dir.join(format!("{}-{}", stamp, name)))
+}
+
+/// Download an attachment and save it, if `config` allows its type and size
+pub async fn download(
+    request: reqwest::RequestBuilder,
+    config: &AttachmentsConfig,
+    channel: &str,
+    chat_id: &str,
+    file_name: &str,
+) -> Result<PathBuf, String> {
+    download_into(request, config, &inbox_dir(channel, chat_id), file_name).await
+}
+
+/// Download a file into `dir`, named as `save` does
+pub async fn download_into(
+    request: reqwest::RequestBuilder,
+    config: &AttachmentsConfig,
+    dir: &Path,
+    file_name: &str,
+) -> Result<PathBuf, String> {
+    let mut response = request.send().await.map_err(|e| format!("Download failed: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Download failed: HTTP {}", response.status()));
+    }
+    let mime_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    // Skip large files before reading them when the server gives a length
+    check(config, file_name, &mime_type, response.content_length().unwrap_or(0))?;
+    let first = response.chunk().await.map_err(|e| format!("Download failed: {}", e))?.unwrap_or_default();
+    let path = stamped_path(dir, file_name, &first).await?;
+    // The length may be missing or wrong, so the limit is checked as chunks
+    // arrive and a file that goes over it is deleted
+    let written = async {
+        let mut file = tokio::fs::File::create(&path)
+            .await
+            .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+        let mut total = 0;
+        let mut chunk = Some(first);
+        while let Some(bytes) = chunk {
+            total += bytes.len() as u64;
+            check(config, file_name, &mime_type, total)?;
+            file.write_all(&bytes)
+                .await
+                .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+            chunk = response.chunk().await.map_err(|e| format!("Download failed: {}", e))?;
+        }
+        file.flush().await.map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+    }
+    .await;
+    if let Err(e) = written {
+        let _ = tokio::fs::remove_file(&path).await;
+        return Err(e);
+    }
+    Ok(path)
+}
+
+/// Delete files under `dir` last modified more than `max_age` ago, and the
+/// directories they leave empty. Returns how many files were deleted.
+pub fn prune(dir: &Path, max_age: std::time::Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut deleted = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            deleted += prune(&path, max_age);
+            // Fails unless the directory is now empty
+            let _ = std::fs::remove_dir(&path);
+        } else if meta.modified().ok().and_then(|t| t.elapsed().ok()).is_some_and(|age| age > max_age)
+            && std::fs::remove_file(&path).is_ok()
+        {
+            deleted += 1;
+        }
+    }
+    deleted
+}
+
+/// Check an inbound file against `channels.attachments`. An empty
+/// `mime_type` is taken from the name's extension.
+pub fn check(config: &AttachmentsConfig, file_name: &str, mime_type: &str, size: u64) -> Result<(), String> {
+    let max = config.max_size_mb * MB;
+    if max > 0 && size > max {
+        return Err(format!("{} is {}, over the {} limit", file_name, size_text(size), size_text(max)));
+    }
+    let essence = match mime_type.split(';').next().unwrap_or_default().trim() {
+        "" => self::mime_type(Path::new(file_name)).to_string(),
+        essence => essence.to_ascii_lowercase(),
+    };
+    let allowed = config.allowed_types.is_empty()
+        || config.allowed_types.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => essence.starts_with(&prefix.to_ascii_lowercase()),
+            None => essence == pattern.to_ascii_lowercase(),
+        });
+    if !allowed {
+        return Err(format!("{} has type {}, which is not in the allowed types", file_name, essence));
+    }
+    Ok(())
+}
+
+/// Name of a saved attachment without its timestamp prefix
+pub fn original_name(path: &Path) -> String {
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    // `save` prefixes names with `%Y%m%d-%H%M%S-`
+    let stamped = name.len() > 16
+        && name.as_bytes()[..16]
+            .iter()
+            .enumerate()
+            .all(|(i, &b)| if i == 8 || i == 15 { b == b'-' } else { b.is_ascii_digit() });
+    if stamped { name[16..].to_string() } else { name.to_string() }
+}
+
+/// Message text followed by a line naming each attachment and where it was
+/// saved, so the agent can open it with file tools
+pub fn describe(content: &str, media: &[String]) -> String {
+    let lines: Vec<String> = media
+        .iter()
+        .map(|file| format!("User attached {} (saved at {})", original_name(Path::new(file)), file))
+        .collect();
+    with_notes(content, &lines)
+}
+
+/// Mime type of an image file vision models accept, from its extension
+pub fn image_mime(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// Mime type of a file to upload, from its extension
+pub fn mime_type(path: &Path) -> &'static str {
+    if let Some(mime_type) = image_mime(path) {
+        return mime_type;
+    }
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    match extension.as_str() {
+        "ogg" | "opus" => "audio/ogg",
+        "mp3" => "audio/mpeg",
+        "aac" => "audio/aac",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        "txt" | "log" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Split outbound attachments into the files a channel can upload under its
+/// per-file size `limit`, and notes to send as text in place of the rest
+pub fn uploadable(media: &[String], limit: u64) -> (Vec<String>, Vec<String>) {
+    let mut files = Vec::new();
+    let mut notes = Vec::new();
+    for file in media {
+        let path = Path::new(file);
+        match std::fs::metadata(path) {
+            Ok(meta) if !meta.is_file() => notes.push(unsent_note(path, "not a file")),
+            Ok(meta) if meta.len() > limit => {
+                let reason = format!("{} is over the {} limit", size_text(meta.len()), size_text(limit));
+                notes.push(unsent_note(path, &reason));
+            }
+            Ok(_) => files.push(file.clone()),
+            Err(e) => notes.push(unsent_note(path, &e.to_string())),
+        }
+    }
+    (files, notes)
+}
+
+/// Text sent in place of an attachment that could not be uploaded
+pub fn unsent_note(path: &Path, reason: &str) -> String {
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    format!("[Attachment {} not sent: {}]", name, reason)
+}
+
+/// Message text followed by notes about unsent attachments
+pub fn with_notes(content: &str, notes: &[String]) -> String {
+    std::iter::once(content)
+        .chain(notes.iter().map(String::as_str))
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A size in megabytes, such as `12.5 MB`
+fn size_text(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / MB as f64)
+}
+
+/// Mime type of image data, from its signature
+fn sniff_image(bytes: &[u8]) -> Option<&'static str> {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// File extension for an image mime type
+pub fn image_extension(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_name() {
+        assert_eq!(safe_name("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(safe_name("-1001234"), "-1001234");
+        assert_eq!(safe_name("screen shot.png"), "screen_shot.png");
+        assert_eq!(safe_name(".."), "file");
+    }
+
+    #[test]
+    fn test_image_mime() {
+        assert_eq!(image_mime(Path::new("inbox/a/b/photo.JPG")), Some("image/jpeg"));
+        assert_eq!(image_mime(Path::new("report.pdf")), None);
+        assert_eq!(image_extension("image/webp"), Some("webp"));
+        assert_eq!(sniff_image(b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+        assert_eq!(sniff_image(b"%PDF-1.7"), None);
+        assert_eq!(mime_type(Path::new("reply.ogg")), "audio/ogg");
+    }
+
+    #[test]
+    fn test_check() {
+        let mut config = AttachmentsConfig::default();
+        assert!(check(&config, "report.pdf", "application/pdf", 5 * MB).is_ok());
+        let too_large = check(&config, "backup.zip", "", 30 * MB).unwrap_err();
+        assert_eq!(too_large, "backup.zip is 30.0 MB, over the 20.0 MB limit");
+
+        config.max_size_mb = 0;
+        config.allowed_types = vec!["image/*".to_string(), "text/csv".to_string()];
+        assert!(check(&config, "backup.zip", "", 30 * MB).is_err());
+        assert!(check(&config, "photo.jpg", "image/jpeg", 1).is_ok());
+        assert!(check(&config, "sales.csv", "", 1).is_ok());
+        assert!(check(&config, "sales", "text/csv; charset=utf-8", 1).is_ok());
+        assert!(check(&config, "app.exe", "application/x-msdownload", 1).is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        let media = vec!["/ws/inbox/telegram/42/20261018-093012-report.pdf".to_string()];
+        assert_eq!(original_name(Path::new(&media[0])), "report.pdf");
+        assert_eq!(original_name(Path::new("notes.txt")), "notes.txt");
+        assert_eq!(
+            describe("Summarize this", &media),
+            "Summarize this\nUser attached report.pdf (saved at /ws/inbox/telegram/42/20261018-093012-report.pdf)"
+        );
+        assert_eq!(describe("Hi", &[]), "Hi");
+    }
+
+    #[test]
+    fn test_uploadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let small = dir.path().join("chart.png");
+        let large = dir.path().join("dump.csv");
+        std::fs::write(&small, [0u8; 10]).unwrap();
+        std::fs::write(&large, vec![0u8; 2 * MB as usize]).unwrap();
+        let missing = dir.path().join("gone.pdf");
+        let media: Vec<String> = [&small, &large, &missing].iter().map(|p| p.display().to_string()).collect();
+
+        let (files, notes) = uploadable(&media, MB);
+        assert_eq!(files, vec![small.display().to_string()]);
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0], "[Attachment dump.csv not sent: 2.0 MB is over the 1.0 MB limit]");
+        assert!(notes[1].starts_with("[Attachment gone.pdf not sent: "));
+
+        assert_eq!(with_notes("Here you go", &notes[..1]), format!("Here you go\n{}", notes[0]));
+        assert_eq!(with_notes("", &notes[..1]), notes[0]);
+    }
+
+    #[tokio::test]
+    async fn test_download_stops_at_limit() {
+        use tokio::io::AsyncReadExt;
+        use tokio::net::TcpListener;
+
+        // No Content-Length, so the limit is only found while reading
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for size in [2 * MB, MB / 2] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 4096];
+                let _ = stream.read(&mut request).await.unwrap();
+                let header = "HTTP/1.1 200 OK\r\nContent-Type: application/zip\r\nConnection: close\r\n\r\n";
+                stream.write_all(header.as_bytes()).await.unwrap();
+                let _ = stream.write_all(&vec![0u8; size as usize]).await;
+                let _ = stream.shutdown().await;
+            }
+        });
+        let url = format!("http://{}/file", addr);
+        let dir = tempfile::tempdir().unwrap();
+        let config = AttachmentsConfig { max_size_mb: 1, ..Default::default() };
+
+        let client = reqwest::Client::new();
+        let error = download_into(client.get(&url), &config, dir.path(), "big.zip").await.unwrap_err();
+        assert!(error.contains("over the 1.0 MB limit"), "{}", error);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        let path = download_into(client.get(&url), &config, dir.path(), "small.zip").await.unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), MB / 2);
+    }
+
+    #[test]
+    fn test_prune() {
+        use std::time::{Duration, SystemTime};
+
+        let root = tempfile::tempdir().unwrap();
+        let chat = root.path().join("telegram").join("42");
+        std::fs::create_dir_all(&chat).unwrap();
+        let old = chat.join("old.ogg");
+        let new = root.path().join("new.ogg");
+        std::fs::write(&old, b"a").unwrap();
+        std::fs::write(&new, b"b").unwrap();
+        let week_ago = SystemTime::now() - Duration::from_secs(7 * 24 * 3600);
+        std::fs::File::options().write(true).open(&old).unwrap().set_modified(week_ago).unwrap();
+
+        assert_eq!(prune(root.path(), Duration::from_secs(24 * 3600)), 1);
+        assert!(!root.path().join("telegram").exists());
+        assert!(new.exists());
+    }
+}
@@ -4,6 +4,8 @@ pub mod telegram;
 pub mod whatsapp;
 pub mod qq;
 pub mod common;
+pub mod inbox;
+pub mod voice;
 
 use crate::core::bus::MessageBus;
 use crate::config::Config;
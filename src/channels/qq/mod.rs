@@ -1,15 +1,21 @@
+use crate::channels::{inbox, voice};
 use crate::core::bus::MessageBus;
-use crate::config::Config;
-use crate::types::InboundMessage;
+use crate::config::{AttachmentsConfig, Config};
+use crate::core::permissions::Permissions;
+use crate::llm::providers::{create_transcriber, TranscriptionProvider};
+use crate::types::{InboundMessage, OutboundMessage};
 use futures_util::stream::StreamExt;
 use futures_util::SinkExt;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 use tokio_tungstenite::{connect_async, tungstenite::Message as WsMessage};
 use tracing::{debug, error, info, warn};
 
+/// Largest image or audio file sent through OneBot
+const MAX_UPLOAD: u64 = 30 * inbox::MB;
+
 /// OneBot v11 event types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "post_type")]
@@ -84,7 +90,79 @@ struct SenderInfo {
     card: Option<String>,
 }
 
+/// Unescape a CQ code parameter value
+fn cq_unescape(value: &str) -> String {
+    value
+        .replace("&#44;", ",")
+        .replace("&#91;", "[")
+        .replace("&#93;", "]")
+        .replace("&amp;", "&")
+}
+
+/// Escape a CQ code parameter value
+fn cq_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('[', "&#91;")
+        .replace(']', "&#93;")
+        .replace(',', "&#44;")
+}
+
+/// Message text with outbound attachments appended as CQ codes: images as
+/// `[CQ:image]`, audio as `[CQ:record]`. OneBot reads them from local
+/// `file://` paths. Other files and ones over the size limit are named in
+/// a note instead.
+fn with_files(content: &str, media: &[String]) -> String {
+    let (files, mut notes) = inbox::uploadable(media, MAX_UPLOAD);
+    let mut message = content.to_string();
+    for file in &files {
+        let path = std::path::Path::new(file);
+        let kind = match inbox::mime_type(path) {
+            mime_type if mime_type.starts_with("image/") => "image",
+            mime_type if mime_type.starts_with("audio/") => "record",
+            _ => {
+                notes.push(inbox::unsent_note(path, "QQ only accepts images and audio"));
+                continue;
+            }
+        };
+        message.push_str(&format!("[CQ:{},file={}]", kind, cq_escape(&format!("file://{}", path.display()))));
+    }
+    inbox::with_notes(&message, &notes)
+}
+
+/// A file sent as a CQ code
+#[derive(Debug, Clone, PartialEq)]
+struct CqFile {
+    /// `image` or `record` (a voice message)
+    kind: String,
+    name: String,
+    url: String,
+}
+
+/// Split `[CQ:image,...]` and `[CQ:record,...]` codes out of a message.
+/// Returns the remaining text and the files that have a URL.
+fn split_files(message: &str) -> (String, Vec<CqFile>) {
+    let mut text = String::new();
+    let mut files = Vec::new();
+    let mut rest = message;
+    while let Some(start) = ["[CQ:image,", "[CQ:record,"].iter().filter_map(|code| rest.find(code)).min() {
+        let Some(len) = rest[start..].find(']') else {
+            break;
+        };
+        text.push_str(&rest[..start]);
+        let (kind, params) = rest[start + "[CQ:".len()..start + len].split_once(',').unwrap_or_default();
+        let params: HashMap<&str, &str> = params.split(',').filter_map(|param| param.split_once('=')).collect();
+        if let (Some(file), Some(url)) = (params.get("file"), params.get("url")) {
+            files.push(CqFile { kind: kind.to_string(), name: cq_unescape(file), url: cq_unescape(url) });
+        }
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(rest);
+    (text.trim().to_string(), files)
+}
+
 /// QQ channel implementation using OneBot v11 protocol
+#[derive(Clone)]
 pub struct QQChannel {
     /// HTTP API URL
     api_url: String,
@@ -94,31 +172,53 @@ pub struct QQChannel {
     access_token: Option<String>,
     /// Message bus for publishing inbound messages
     bus: MessageBus,
-    /// Allowed user IDs
-    allowed_users: Vec<String>,
-    /// Shutdown flag
-    running: Arc<AtomicBool>,
+    /// Roles of senders; unknown senders' files are not downloaded
+    permissions: Permissions,
+    /// Speech to text for voice messages; without one they are attached as files
+    transcriber: Option<Arc<dyn TranscriptionProvider>>,
+    /// Limits on the files saved from messages
+    attachments: AttachmentsConfig,
+    /// Chats seen to be groups. Chat ids are group or user ids, so replies
+    /// to chats not seen here go out as private messages.
+    groups: Arc<Mutex<HashSet<String>>>,
 }
 
 impl QQChannel {
     /// Create a new QQ channel
-    pub fn new(api_url: String, event_url: String, access_token: Option<String>, bus: MessageBus, allowed_users: Vec<String>) -> Self {
+    pub fn new(
+        api_url: String,
+        event_url: String,
+        access_token: Option<String>,
+        bus: MessageBus,
+        permissions: Permissions,
+    ) -> Self {
         Self {
             api_url,
             event_url,
             access_token,
             bus,
-            allowed_users,
-            running: Arc::new(AtomicBool::new(true)),
+            permissions,
+            transcriber: None,
+            attachments: AttachmentsConfig::default(),
+            groups: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
-    fn is_allowed(&self, user_id: &str) -> bool {
-        self.allowed_users.is_empty() || self.allowed_users.contains(&user_id.to_string())
+    /// Transcribe voice messages with this provider
+    pub fn with_transcriber(mut self, transcriber: Option<Arc<dyn TranscriptionProvider>>) -> Self {
+        self.transcriber = transcriber;
+        self
+    }
+
+    /// Save attachments under these limits
+    pub fn with_attachments(mut self, attachments: AttachmentsConfig) -> Self {
+        self.attachments = attachments;
+        self
     }
 
-    /// Start the QQ channel event loop
-    pub async fn run(&mut self) -> anyhow::Result<()> {
+    /// Connect to OneBot and start the event, heartbeat and delivery tasks.
+    /// Abort the returned tasks to stop the channel.
+    pub async fn run(self) -> anyhow::Result<Vec<tokio::task::JoinHandle<()>>> {
         info!("Connecting to OneBot WebSocket at {}...", self.event_url);
 
         let (ws_stream, _) = connect_async(&self.event_url).await
@@ -128,59 +228,22 @@ impl QQChannel {
 
         let (mut write, mut read) = ws_stream.split();
 
-        let bus = self.bus.clone();
-        let allowed_users = self.allowed_users.clone();
-        let running = self.running.clone();
-
         // Handle incoming messages
-        tokio::spawn(async move {
-            while running.load(Ordering::Relaxed) {
+        let channel = self.clone();
+        let events = tokio::spawn(async move {
+            loop {
                 match read.next().await {
                     Some(Ok(WsMessage::Text(text))) => {
-                        if let Ok(event) = serde_json::from_str::<OneBotEvent>(&text) {
-                            if let OneBotEvent::Message(msg_event) = event {
-                                let user_id = msg_event.user_id.map(|id| id.to_string()).unwrap_or_default();
-                                let group_id = msg_event.group_id.map(|id| id.to_string());
-                                let content = msg_event.msg_content.clone();
-
-                                if content.is_empty() {
-                                    continue;
-                                }
-
-                                // Check if user is allowed
-                                if !allowed_users.is_empty() && !allowed_users.contains(&user_id) {
-                                    warn!("User {} is not authorized", user_id);
-                                    continue;
-                                }
-
-                                debug!("Received QQ message from user {}: {}", user_id, content);
-
-                                let chat_id = group_id.clone().unwrap_or_else(|| user_id.clone());
-
-                                // Create and publish inbound message
-                                let inbound = InboundMessage::new(
-                                    "qq",
-                                    &user_id,
-                                    &chat_id,
-                                    &content,
-                                );
-
-                                bus.publish_inbound(inbound).await;
-                            }
+                        if let Ok(OneBotEvent::Message(msg_event)) = serde_json::from_str::<OneBotEvent>(&text) {
+                            channel.handle_message(msg_event).await;
                         }
                     }
                     Some(Ok(WsMessage::Close(_))) => {
                         warn!("OneBot WebSocket connection closed");
                         break;
                     }
-                    Some(Ok(WsMessage::Ping(_))) | Some(Ok(WsMessage::Pong(_))) => {
-                        // Ignore heartbeat messages
-                    }
-                    Some(Ok(WsMessage::Binary(_))) => {
-                        // Ignore binary messages
-                    }
-                    Some(Ok(WsMessage::Frame(_))) => {
-                        // Ignore frame messages
+                    Some(Ok(_)) => {
+                        // Ignore heartbeat, binary and frame messages
                     }
                     Some(Err(e)) => {
                         error!("WebSocket error: {}", e);
@@ -194,10 +257,9 @@ impl QQChannel {
         });
 
         // Send heartbeat every 30 seconds
-        let running_heartbeat = self.running.clone();
-        tokio::spawn(async move {
+        let heartbeat = tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-            while running_heartbeat.load(Ordering::Relaxed) {
+            loop {
                 interval.tick().await;
                 if let Err(e) = write.send(WsMessage::Text(json!({
                     "action": "send_packets",
@@ -210,28 +272,95 @@ impl QQChannel {
             }
         });
 
-        Ok(())
+        // Send outbound messages addressed to QQ
+        let mut outbound_rx = self.bus.subscribe_outbound();
+        let delivery = tokio::spawn(async move {
+            while let Ok(msg) = outbound_rx.recv().await {
+                if msg.channel == "qq" && let Err(e) = self.deliver(&msg).await {
+                    error!("Failed to send QQ message: {}", e);
+                }
+            }
+        });
+
+        Ok(vec![events, heartbeat, delivery])
     }
 
-    /// Stop the channel
-    pub async fn stop(&mut self) {
-        self.running.store(false, Ordering::Relaxed);
-        info!("QQ channel stopped");
+    /// Publish a message event, with its files downloaded into the inbox
+    async fn handle_message(&self, msg_event: MessageEvent) {
+        let user_id = msg_event.user_id.map(|id| id.to_string()).unwrap_or_default();
+        let group_id = msg_event.group_id.map(|id| id.to_string());
+        let (content, files) = split_files(&msg_event.msg_content);
+        let chat_id = group_id.clone().unwrap_or_else(|| user_id.clone());
+        if group_id.is_some() {
+            self.groups.lock().unwrap().insert(chat_id.clone());
+        }
+        let mut inbound = InboundMessage::new(
+            "qq",
+            &user_id,
+            &chat_id,
+            &content,
+        );
+
+        // Access is checked by the gateway, which also handles pairing;
+        // unknown senders' files are not downloaded
+        let allowed = self.permissions.is_allowed("qq", &user_id, &chat_id);
+        for file in files.into_iter().filter(|_| allowed) {
+            let request = reqwest::Client::new().get(&file.url);
+            let path = match inbox::download(request, &self.attachments, "qq", &chat_id, &file.name).await {
+                Ok(path) => path,
+                Err(e) => {
+                    warn!("Failed to download QQ {} {}: {}", file.kind, file.name, e);
+                    continue;
+                }
+            };
+            // Voice messages are transcribed when possible, and attached otherwise
+            let transcribed = match &self.transcriber {
+                Some(transcriber) if file.kind == "record" => {
+                    voice::transcribe_into(&mut inbound, transcriber.as_ref(), &path).await
+                }
+                _ => false,
+            };
+            if !transcribed {
+                inbound.media.push(path.display().to_string());
+            }
+        }
+
+        if inbound.content.is_empty() && inbound.media.is_empty() {
+            return;
+        }
+
+        debug!("Received QQ message from user {}: {}", user_id, inbound.content);
+
+        self.bus.publish_inbound(inbound).await;
+    }
+
+    /// Send an outbound message to the group or user it is addressed to
+    async fn deliver(&self, msg: &OutboundMessage) -> anyhow::Result<()> {
+        if msg.content.is_empty() && msg.media.is_empty() {
+            return Ok(());
+        }
+        let id: i64 = msg.chat_id.parse().map_err(|_| anyhow::anyhow!("Invalid QQ chat id: {}", msg.chat_id))?;
+        let group = self.groups.lock().unwrap().contains(&msg.chat_id);
+        if group {
+            self.send_group_msg(id, &msg.content, &msg.media).await
+        } else {
+            self.send_private_msg(id, &msg.content, &msg.media).await
+        }
     }
 
-    /// Send a private message via OneBot HTTP API
-    pub async fn send_private_msg(&self, user_id: i64, content: &str) -> anyhow::Result<()> {
+    /// Send a private message via OneBot HTTP API, with `media` attached
+    pub async fn send_private_msg(&self, user_id: i64, content: &str, media: &[String]) -> anyhow::Result<()> {
         self.call_api("send_private_msg", json!({
             "user_id": user_id,
-            "message": content,
+            "message": with_files(content, media),
         })).await
     }
 
-    /// Send a group message via OneBot HTTP API
-    pub async fn send_group_msg(&self, group_id: i64, content: &str) -> anyhow::Result<()> {
+    /// Send a group message via OneBot HTTP API, with `media` attached
+    pub async fn send_group_msg(&self, group_id: i64, content: &str, media: &[String]) -> anyhow::Result<()> {
         self.call_api("send_group_msg", json!({
             "group_id": group_id,
-            "message": content,
+            "message": with_files(content, media),
         })).await
     }
 
@@ -262,8 +391,12 @@ impl QQChannel {
     }
 }
 
-/// Start the QQ channel with OneBot connection
-pub async fn start_qq_channel(config: &Config, bus: &MessageBus) -> anyhow::Result<QQChannel> {
+/// Start the QQ channel with OneBot connection. Returns its tasks; abort them to stop it.
+pub async fn start_qq_channel(
+    config: &Config,
+    bus: &MessageBus,
+    permissions: Permissions,
+) -> anyhow::Result<Vec<tokio::task::JoinHandle<()>>> {
     let qq_config = &config.channels.qq;
 
     if !qq_config.enabled {
@@ -282,20 +415,52 @@ pub async fn start_qq_channel(config: &Config, bus: &MessageBus) -> anyhow::Resu
         Some(qq_config.access_token.clone())
     };
 
-    let allowed_users: Vec<String> = qq_config.allowed_users
-        .iter()
-        .cloned()
-        .collect();
-
-    let mut channel = QQChannel::new(
+    QQChannel::new(
         qq_config.api_url.clone(),
         qq_config.event_url.clone(),
         access_token,
         bus.clone(),
-        allowed_users,
-    );
+        permissions,
+    )
+    .with_transcriber(create_transcriber(config))
+    .with_attachments(config.channels.attachments.clone())
+    .run()
+    .await
+}
 
-    channel.run().await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_files() {
+        let (text, files) = split_files(
+            "What is this? [CQ:image,file=a1b2.image,url=https://gchat.qpic.cn/x?term=2&amp;is_origin=0][CQ:record,file=c3.amr,url=http://127.0.0.1/c3.amr]",
+        );
+        assert_eq!(text, "What is this?");
+        assert_eq!(files[0], CqFile {
+            kind: "image".to_string(),
+            name: "a1b2.image".to_string(),
+            url: "https://gchat.qpic.cn/x?term=2&is_origin=0".to_string(),
+        });
+        assert_eq!(files[1].kind, "record");
+        assert_eq!(split_files("[CQ:face,id=1] hi").0, "[CQ:face,id=1] hi");
+    }
 
-    Ok(channel)
+    #[test]
+    fn test_with_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let chart = dir.path().join("chart,1.png");
+        let report = dir.path().join("report.pdf");
+        std::fs::write(&chart, b"\x89PNG").unwrap();
+        std::fs::write(&report, b"%PDF").unwrap();
+        let media = vec![chart.display().to_string(), report.display().to_string()];
+
+        let message = with_files("Here it is", &media);
+        let expected_code = format!("[CQ:image,file=file://{}/chart&#44;1.png]", dir.path().display());
+        assert_eq!(
+            message,
+            format!("Here it is{}\n[Attachment report.pdf not sent: QQ only accepts images and audio]", expected_code)
+        );
+    }
 }
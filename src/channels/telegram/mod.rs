@@ -1,34 +1,60 @@
+use crate::channels::{inbox, voice};
 use crate::core::bus::MessageBus;
-use crate::config::Config;
-use crate::types::InboundMessage;
+use crate::config::{AttachmentsConfig, Config};
+use crate::core::permissions::Permissions;
+use crate::llm::providers::{create_transcriber, TranscriptionProvider};
+use crate::tools::approval;
+use crate::types::{InboundMessage, OutboundMessage};
 use teloxide::Bot;
-use teloxide::payloads::GetUpdatesSetters;
+use teloxide::net::Download;
+use teloxide::payloads::{GetUpdatesSetters, SendMessageSetters};
 use teloxide::prelude::{Request, Requester};
+use teloxide::types::{FileMeta, InlineKeyboardButton, InlineKeyboardMarkup, InputFile};
 use tracing::info;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use tokio::time::{interval, Duration};
 
+/// Largest file a bot may upload
+const MAX_UPLOAD: u64 = 50 * inbox::MB;
+
+/// Largest image sent as a photo; bigger ones go as documents
+const MAX_PHOTO: u64 = 10 * inbox::MB;
+
 /// Telegram channel implementation
 #[derive(Clone)]
 pub struct TelegramChannel {
     bot: Bot,
     bus: MessageBus,
-    allowed_users: Vec<String>,
+    permissions: Permissions,
+    /// Speech to text for voice notes; without one they are ignored
+    transcriber: Option<Arc<dyn TranscriptionProvider>>,
+    /// Limits on the files saved from messages
+    attachments: AttachmentsConfig,
     last_update_id: i32,
 }
 
 impl TelegramChannel {
-    pub fn new(bot: Bot, bus: MessageBus, allowed_users: Vec<String>) -> Self {
+    pub fn new(
+        bot: Bot,
+        bus: MessageBus,
+        permissions: Permissions,
+        transcriber: Option<Arc<dyn TranscriptionProvider>>,
+        attachments: AttachmentsConfig,
+    ) -> Self {
         Self {
             bot,
             bus,
-            allowed_users,
+            permissions,
+            transcriber,
+            attachments,
             last_update_id: 0,
         }
     }
 
-    fn is_allowed(&self, user_id: &str) -> bool {
-        self.allowed_users.is_empty() || self.allowed_users.contains(&user_id.to_string())
+    fn is_allowed(&self, user_id: &str, chat_id: &str) -> bool {
+        self.permissions.is_allowed("telegram", user_id, chat_id)
     }
 
     async fn fetch_updates(&mut self) -> anyhow::Result<()> {
@@ -43,9 +69,10 @@ impl TelegramChannel {
             // Update the last update id
             self.last_update_id = update.id.0 as i32;
 
-            // Handle message updates
-            if let teloxide::types::UpdateKind::Message(msg) = update.kind {
-                self.handle_message(&msg).await;
+            match update.kind {
+                teloxide::types::UpdateKind::Message(msg) => self.handle_message(&msg).await,
+                teloxide::types::UpdateKind::CallbackQuery(query) => self.handle_callback(query).await,
+                _ => {}
             }
         }
 
@@ -59,30 +86,74 @@ impl TelegramChannel {
             .map(|u| u.id.0.to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        // Check if user is allowed
-        if !self.is_allowed(&sender_id) {
-            let _ = self.bot.send_message(msg.chat.id, "You are not authorized to use this bot.")
-                .await;
+        // Photos and files carry their text as a caption
+        let content = msg.text().or(msg.caption()).unwrap_or("").to_string();
+        let mut inbound = InboundMessage::new(
+            "telegram",
+            &sender_id,
+            &chat_id,
+            &content,
+        );
+
+        // Unknown senders are still forwarded for pairing, but their files are not downloaded
+        if self.is_allowed(&sender_id, &chat_id)
+            && let Some(file) = attachment(msg)
+            && let Some(path) = self.download(&file, &chat_id).await
+        {
+            // Audio is transcribed when possible, and attached otherwise
+            let transcribed = match &self.transcriber {
+                Some(transcriber) if file.mime_type.starts_with("audio/") => {
+                    voice::transcribe_into(&mut inbound, transcriber.as_ref(), &path).await
+                }
+                _ => false,
+            };
+            if !transcribed {
+                inbound.media.push(path.display().to_string());
+            }
+        }
+
+        if inbound.content.is_empty() && inbound.media.is_empty() {
             return;
         }
 
-        let content = msg.text().unwrap_or("").to_string();
+        info!("Received message from {} in chat {}: {}", sender_id, chat_id, inbound.content);
+        self.bus.publish_inbound(inbound).await;
+    }
 
-        if content.is_empty() {
-            return;
+    /// Download a file into the chat's inbox, if its type and size are allowed
+    async fn download(&self, attachment: &Attachment, chat_id: &str) -> Option<PathBuf> {
+        let result = async {
+            let size = attachment.file.size as u64;
+            inbox::check(&self.attachments, &attachment.name, &attachment.mime_type, size)?;
+            let file = self.bot.get_file(attachment.file.id.clone()).await.map_err(|e| e.to_string())?;
+            let mut bytes = Vec::new();
+            self.bot.download_file(&file.path, &mut bytes).await.map_err(|e| e.to_string())?;
+            inbox::save("telegram", chat_id, &attachment.name, &bytes).await
+        };
+        match result.await {
+            Ok(path) => Some(path),
+            Err(e) => {
+                tracing::warn!("Failed to download Telegram file {}: {}", attachment.name, e);
+                None
+            }
         }
+    }
 
-        info!("Received message from {} in chat {}: {}", sender_id, chat_id, content);
+    /// Handle a button press on an approval prompt
+    async fn handle_callback(&self, query: teloxide::types::CallbackQuery) {
+        let sender_id = query.from.id.0.to_string();
+        let chat_id = query.message.as_ref().map(|m| m.chat().id.0.to_string()).unwrap_or_default();
+        let data = query.data.clone().unwrap_or_default();
 
-        // Create and publish inbound message
-        let inbound = InboundMessage::new(
-            "telegram",
-            &sender_id,
-            &chat_id,
-            &content,
-        );
+        // Stop the button's loading spinner
+        let _ = self.bot.answer_callback_query(query.id).await;
 
-        self.bus.publish_inbound(inbound).await;
+        if !self.is_allowed(&sender_id, &chat_id) {
+            return;
+        }
+        if let Some(answer) = approval::button_answer("telegram", &sender_id, &chat_id, &data) {
+            self.bus.publish_inbound(answer).await;
+        }
     }
 
     /// Start the polling loop
@@ -102,8 +173,47 @@ impl TelegramChannel {
     }
 }
 
-/// Start the Telegram bot
-pub async fn start_telegram_bot(config: &Config, bus: &MessageBus) -> anyhow::Result<TelegramChannel> {
+/// A file attached to a message
+struct Attachment {
+    file: FileMeta,
+    name: String,
+    mime_type: String,
+}
+
+/// The photo, voice note, audio file, video or document attached to a message
+fn attachment(msg: &teloxide::types::Message) -> Option<Attachment> {
+    let attachment = |file: &FileMeta, name: String, mime_type: String| Attachment { file: file.clone(), name, mime_type };
+
+    if let Some(sizes) = msg.photo() {
+        // Sizes are listed smallest first
+        return Some(attachment(&sizes.last()?.file, "photo.jpg".to_string(), "image/jpeg".to_string()));
+    }
+    if let Some(voice) = msg.voice() {
+        // Voice notes are always ogg/opus
+        return Some(attachment(&voice.file, "voice.ogg".to_string(), "audio/ogg".to_string()));
+    }
+    if let Some(audio) = msg.audio() {
+        let mime_type = audio.mime_type.as_ref().map(ToString::to_string).unwrap_or_default();
+        let name = audio.file_name.clone().unwrap_or_else(|| {
+            format!("audio.{}", voice::audio_extension(&mime_type).unwrap_or("mp3"))
+        });
+        return Some(attachment(&audio.file, name, mime_type));
+    }
+    if let Some(video) = msg.video() {
+        let name = video.file_name.clone().unwrap_or_else(|| "video.mp4".to_string());
+        return Some(attachment(&video.file, name, video.mime_type.as_ref().map(ToString::to_string).unwrap_or_default()));
+    }
+    let document = msg.document()?;
+    let name = document.file_name.clone().unwrap_or_else(|| "document".to_string());
+    Some(attachment(&document.file, name, document.mime_type.as_ref().map(ToString::to_string).unwrap_or_default()))
+}
+
+/// Start the Telegram bot. Returns the polling and delivery tasks; abort them to stop it.
+pub async fn start_telegram_bot(
+    config: &Config,
+    bus: &MessageBus,
+    permissions: Permissions,
+) -> anyhow::Result<Vec<tokio::task::JoinHandle<()>>> {
     let token = &config.channels.telegram.token;
 
     if token.is_empty() {
@@ -113,31 +223,30 @@ pub async fn start_telegram_bot(config: &Config, bus: &MessageBus) -> anyhow::Re
 
     let bot = Bot::new(token);
 
-    // Get allowed users from config
-    let allowed_users: Vec<String> = config.channels.telegram.allowed_users
-        .iter()
-        .cloned()
-        .collect();
-
     let me = bot.get_me().await?;
     let username = me.username();
     let username_str = if username.is_empty() { "unknown" } else { username };
     info!("Logged in as @{}", username_str);
 
-    let channel = Arc::new(TelegramChannel::new(bot.clone(), bus.clone(), allowed_users.clone()));
-
     // Start polling in background
-    let mut channel_clone = (*channel).clone();
-    tokio::spawn(async move {
-        channel_clone.run().await;
+    let mut channel = TelegramChannel::new(
+        bot.clone(),
+        bus.clone(),
+        permissions,
+        create_transcriber(config),
+        config.channels.attachments.clone(),
+    );
+    let polling = tokio::spawn(async move {
+        channel.run().await;
+    });
+
+    // Deliver agent replies
+    let outbound_rx = bus.subscribe_outbound();
+    let delivery = tokio::spawn(async move {
+        handle_outbound_messages(bot, outbound_rx).await;
     });
 
-    Ok(TelegramChannel {
-        bot,
-        bus: bus.clone(),
-        allowed_users,
-        last_update_id: 0,
-    })
+    Ok(vec![polling, delivery])
 }
 
 /// Send a message through Telegram
@@ -147,3 +256,64 @@ pub async fn send_telegram_message(bot: &Bot, chat_id: i64, text: &str) -> anyho
 
     Ok(())
 }
+
+/// Send outbound messages addressed to Telegram
+async fn handle_outbound_messages(bot: Bot, mut rx: broadcast::Receiver<OutboundMessage>) {
+    while let Ok(msg) = rx.recv().await {
+        if msg.channel != "telegram" {
+            continue;
+        }
+        let Ok(chat_id) = msg.chat_id.parse::<i64>() else {
+            tracing::warn!("Invalid Telegram chat id: {}", msg.chat_id);
+            continue;
+        };
+
+        // Telegram rejects empty messages, as when only a file is sent
+        if !msg.content.is_empty() {
+            let request = bot.send_message(teloxide::types::ChatId(chat_id), &msg.content);
+            let result = match approval_keyboard(&msg) {
+                Some(keyboard) => request.reply_markup(keyboard).await,
+                None => request.await,
+            };
+            if let Err(e) = result {
+                tracing::error!("Failed to send Telegram message: {}", e);
+            }
+        }
+
+        let (files, mut notes) = inbox::uploadable(&msg.media, MAX_UPLOAD);
+        for path in &files {
+            if let Err(e) = send_file(&bot, teloxide::types::ChatId(chat_id), Path::new(path)).await {
+                tracing::error!("Failed to send {} to Telegram: {}", path, e);
+                notes.push(inbox::unsent_note(Path::new(path), "upload failed"));
+            }
+        }
+        if !notes.is_empty()
+            && let Err(e) = bot.send_message(teloxide::types::ChatId(chat_id), notes.join("\n")).await
+        {
+            tracing::error!("Failed to send Telegram message: {}", e);
+        }
+    }
+}
+
+/// Upload a file: ogg/opus as a voice note, other audio as a track, images
+/// small enough as photos, anything else as a document
+async fn send_file(bot: &Bot, chat_id: teloxide::types::ChatId, path: &Path) -> Result<(), teloxide::RequestError> {
+    let file = InputFile::file(path);
+    let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(u64::MAX);
+    match inbox::mime_type(path) {
+        "audio/ogg" => bot.send_voice(chat_id, file).await?,
+        mime_type if mime_type.starts_with("audio/") => bot.send_audio(chat_id, file).await?,
+        "image/png" | "image/jpeg" if size <= MAX_PHOTO => bot.send_photo(chat_id, file).await?,
+        _ => bot.send_document(chat_id, file).await?,
+    };
+    Ok(())
+}
+
+/// Approve/Deny buttons for approval prompts
+fn approval_keyboard(msg: &OutboundMessage) -> Option<InlineKeyboardMarkup> {
+    let id = msg.metadata.get(approval::APPROVAL_ID)?;
+    Some(InlineKeyboardMarkup::new([[
+        InlineKeyboardButton::callback("Approve", approval::button_data(id, true)),
+        InlineKeyboardButton::callback("Deny", approval::button_data(id, false)),
+    ]]))
+}
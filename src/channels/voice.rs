@@ -0,0 +1,84 @@
+//! Voice notes - audio attachments transcribed into message text.
+
+use crate::llm::providers::TranscriptionProvider;
+use crate::types::InboundMessage;
+use std::path::Path;
+
+/// Inbound metadata key marking a message transcribed from a voice note;
+/// the value is the saved audio file
+pub const VOICE_NOTE: &str = "voice_note";
+
+/// Transcribe a saved voice note into the message text, ahead of any caption
+/// sent with it. Returns whether it worked; failures are logged.
+pub async fn transcribe_into(inbound: &mut InboundMessage, transcriber: &dyn TranscriptionProvider, path: &Path) -> bool {
+    let transcript = match transcriber.transcribe(path).await {
+        Ok(transcript) if !transcript.is_empty() => transcript,
+        Ok(_) => {
+            tracing::warn!("Voice note {} has no speech", path.display());
+            return false;
+        }
+        Err(e) => {
+            tracing::warn!("Failed to transcribe {} with {}: {}", path.display(), transcriber.name(), e);
+            return false;
+        }
+    };
+    inbound.content = match inbound.content.trim() {
+        "" => transcript,
+        caption => format!("{}\n\n{}", transcript, caption),
+    };
+    inbound.metadata.insert(VOICE_NOTE.to_string(), path.display().to_string());
+    true
+}
+
+/// File extension for an audio mime type, such as `audio/ogg; codecs=opus`
+pub fn audio_extension(mime_type: &str) -> Option<&'static str> {
+    let essence = mime_type.split(';').next().unwrap_or_default().trim();
+    match essence {
+        "audio/ogg" | "audio/opus" => Some("ogg"),
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/mp4" | "audio/m4a" | "audio/x-m4a" | "audio/aac" => Some("m4a"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        "audio/webm" => Some("webm"),
+        "audio/flac" => Some("flac"),
+        "audio/amr" => Some("amr"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str);
+
+    #[async_trait::async_trait]
+    impl TranscriptionProvider for Fixed {
+        async fn transcribe(&self, _file_path: &Path) -> Result<String, String> {
+            Ok(self.0.to_string())
+        }
+
+        fn name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transcribe_into() {
+        let mut inbound = InboundMessage::new("telegram", "1", "2", "for the standup");
+        let path = Path::new("inbox/telegram/2/voice.ogg");
+        assert!(transcribe_into(&mut inbound, &Fixed("Ship the release today"), path).await);
+        assert_eq!(inbound.content, "Ship the release today\n\nfor the standup");
+        assert_eq!(inbound.metadata[VOICE_NOTE], "inbox/telegram/2/voice.ogg");
+
+        let mut silent = InboundMessage::new("telegram", "1", "2", "");
+        assert!(!transcribe_into(&mut silent, &Fixed(""), path).await);
+        assert!(!silent.metadata.contains_key(VOICE_NOTE));
+    }
+
+    #[test]
+    fn test_audio_extension() {
+        assert_eq!(audio_extension("audio/ogg; codecs=opus"), Some("ogg"));
+        assert_eq!(audio_extension("audio/mpeg"), Some("mp3"));
+        assert_eq!(audio_extension("image/png"), None);
+    }
+}
@@ -1,14 +1,24 @@
+use crate::channels::{inbox, voice};
 use crate::core::bus::MessageBus;
-use crate::config::Config;
-use crate::types::InboundMessage;
+use crate::config::{AttachmentsConfig, Config};
+use crate::core::permissions::Permissions;
+use crate::llm::providers::{create_transcriber, TranscriptionProvider};
+use crate::types::{InboundMessage, OutboundMessage};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message, WebSocketStream};
 use tracing::{info, warn};
 use futures_util::stream::StreamExt;
 use futures_util::sink::SinkExt;
 
+/// Largest media file WhatsApp delivers
+const MAX_UPLOAD: u64 = 16 * inbox::MB;
+
 /// WhatsApp bridge message format
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhatsAppBridgeMessage {
@@ -19,38 +29,69 @@ pub struct WhatsAppBridgeMessage {
     pub sender_name: Option<String>,
     pub chat_id: Option<String>,
     pub timestamp: Option<u64>,
+    /// Attached file, base64-encoded
+    #[serde(default)]
+    pub media: Option<String>,
+    #[serde(default)]
+    pub mimetype: Option<String>,
+    #[serde(default)]
+    pub file_name: Option<String>,
+}
+
+/// Save a file the bridge sent inline into the inbox, if its type and size are allowed
+async fn save_media(msg: &WhatsAppBridgeMessage, chat_id: &str, attachments: &AttachmentsConfig) -> Option<PathBuf> {
+    use base64::Engine;
+
+    let bytes = match base64::engine::general_purpose::STANDARD.decode(msg.media.as_deref()?) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Invalid WhatsApp media: {}", e);
+            return None;
+        }
+    };
+    let mimetype = msg.mimetype.as_deref().unwrap_or_default();
+    let name = msg.file_name.clone().unwrap_or_else(|| {
+        let extension = inbox::image_extension(mimetype).or(voice::audio_extension(mimetype));
+        format!("media.{}", extension.unwrap_or("bin"))
+    });
+    if let Err(e) = inbox::check(attachments, &name, mimetype, bytes.len() as u64) {
+        warn!("Skipping WhatsApp media: {}", e);
+        return None;
+    }
+    match inbox::save("whatsapp", chat_id, &name, &bytes).await {
+        Ok(path) => Some(path),
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    }
 }
 
 /// WhatsApp channel implementation using WebSocket bridge
-#[derive(Debug)]
 pub struct WhatsAppChannel {
     bus: MessageBus,
     bridge_url: String,
-    phone_number: Option<String>,
-    allowed_numbers: Vec<String>,
     ws_stream: Option<WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>>,
-    running: bool,
+    /// Roles of senders; unknown senders' media is not saved
+    permissions: Permissions,
+    /// Speech to text for voice messages; without one they are attached as files
+    transcriber: Option<Arc<dyn TranscriptionProvider>>,
+    /// Limits on the files saved from messages
+    attachments: AttachmentsConfig,
 }
 
 impl WhatsAppChannel {
-    pub fn new(config: &Config, bus: MessageBus) -> Self {
+    pub fn new(config: &Config, bus: MessageBus, permissions: Permissions) -> Self {
         Self {
             bus,
             bridge_url: config.channels.whatsapp.bridge_url.clone(),
-            phone_number: config.channels.whatsapp.phone_number.clone(),
-            allowed_numbers: config.channels.whatsapp.allowed_numbers
-                .iter()
-                .cloned()
-                .collect(),
             ws_stream: None,
-            running: false,
+            permissions,
+            transcriber: create_transcriber(config),
+            attachments: config.channels.attachments.clone(),
         }
     }
 
-    fn is_allowed(&self, sender: &str) -> bool {
-        self.allowed_numbers.is_empty() || self.allowed_numbers.contains(&sender.to_string())
-    }
-
     /// Connect to WhatsApp bridge
     pub async fn connect(&mut self) -> Result<()> {
         info!("Connecting to WhatsApp bridge at {}...", self.bridge_url);
@@ -60,53 +101,55 @@ impl WhatsAppChannel {
             .context("Failed to connect to WhatsApp bridge")?;
 
         self.ws_stream = Some(ws_stream);
-        self.running = true;
 
         info!("Connected to WhatsApp bridge");
         Ok(())
     }
 
-    /// Start listening for messages
-    pub async fn run(&mut self) {
-        if self.ws_stream.is_none() {
-            if let Err(e) = self.connect().await {
-                warn!("Failed to connect to WhatsApp bridge: {}", e);
-                return;
-            }
-        }
-
+    /// Receive bridge messages and send `outbound` ones addressed to WhatsApp,
+    /// reconnecting when the bridge drops. Runs until the task is aborted.
+    pub async fn run(mut self, mut outbound: broadcast::Receiver<OutboundMessage>) {
         info!("WhatsApp channel started");
 
-        while self.running {
-            if let Some(stream) = self.ws_stream.as_mut() {
-                tokio::select! {
-                    msg = stream.next() => {
-                        match msg {
-                            Some(Ok(Message::Text(text))) => {
+        loop {
+            let Some(stream) = self.ws_stream.as_mut() else {
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                if let Err(e) = self.connect().await {
+                    warn!("Reconnection failed: {}", e);
+                }
+                continue;
+            };
+            tokio::select! {
+                msg = stream.next() => {
+                    match msg {
+                        Some(Ok(Message::Text(text))) => {
+                            self.handle_message(&text).await;
+                        }
+                        Some(Ok(Message::Binary(data))) => {
+                            if let Ok(text) = String::from_utf8(data) {
                                 self.handle_message(&text).await;
                             }
-                            Some(Ok(Message::Binary(data))) => {
-                                if let Ok(text) = String::from_utf8(data) {
-                                    self.handle_message(&text).await;
-                                }
-                            }
-                            Some(Err(e)) => {
-                                warn!("WebSocket error: {}", e);
-                                // Try to reconnect
-                                if let Err(e) = self.connect().await {
-                                    warn!("Reconnection failed: {}", e);
-                                    tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                                }
-                            }
-                            None => {
-                                warn!("WebSocket stream ended");
-                                self.running = false;
-                            }
-                            _ => {}
                         }
+                        Some(Err(e)) => {
+                            warn!("WebSocket error: {}", e);
+                            self.ws_stream = None;
+                        }
+                        None => {
+                            warn!("WebSocket stream ended");
+                            self.ws_stream = None;
+                        }
+                        _ => {}
                     }
-                    _ = tokio::time::sleep(tokio::time::Duration::from_secs(30)) => {
-                        // Keep-alive ping
+                }
+                msg = outbound.recv() => {
+                    match msg {
+                        Ok(msg) if msg.channel == "whatsapp" => {
+                            if let Err(e) = self.deliver(&msg).await {
+                                warn!("Failed to send WhatsApp message: {}", e);
+                            }
+                        }
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
                     }
                 }
             }
@@ -125,12 +168,6 @@ impl WhatsAppChannel {
                 None => return,
             };
 
-            // Check if sender is allowed
-            if !self.is_allowed(&sender) {
-                warn!("Sender {} is not allowed", sender);
-                return;
-            }
-
             let chat_id = msg.chat_id.as_ref()
                 .unwrap_or(&sender)
                 .clone();
@@ -139,24 +176,84 @@ impl WhatsAppChannel {
                 .map(|s| s.clone())
                 .unwrap_or_default();
 
-            if content.is_empty() {
-                return;
-            }
-
-            info!("Received WhatsApp message from {}: {}", sender, content);
-
             // Create and publish inbound message
-            let inbound = InboundMessage::new(
+            let mut inbound = InboundMessage::new(
                 "whatsapp",
                 &sender,
                 &chat_id,
                 &content,
             );
 
+            // Access is checked by the gateway, which also handles pairing;
+            // unknown senders' media is not saved
+            let media = match self.permissions.is_allowed("whatsapp", &sender, &chat_id) {
+                true => save_media(&msg, &chat_id, &self.attachments).await,
+                false => None,
+            };
+            if let Some(path) = media {
+                // Audio is transcribed when possible, and attached otherwise
+                let mimetype = msg.mimetype.as_deref().unwrap_or_default();
+                let transcribed = match &self.transcriber {
+                    Some(transcriber) if voice::audio_extension(mimetype).is_some() => {
+                        voice::transcribe_into(&mut inbound, transcriber.as_ref(), &path).await
+                    }
+                    _ => false,
+                };
+                if !transcribed {
+                    inbound.media.push(path.display().to_string());
+                }
+            }
+
+            if inbound.content.is_empty() && inbound.media.is_empty() {
+                return;
+            }
+
+            info!("Received WhatsApp message from {}: {}", sender, inbound.content);
+
             self.bus.publish_inbound(inbound).await;
         }
     }
 
+    /// Send an outbound message's text, then its files, then notes on files not sent
+    async fn deliver(&mut self, msg: &OutboundMessage) -> Result<()> {
+        if !msg.content.is_empty() {
+            self.send_message(&msg.chat_id, &msg.content).await?;
+        }
+        let (files, mut notes) = inbox::uploadable(&msg.media, MAX_UPLOAD);
+        for path in &files {
+            if let Err(e) = self.send_media(&msg.chat_id, Path::new(path)).await {
+                warn!("Failed to send {} to WhatsApp: {}", path, e);
+                notes.push(inbox::unsent_note(Path::new(path), "upload failed"));
+            }
+        }
+        if !notes.is_empty() {
+            self.send_message(&msg.chat_id, &notes.join("\n")).await?;
+        }
+        Ok(())
+    }
+
+    /// Send a file through the bridge, base64-encoded like inbound media
+    pub async fn send_media(&mut self, chat_id: &str, path: &Path) -> Result<()> {
+        use base64::Engine;
+
+        let bytes = tokio::fs::read(path)
+            .await
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let message = serde_json::json!({
+            "type": "message",
+            "chat_id": chat_id,
+            "media": base64::engine::general_purpose::STANDARD.encode(bytes),
+            "mimetype": inbox::mime_type(path),
+            "file_name": path.file_name().and_then(|n| n.to_str()),
+        });
+
+        let stream = self.ws_stream.as_mut().context("Not connected to WhatsApp bridge")?;
+        stream.send(Message::Text(message.to_string()))
+            .await
+            .context("Failed to send media")?;
+        Ok(())
+    }
+
     /// Send a message through WhatsApp bridge
     pub async fn send_message(&mut self, chat_id: &str, content: &str) -> Result<()> {
         if self.ws_stream.is_none() {
@@ -177,24 +274,16 @@ impl WhatsAppChannel {
 
         Ok(())
     }
-
-    pub fn stop(&mut self) {
-        self.running = false;
-    }
-}
-
-/// WhatsApp outbound handler
-pub struct WhatsAppOutboundHandler {
-    channel: std::sync::Arc<std::sync::Mutex<WhatsAppChannel>>,
 }
 
-impl WhatsAppOutboundHandler {
-    pub fn new(channel: std::sync::Arc<std::sync::Mutex<WhatsAppChannel>>) -> Self {
-        Self { channel }
-    }
-
-    pub async fn handle_outbound(&self, chat_id: &str, content: &str) -> Result<()> {
-        let mut channel = self.channel.lock().unwrap();
-        channel.send_message(chat_id, content).await
-    }
+/// Connect to the WhatsApp bridge and start the channel. Returns its task; abort it to stop it.
+pub async fn start_whatsapp_channel(
+    config: &Config,
+    bus: &MessageBus,
+    permissions: Permissions,
+) -> Result<Vec<JoinHandle<()>>> {
+    let mut channel = WhatsAppChannel::new(config, bus.clone(), permissions);
+    channel.connect().await?;
+    let outbound = bus.subscribe_outbound();
+    Ok(vec![tokio::spawn(channel.run(outbound))])
 }
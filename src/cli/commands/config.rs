@@ -0,0 +1,27 @@
+//! Config commands - check the config file before starting the gateway.
+
+use crate::config::{self, validate};
+use anyhow::{bail, Result};
+
+/// Validate the config file and cron jobs, failing when there are errors
+pub fn validate() -> Result<()> {
+    let path = config::config_path();
+    println!("Validating {}", path.display());
+
+    let validation = validate::validate(&path);
+    for issue in &validation.issues {
+        println!("  {}", issue);
+    }
+
+    let errors = validation
+        .issues
+        .iter()
+        .filter(|i| i.severity == validate::Severity::Error)
+        .count();
+    let warnings = validation.issues.len() - errors;
+    if errors > 0 {
+        bail!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    println!("[+] Config is valid ({} warning(s))", warnings);
+    Ok(())
+}
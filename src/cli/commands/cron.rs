@@ -49,6 +49,7 @@ pub fn list(all: bool) -> Result<()> {
 }
 
 /// Add a new scheduled job
+#[allow(clippy::too_many_arguments)]
 pub fn add(
     name: &str,
     message: &str,
@@ -57,6 +58,7 @@ pub fn add(
     deliver: bool,
     to: Option<&str>,
     channel: Option<&str>,
+    model: Option<String>,
 ) -> Result<()> {
     let jobs_dir = get_cron_dir();
     let mut manager = JobManager::with_dir(jobs_dir);
@@ -67,6 +69,7 @@ pub fn add(
     job.deliver_response = deliver;
     job.deliver_to = to.map(|s| s.to_string());
     job.deliver_channel = channel.map(|s| s.to_string());
+    job.model = model;
 
     manager.add_job(&mut job);
 
@@ -1,14 +1,25 @@
 //! Gateway command - starts the main bot gateway.
 
 use crate::channels::discord::DiscordChannel;
+use crate::channels::{qq, telegram, whatsapp};
 use crate::channels::Channel;
-use crate::config::Config;
+use crate::config::{self, validate};
+use crate::core::agent::skills;
 use crate::core::agent::AgentExecutor;
+use crate::core::permissions::{Admission, Permissions};
+use crate::core::reload::{self, Change};
 use crate::core::scheduler::Scheduler;
+use crate::core::bus::http as bus_http;
 use crate::core::MessageBus;
 use crate::heartbeat::Heartbeat;
 use crate::llm::create_provider;
+use crate::llm::providers::create_transcriber;
+use crate::mcp;
+use crate::types::{Event, OutboundMessage};
 use anyhow::Result;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
 use tracing::info;
 
 pub const LOGO: &str = r#"
@@ -18,12 +29,89 @@ pub const LOGO: &str = r#"
        \/   \/
 "#;
 
+/// Channels the gateway can run
+const CHANNELS: [&str; 4] = ["discord", "telegram", "qq", "whatsapp"];
+
+/// Channels run by the gateway, kept so a config reload can restart them
+#[derive(Default)]
+struct Channels {
+    discord: Option<DiscordChannel>,
+    /// Tasks of the channels that run as spawned tasks, by channel name
+    tasks: HashMap<String, Vec<JoinHandle<()>>>,
+}
+
+impl Channels {
+    /// Start a channel if it is enabled in `config`
+    async fn start(&mut self, name: &str, config: &config::Config, bus: &MessageBus, permissions: &Permissions) {
+        match name {
+            "discord" if config.channels.discord.enabled && !config.channels.discord.token.is_empty() => {
+                info!("Initializing Discord channel...");
+                let mut channel = DiscordChannel::new(config.channels.discord.clone(), permissions.clone())
+                    .with_transcriber(create_transcriber(config))
+                    .with_attachments(config.channels.attachments.clone());
+                if let Err(e) = channel.start(bus).await {
+                    tracing::error!("Failed to start Discord channel: {}", e);
+                } else {
+                    println!("  [-] Discord: connected!");
+                    self.discord = Some(channel);
+                }
+            }
+            "telegram" if config.channels.telegram.enabled && !config.channels.telegram.token.is_empty() => {
+                let started = telegram::start_telegram_bot(config, bus, permissions.clone()).await;
+                self.spawned(name, "Telegram", started);
+            }
+            "qq" if config.channels.qq.enabled && !config.channels.qq.event_url.is_empty() => {
+                let started = qq::start_qq_channel(config, bus, permissions.clone()).await;
+                self.spawned(name, "QQ", started);
+            }
+            "whatsapp" if config.channels.whatsapp.enabled && !config.channels.whatsapp.bridge_url.is_empty() => {
+                let started = whatsapp::start_whatsapp_channel(config, bus, permissions.clone()).await;
+                self.spawned(name, "WhatsApp", started);
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep the tasks of a channel that started, or log why it did not
+    fn spawned(&mut self, name: &str, label: &str, started: Result<Vec<JoinHandle<()>>>) {
+        match started {
+            Ok(tasks) => {
+                println!("  [-] {}: connected!", label);
+                self.tasks.insert(name.to_string(), tasks);
+            }
+            Err(e) => tracing::error!("Failed to start {} channel: {}", label, e),
+        }
+    }
+
+    async fn stop(&mut self, name: &str) {
+        match name {
+            "discord" => {
+                if let Some(mut channel) = self.discord.take() {
+                    let _ = channel.stop().await;
+                }
+            }
+            _ => {
+                for task in self.tasks.remove(name).unwrap_or_default() {
+                    task.abort();
+                }
+            }
+        }
+    }
+}
+
 pub async fn execute(port: u16) -> Result<()> {
     println!("{}", LOGO);
     println!("Starting gateway on port {}...", port);
 
-    // Load configuration
-    let config = Config::load();
+    // Load configuration, refusing to start on errors
+    let validation = validate::validate(&config::config_path());
+    for issue in &validation.issues {
+        eprintln!("{}", issue);
+    }
+    if validation.has_errors() {
+        anyhow::bail!("Invalid configuration in {}; run `openat config-validate` for details", config::config_path().display());
+    }
+    let mut config = validation.config;
 
     // Create message bus for component communication
     let bus = MessageBus::new();
@@ -34,77 +122,170 @@ pub async fn execute(port: u16) -> Result<()> {
 
     // Create agent executor
     let provider = create_provider(&config);
-    let agent_executor = AgentExecutor::new(provider, &config, &bus);
+    let mut agent_executor = AgentExecutor::new(provider, &config, &bus);
+
+    // Connect MCP servers and expose their tools to the agent
+    let mcp_tools = mcp::connect_all(&config.tools.mcp_servers).await;
+    let mcp_tool_count = mcp_tools.len();
+    agent_executor.add_tools(mcp_tools);
+
+    // Load skills and pick up new or edited SKILL.md files while running
+    let skills_watcher = skills::spawn_watcher(agent_executor.skills(), Duration::from_secs(5));
+
+    // Local HTTP API for posting messages into the bus (used by `mcp-serve`)
+    let api_addr = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+    let api_task = match bus_http::load_or_create_token(&bus_http::token_path()) {
+        Ok(token) => match bus_http::serve(bus.clone(), api_addr, token).await {
+            Ok(task) => Some(task),
+            Err(e) => {
+                tracing::warn!("Gateway API not started on {}: {}", api_addr, e);
+                None
+            }
+        },
+        Err(e) => {
+            tracing::warn!("Gateway API not started, no token at {}: {}", bus_http::token_path().display(), e);
+            None
+        }
+    };
 
     // Create scheduler
     let scheduler = Scheduler::new(&bus);
 
-    // Initialize Discord channel if enabled
-    let mut discord_channel = None;
-    if config.channels.discord.enabled && !config.channels.discord.token.is_empty() {
-        info!("Initializing Discord channel...");
-        let channel = DiscordChannel::new(config.channels.discord.clone());
-        discord_channel = Some(channel);
-    }
+    // Reload the config when the file changes or on SIGHUP
+    let (config_watcher, mut reload_rx) =
+        reload::spawn_watcher(config::config_path(), Duration::from_secs(2), bus.clone());
 
     //println!("\n{}", LOGO);
     println!("Gateway components initialized:");
     println!("  [-] Heartbeat: running");
     println!("  [-] Agent Executor: ready");
     println!("  [-] Scheduler: ready");
-
-    if discord_channel.is_some() {
-        println!("  [-] Discord: starting...");
+    println!("  [-] Skills watcher: running");
+    println!("  [-] Config watcher: running");
+    if api_task.is_some() {
+        println!("  [-] API: http://{}", api_addr);
+    }
+    if !config.tools.mcp_servers.is_empty() {
+        println!("  [-] MCP: {} tools", mcp_tool_count);
     }
 
-    // Run components concurrently
-    let bus_for_agent = bus.clone();
-    let agent_task = tokio::spawn(async move {
-        let mut executor = agent_executor;
-        let mut inbound_rx = bus_for_agent.subscribe_inbound();
+    // Route inbound messages. Unknown senders are asked for a pairing code,
+    // and answers to approval prompts are handled here, since the agent is
+    // blocked waiting for them.
+    let approvals = agent_executor.approvals();
+    let permissions = agent_executor.permissions();
+    let gate_permissions = permissions.clone();
+    let gate_bus = bus.clone();
+    let (agent_tx, mut agent_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut inbound_rx = bus.subscribe_inbound();
+    let dispatch_task = tokio::spawn(async move {
         while let Ok(msg) = inbound_rx.recv().await {
-            tracing::info!("Processing message from {}", msg.channel);
-            if let Err(e) = executor.handle_message(&msg).await {
-                tracing::error!("Agent error: {}", e);
+            match gate_permissions.admit(&msg) {
+                Admission::Allowed => {}
+                Admission::Reply(content) => {
+                    gate_bus.publish_outbound(OutboundMessage::new(&msg.channel, &msg.chat_id, content)).await;
+                    continue;
+                }
+                Admission::Ignore => continue,
+            }
+            if approvals.try_answer(&msg).await {
+                continue;
+            }
+            if agent_tx.send(msg).is_err() {
+                break;
             }
         }
     });
 
-    let scheduler_task = tokio::spawn(async move {
+    // Run components concurrently. Provider changes are applied between messages.
+    let (provider_tx, mut provider_rx) = tokio::sync::mpsc::unbounded_channel::<config::Config>();
+    let mut agent_task = tokio::spawn(async move {
+        let mut executor = agent_executor;
+        loop {
+            tokio::select! {
+                Some(config) = provider_rx.recv() => {
+                    executor.reload(create_provider(&config), &config);
+                    info!("Agent now using model {}", config.agents.defaults.model);
+                }
+                msg = agent_rx.recv() => {
+                    let Some(msg) = msg else { break };
+                    tracing::info!("Processing message from {}", msg.channel);
+                    if let Err(e) = executor.handle_message(&msg).await {
+                        tracing::error!("Agent error: {}", e);
+                    }
+                }
+            }
+        }
+    });
+
+    let mut scheduler_task = tokio::spawn(async move {
         scheduler.run().await;
     });
 
-    // Start Discord channel if enabled
-    if let Some(ref mut channel) = discord_channel {
-        if let Err(e) = channel.start(&bus).await {
-            tracing::error!("Failed to start Discord channel: {}", e);
-        } else {
-            println!("  [-] Discord: connected!");
-        }
+    // Start the channels that are enabled
+    let mut channels = Channels::default();
+    for name in CHANNELS {
+        channels.start(name, &config, &bus, &permissions).await;
     }
 
     println!("\nGateway running. Press Ctrl+C to stop.");
     println!("Heartbeat: {}", heartbeat.uptime());
 
-    // Wait for shutdown signal
-    tokio::select! {
-        _ = tokio::signal::ctrl_c() => {
-            println!("\nShutting down gateway...");
-        }
-        _ = agent_task => {
-            println!("Agent task ended unexpectedly");
-        }
-        _ = scheduler_task => {
-            println!("Scheduler task ended unexpectedly");
+    // Apply config reloads until a shutdown signal
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                println!("\nShutting down gateway...");
+                break;
+            }
+            _ = &mut agent_task => {
+                println!("Agent task ended unexpectedly");
+                break;
+            }
+            _ = &mut scheduler_task => {
+                println!("Scheduler task ended unexpectedly");
+                break;
+            }
+            Some(new_config) = reload_rx.recv() => {
+                let changes = reload::diff(&config, &new_config);
+                if changes.is_empty() {
+                    continue;
+                }
+                for change in &changes {
+                    match change {
+                        Change::Provider => {
+                            let _ = provider_tx.send(new_config.clone());
+                        }
+                        Change::Permissions => permissions.update(&new_config),
+                        Change::Channel(name) => {
+                            channels.stop(name).await;
+                            channels.start(name, &new_config, &bus, &permissions).await;
+                        }
+                        Change::NeedsRestart(section) => {
+                            tracing::warn!("Config section {} changed; restart the gateway to apply it", section)
+                        }
+                    }
+                }
+                let changes: Vec<String> = changes.iter().map(ToString::to_string).collect();
+                info!("Config reloaded: {}", changes.join(", "));
+                bus.publish_event(Event::ConfigReload { changes }).await;
+                config = new_config;
+            }
         }
     }
 
     // Cleanup
     heartbeat.stop();
+    skills_watcher.abort();
+    config_watcher.abort();
+    dispatch_task.abort();
+    if let Some(task) = api_task {
+        task.abort();
+    }
 
-    // Stop Discord channel
-    if let Some(ref mut channel) = discord_channel {
-        let _ = channel.stop().await;
+    // Stop channels
+    for name in CHANNELS {
+        channels.stop(name).await;
     }
 
     println!("Gateway stopped.");
@@ -0,0 +1,42 @@
+//! MCP command - serve openat tools and memory to other agents over stdio.
+
+use crate::config::{self, Config};
+use crate::core::agent::skills::SkillManager;
+use crate::core::bus::http as bus_http;
+use crate::mcp::{McpServer, SendMessageTool};
+use crate::tools::cron_tool::CronTool;
+use crate::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
+use crate::tools::path_policy::PathPolicy;
+use crate::tools::sandbox::Sandbox;
+use crate::tools::shell::ShellTool;
+use crate::tools::ToolRegistry;
+use anyhow::Result;
+use std::sync::Arc;
+
+/// Run the MCP server on stdin/stdout until the client disconnects
+pub async fn serve(gateway_url: &str) -> Result<()> {
+    let config = Config::load();
+    let workspace = config::ensure_workspace_exists();
+    let paths = PathPolicy::new(&config.tools, workspace.clone()).with_protected(&config.secret_files);
+    let sandbox = Sandbox::new(&config.tools.exec, workspace.clone());
+    let token = bus_http::load_or_create_token(&bus_http::token_path())?;
+
+    let mut tools = ToolRegistry::new();
+    tools.register(Arc::new(ReadFileTool::new(paths.clone())));
+    tools.register(Arc::new(WriteFileTool::new(paths.clone())));
+    tools.register(Arc::new(EditFileTool::new(paths.clone())));
+    tools.register(Arc::new(ListDirTool::new(paths.clone())));
+    tools.register(Arc::new(ShellTool::new(sandbox.clone(), paths)));
+    tools.register(Arc::new(CronTool::new()));
+    tools.register(Arc::new(SendMessageTool::new(gateway_url, &token)));
+
+    let mut skills = SkillManager::new(&workspace);
+    skills.load_all().await;
+    skills.register_tools(&mut tools, &sandbox);
+
+    tracing::info!("Serving MCP on stdio ({} tools)", tools.definitions().len());
+    McpServer::new(tools, workspace)
+        .serve(tokio::io::stdin(), tokio::io::stdout())
+        .await?;
+    Ok(())
+}
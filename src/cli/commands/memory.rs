@@ -0,0 +1,84 @@
+//! Memory commands - inspect and prune long-term memory entries.
+
+use crate::config::{self, Config};
+use crate::core::agent::memory::MemoryManager;
+use anyhow::Result;
+
+fn manager() -> MemoryManager {
+    let config = Config::load();
+    let workspace = config::ensure_workspace_exists();
+    MemoryManager::with_config(&workspace, &config.agents.memory)
+}
+
+/// List all memory entries
+pub fn list() -> Result<()> {
+    println!("=== Memory Entries ===");
+    println!("{}", "=".repeat(50));
+
+    let memory = manager();
+    let entries = memory.store().load();
+
+    if entries.is_empty() {
+        println!("No memory entries.");
+        return Ok(());
+    }
+
+    for entry in &entries {
+        println!("\n[{}] {} ({})", entry.id, entry.created_at.format("%Y-%m-%d %H:%M"), entry.source);
+        if !entry.tags.is_empty() {
+            println!("  Tags: {}", entry.tags.join(", "));
+        }
+        for line in entry.content.lines() {
+            println!("  {}", line);
+        }
+    }
+
+    println!("\n{} entries in {}", entries.len(), memory.store().path().display());
+    Ok(())
+}
+
+/// Show the entries that would be retrieved for a query
+pub async fn search(query: &str, limit: usize) -> Result<()> {
+    let results = manager().retrieve(query, limit).await;
+
+    if results.is_empty() {
+        println!("No matching memories.");
+        return Ok(());
+    }
+
+    for result in results {
+        println!("[{}] score={:.3}", result.id, result.score);
+        for line in result.content.lines() {
+            println!("  {}", line);
+        }
+    }
+    Ok(())
+}
+
+/// Add a memory entry
+pub fn add(content: &str, tags: Vec<String>) -> Result<()> {
+    let entry = manager().store().add(content, tags, "cli")?;
+    println!("[+] Added memory: {}", entry.id);
+    Ok(())
+}
+
+/// Remove a memory entry by id
+pub fn remove(id: &str) -> Result<()> {
+    if manager().store().remove(id)? {
+        println!("[+] Removed memory: {}", id);
+    } else {
+        println!("[-] Memory not found (or id prefix ambiguous): {}", id);
+    }
+    Ok(())
+}
+
+/// Prune old memory entries
+pub fn prune(older_than_days: Option<u64>, keep: Option<usize>) -> Result<()> {
+    if older_than_days.is_none() && keep.is_none() {
+        anyhow::bail!("Specify --older-than-days and/or --keep");
+    }
+
+    let removed = manager().store().prune(older_than_days, keep)?;
+    println!("[+] Pruned {} memory entries", removed);
+    Ok(())
+}
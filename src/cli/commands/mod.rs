@@ -2,12 +2,29 @@
 
 pub mod agent;
 pub mod channel;
+pub mod config;
 pub mod cron;
 pub mod discord_test;
 pub mod gateway;
+pub mod mcp;
+pub mod memory;
+pub mod models;
+pub mod pair;
+pub mod skill;
 
 pub use agent::{execute as agent, interactive as agent_interactive};
 pub use channel::{login as channel_login, status as channel_status};
+pub use config::validate as config_validate;
 pub use cron::{add as cron_add, enable as cron_enable, list as cron_list, remove as cron_remove};
 pub use discord_test::execute as discord_test;
 pub use gateway::execute as gateway;
+pub use mcp::serve as mcp_serve;
+pub use memory::{
+    add as memory_add, list as memory_list, prune as memory_prune, remove as memory_remove,
+    search as memory_search,
+};
+pub use models::execute as models;
+pub use pair::execute as pair;
+pub use skill::{
+    enable as skill_enable, install as skill_install, list as skill_list, remove as skill_remove,
+};
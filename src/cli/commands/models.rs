@@ -0,0 +1,51 @@
+//! Models command - list the models the configured providers offer.
+
+use crate::config::Config;
+use crate::llm::providers::OllamaProvider;
+use crate::llm::LLMProvider;
+use anyhow::Result;
+
+/// Print the default model, aliases, custom provider models and local Ollama models
+pub async fn execute() -> Result<()> {
+    let config = Config::load();
+    let providers = &config.providers;
+
+    let model = &config.agents.defaults.model;
+    match providers.route(model) {
+        Some((provider, _)) => println!("Default: {} (via {})", model, provider),
+        None => println!("Default: {} (no configured provider serves it)", model),
+    }
+
+    if !providers.aliases.is_empty() {
+        println!("\nAliases:");
+        for (alias, target) in &providers.aliases {
+            println!("  {} -> {}", alias, target);
+        }
+    }
+
+    for (name, custom) in &providers.custom {
+        println!("\n{} ({}):", name, custom.api_base);
+        if custom.models.is_empty() {
+            println!("  Use `{}/<model>`", name);
+        }
+        for model in &custom.models {
+            println!("  {}", model);
+        }
+    }
+
+    if providers.is_configured("ollama") {
+        let ollama = OllamaProvider::new(&providers.ollama);
+        println!("\nOllama ({}):", ollama.api_base());
+        match ollama.list_models().await {
+            Ok(models) if models.is_empty() => println!("  No models pulled; try `ollama pull llama3.2`"),
+            Ok(models) => {
+                for model in models {
+                    println!("  ollama/{}  {:.1} GB", model.name, model.size as f64 / 1e9);
+                }
+            }
+            Err(e) => println!("  {}", e),
+        }
+    }
+
+    Ok(())
+}
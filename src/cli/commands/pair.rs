@@ -0,0 +1,29 @@
+//! Pair command - create a code that admits a new chat user.
+
+use crate::config::Config;
+use crate::core::permissions::Permissions;
+use anyhow::{bail, Result};
+
+const CHANNELS: &[&str] = &["telegram", "discord", "qq", "whatsapp"];
+
+/// Create a pairing code for `channel` granting `role`
+pub fn execute(channel: &str, role: &str, minutes: i64) -> Result<()> {
+    if !CHANNELS.contains(&channel) {
+        bail!("Unknown channel '{}'. Expected one of: {}", channel, CHANNELS.join(", "));
+    }
+    if minutes <= 0 {
+        bail!("--minutes must be positive");
+    }
+
+    let permissions = Permissions::new(&Config::load());
+    let code = permissions
+        .create_code(channel, role, chrono::Duration::minutes(minutes))
+        .map_err(anyhow::Error::msg)?;
+
+    println!("Pairing code: {}", code.code);
+    println!("  Channel: {}", code.channel);
+    println!("  Role: {}", code.role);
+    println!("  Expires: {}", code.expires_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"));
+    println!("\nAsk the new user to send this code to the bot on {}. It works once.", code.channel);
+    Ok(())
+}
@@ -0,0 +1,323 @@
+//! Skill commands - install and manage skill packages in `workspace/skills/`.
+//!
+//! A skill package is a directory (for example a git checkout) or a tarball
+//! containing `SKILL.md`, either at its root or inside a single top-level
+//! directory.
+
+use crate::config;
+use crate::core::agent::skills::{InstalledSkill, Skill, SkillManifest};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn skills_dir() -> PathBuf {
+    config::ensure_workspace_exists().join("skills")
+}
+
+/// Install a skill from a directory or tarball
+pub fn install(source: &str, force: bool) -> Result<()> {
+    let source_path = PathBuf::from(shellexpand::tilde(source).to_string());
+    if !source_path.exists() {
+        bail!("Skill source not found: {}", source_path.display());
+    }
+
+    // Keep the extraction directory alive until the copy is done
+    let extracted = if source_path.is_file() {
+        Some(extract_archive(&source_path)?)
+    } else {
+        None
+    };
+    let root = match &extracted {
+        Some(dir) => find_skill_root(dir.path())?,
+        None => find_skill_root(&source_path)?,
+    };
+
+    let dir = skills_dir();
+    let target = install_from_dir(&root, &dir, &source_path.display().to_string(), force)?;
+    let skill = Skill::load_checked(&target).map_err(anyhow::Error::msg)?;
+
+    println!(
+        "[+] Installed skill '{}' {} to {}",
+        skill.name,
+        skill.version.as_deref().unwrap_or("(unversioned)"),
+        target.display()
+    );
+    for tool in &skill.tools {
+        println!("  Tool: {}", tool.name);
+    }
+    Ok(())
+}
+
+/// Validate a skill directory and copy it into `skills_dir`, recording it in the manifest
+fn install_from_dir(root: &Path, skills_dir: &Path, source: &str, force: bool) -> Result<PathBuf> {
+    let skill = Skill::load_checked(root).map_err(|e| anyhow::anyhow!("Invalid skill: {}", e))?;
+
+    if !is_valid_dir_name(&skill.name) {
+        bail!("Invalid skill name '{}': use letters, digits, '-' and '_'", skill.name);
+    }
+    if !skill.is_available() && !force {
+        bail!(
+            "Skill '{}' requirements not met: missing {} (use --force to install anyway)",
+            skill.name,
+            skill.missing.join(", ")
+        );
+    }
+
+    let target = skills_dir.join(&skill.name);
+    if target.exists() {
+        if !force {
+            bail!("Skill '{}' is already installed (use --force to replace it)", skill.name);
+        }
+        fs::remove_dir_all(&target)
+            .with_context(|| format!("Failed to remove {}", target.display()))?;
+    }
+
+    fs::create_dir_all(skills_dir)?;
+    copy_dir(root, &target)?;
+
+    let mut manifest = SkillManifest::load(skills_dir);
+    manifest.skills.insert(skill.name.clone(), InstalledSkill {
+        version: skill.version.clone(),
+        source: source.to_string(),
+        installed_at: Utc::now(),
+        enabled: true,
+    });
+    manifest.save(skills_dir)?;
+
+    Ok(target)
+}
+
+/// Extract a tarball into a temporary directory using the system `tar`
+fn extract_archive(archive: &Path) -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    let status = std::process::Command::new("tar")
+        .arg("-xf")
+        .arg(archive)
+        .arg("-C")
+        .arg(dir.path())
+        .status()
+        .context("Failed to run tar")?;
+    if !status.success() {
+        bail!("Failed to extract {}", archive.display());
+    }
+    Ok(dir)
+}
+
+/// Locate the directory containing SKILL.md: the root itself or its only subdirectory
+fn find_skill_root(path: &Path) -> Result<PathBuf> {
+    if path.join("SKILL.md").is_file() {
+        return Ok(path.to_path_buf());
+    }
+
+    let subdirs: Vec<PathBuf> = fs::read_dir(path)?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .collect();
+    match subdirs.as_slice() {
+        [only] if only.join("SKILL.md").is_file() => Ok(only.clone()),
+        _ => bail!("No SKILL.md found in {}", path.display()),
+    }
+}
+
+fn is_valid_dir_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Recursively copy a directory, skipping `.git` and symlinks that leave the package
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == ".git" {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        let dest = to.join(&name);
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &dest)?;
+        } else if file_type.is_file() {
+            fs::copy(entry.path(), &dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// List installed skills
+pub fn list() -> Result<()> {
+    println!("=== Skills ===");
+    println!("{}", "=".repeat(50));
+
+    let dir = skills_dir();
+    let manifest = SkillManifest::load(&dir);
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir() && p.join("SKILL.md").exists())
+        .collect();
+    dirs.sort();
+
+    if dirs.is_empty() {
+        println!("No skills installed.");
+        return Ok(());
+    }
+
+    for path in dirs {
+        let dir_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let enabled = manifest.is_enabled(&dir_name);
+        match Skill::load_checked(&path) {
+            Ok(skill) => {
+                println!(
+                    "\n[{}] {} {}",
+                    if enabled { "X" } else { " " },
+                    skill.name,
+                    skill.version.as_deref().unwrap_or("")
+                );
+                if !skill.description.is_empty() {
+                    println!("  {}", skill.description);
+                }
+                if let Some(installed) = manifest.skills.get(&dir_name) {
+                    println!("  Source: {}", installed.source);
+                }
+                if !skill.tools.is_empty() {
+                    let tools: Vec<&str> = skill.tools.iter().map(|t| t.name.as_str()).collect();
+                    println!("  Tools: {}", tools.join(", "));
+                }
+                if !skill.is_available() {
+                    println!("  Unavailable: missing {}", skill.missing.join(", "));
+                }
+            }
+            Err(e) => println!("\n[!] {}: {}", dir_name, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Enable or disable an installed skill
+pub fn enable(name: &str, disable: bool) -> Result<()> {
+    set_enabled(&skills_dir(), name, !disable)?;
+    println!("[+] Skill '{}' {}", name, if disable { "disabled" } else { "enabled" });
+    Ok(())
+}
+
+fn set_enabled(dir: &Path, name: &str, enabled: bool) -> Result<()> {
+    if !is_valid_dir_name(name) {
+        bail!("Invalid skill name: {}", name);
+    }
+
+    let path = dir.join(name);
+    if !path.join("SKILL.md").exists() {
+        bail!("Skill not found: {}", name);
+    }
+
+    let mut manifest = SkillManifest::load(dir);
+    let entry = manifest.skills.entry(name.to_string()).or_insert_with(|| InstalledSkill {
+        version: Skill::load_checked(&path).ok().and_then(|s| s.version),
+        source: path.display().to_string(),
+        installed_at: Utc::now(),
+        enabled: true,
+    });
+    entry.enabled = enabled;
+    manifest.save(dir)?;
+    Ok(())
+}
+
+/// Remove an installed skill
+pub fn remove(name: &str) -> Result<()> {
+    if !is_valid_dir_name(name) {
+        bail!("Invalid skill name: {}", name);
+    }
+
+    let dir = skills_dir();
+    let path = dir.join(name);
+    if !path.exists() {
+        bail!("Skill not found: {}", name);
+    }
+    fs::remove_dir_all(&path)?;
+
+    let mut manifest = SkillManifest::load(&dir);
+    if manifest.skills.remove(name).is_some() {
+        manifest.save(&dir)?;
+    }
+
+    println!("[+] Removed skill: {}", name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_package(root: &Path, skill_md: &str) {
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join(".git").join("HEAD"), "ref").unwrap();
+        fs::write(root.join("SKILL.md"), skill_md).unwrap();
+        fs::write(root.join("run.sh"), "cat").unwrap();
+    }
+
+    #[test]
+    fn test_install_from_dir() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        write_package(src.path(), "---\nname: demo\nversion: 0.2.0\n---\nbody");
+
+        let target = install_from_dir(src.path(), dest.path(), "local", false).unwrap();
+        assert!(target.join("run.sh").exists());
+        assert!(!target.join(".git").exists());
+
+        let manifest = SkillManifest::load(dest.path());
+        assert_eq!(manifest.skills["demo"].version.as_deref(), Some("0.2.0"));
+
+        assert!(install_from_dir(src.path(), dest.path(), "local", false).is_err());
+        assert!(install_from_dir(src.path(), dest.path(), "local", true).is_ok());
+    }
+
+    #[test]
+    fn test_install_rejects_invalid() {
+        let src = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+
+        write_package(src.path(), "no frontmatter");
+        assert!(install_from_dir(src.path(), dest.path(), "local", false).is_err());
+
+        write_package(src.path(), "---\nname: ../escape\n---\nbody");
+        assert!(install_from_dir(src.path(), dest.path(), "local", false).is_err());
+
+        write_package(src.path(), "---\nname: needs\nrequires:\n  bins: [definitely-not-a-real-binary-xyz]\n---\nbody");
+        assert!(install_from_dir(src.path(), dest.path(), "local", false).is_err());
+    }
+
+    #[test]
+    fn test_enable_rejects_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let skills = dir.path().join("skills");
+        fs::create_dir_all(skills.join("demo")).unwrap();
+        fs::write(skills.join("demo").join("SKILL.md"), "---\nname: demo\n---\n").unwrap();
+        fs::create_dir_all(dir.path().join("x")).unwrap();
+        fs::write(dir.path().join("x").join("SKILL.md"), "---\nname: x\n---\n").unwrap();
+
+        let err = set_enabled(&skills, "../x", false).unwrap_err();
+        assert!(err.to_string().contains("Invalid skill name"), "{}", err);
+        assert!(!SkillManifest::load(&skills).skills.contains_key("../x"));
+
+        set_enabled(&skills, "demo", false).unwrap();
+        assert!(!SkillManifest::load(&skills).skills["demo"].enabled);
+    }
+
+    #[test]
+    fn test_find_skill_root_in_archive_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = dir.path().join("demo-1.0");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(inner.join("SKILL.md"), "---\nname: demo\n---\n").unwrap();
+        assert_eq!(find_skill_root(dir.path()).unwrap(), inner);
+    }
+}
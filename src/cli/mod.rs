@@ -6,12 +6,19 @@ pub use commands::{
     agent,
     agent_interactive,
     channel_login, channel_status,
+    config_validate,
     cron_add, cron_enable, cron_list, cron_remove,
     discord_test,
     gateway,
+    mcp_serve,
+    memory_add, memory_list, memory_prune, memory_remove, memory_search,
+    models,
+    pair,
+    skill_enable, skill_install, skill_list, skill_remove,
 };
 
 use crate::config::{self, Config};
+use crate::core::agent::skills::SkillManager;
 use anyhow::Result;
 use std::path::Path;
 
@@ -29,9 +36,15 @@ pub async fn onboard() -> Result<()> {
 
     let workspace = config::ensure_workspace_exists();
 
-    let config = Config::load();
-    config.save()?;
-    println!("[+] Created ~/.openat/config.json");
+    // An existing config is left alone: saving it would write out
+    // interpolated secrets and drop comments
+    let config_path = config::config_path();
+    if config_path.exists() {
+        println!("[+] Keeping existing {}", config_path.display());
+    } else {
+        Config::default().save()?;
+        println!("[+] Created {}", config_path.display());
+    }
 
     create_template(&workspace, "AGENTS.md", "# Agent Instructions\n\nYou are a helpful AI assistant.")?;
     create_template(&workspace, "SOUL.md", "# Soul\n\nI am openat, an AI assistant.")?;
@@ -59,22 +72,29 @@ fn create_template(path: &Path, name: &str, content: &str) -> Result<()> {
 }
 
 /// Show status
-pub fn status() -> Result<()> {
+pub async fn status() -> Result<()> {
     println!("{}", LOGO);
     println!("\nopenat Status");
     println!("==============");
 
     let config_path = config::config_path();
-    let _workspace = config::ensure_workspace_exists();
+    let workspace = config::ensure_workspace_exists();
 
     println!("\nConfig: {}", config_path.display());
 
     let config = Config::load();
-    println!("Model: {}", config.agents.defaults.model);
+    let model = &config.agents.defaults.model;
+    match config.providers.route(model) {
+        Some((provider, _)) => {
+            let api_base = crate::llm::create_provider(&config).api_base().to_string();
+            println!("Model: {} via {} ({})", model, provider, api_base);
+        }
+        None => println!("Model: {} (no configured provider serves it)", model),
+    }
 
-    let has_openrouter = !config.providers.openrouter.api_key.is_empty();
-    let has_anthropic = !config.providers.anthropic.api_key.is_empty();
-    let has_openai = !config.providers.openai.api_key.is_empty();
+    let has_openrouter = config.providers.api_key("openrouter").is_some();
+    let has_anthropic = config.providers.api_key("anthropic").is_some();
+    let has_openai = config.providers.api_key("openai").is_some();
 
     println!("\nAPI Keys:");
     println!("  OpenRouter: {}", if has_openrouter { "[+] Set" } else { "[-] Not set" });
@@ -93,5 +113,17 @@ pub fn status() -> Result<()> {
         "[-] Disabled"
     });
 
+    let mut skills = SkillManager::new(&workspace);
+    skills.load_all().await;
+    let unavailable = skills.get_unavailable();
+    println!("\nSkills: {} available, {} unavailable",
+        skills.get_always_load().len() + skills.get_optional().len(),
+        unavailable.len());
+    if !unavailable.is_empty() {
+        for skill in unavailable {
+            println!("  [-] {}: missing {}", skill.name, skill.missing.join(", "));
+        }
+    }
+
     Ok(())
 }
@@ -2,8 +2,15 @@
 //!
 //! All config structs use #[derive(Default)] and #[serde(default)]
 //! to reduce boilerplate and enable field-level defaults.
+//!
+//! The file is `~/.openat/config.{json,yaml,yml,toml}` or `$OPENAT_CONFIG`;
+//! see `source` for formats, `${VAR}` interpolation and `*_file` secrets.
+
+pub mod source;
+pub mod validate;
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
@@ -16,6 +23,42 @@ pub struct ProviderConfig {
     pub api_base: Option<String>,
 }
 
+/// Azure OpenAI, which serves models from named deployments of a resource
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AzureConfig {
+    /// Resource endpoint, such as `https://my-resource.openai.azure.com`
+    pub endpoint: String,
+    pub api_key: String,
+    /// Defaults to `2024-10-21`
+    pub api_version: String,
+    /// Deployment name for each model; other models are used as deployment names
+    pub deployments: BTreeMap<String, String>,
+}
+
+/// A local Ollama server, used once `api_base` is set (usually `http://localhost:11434`)
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct OllamaConfig {
+    pub api_base: Option<String>,
+    /// How long Ollama keeps a model loaded after a request, such as `"10m"` or `"-1"`
+    pub keep_alive: String,
+    /// Read replies as they are generated instead of in one response
+    pub stream: bool,
+}
+
+/// An OpenAI-compatible provider defined in the config, under `providers.custom.<name>`
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct CustomProviderConfig {
+    pub api_base: String,
+    pub api_key: String,
+    /// Extra HTTP headers sent with every request
+    pub headers: BTreeMap<String, String>,
+    /// Models served without a `<name>/` prefix
+    pub models: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct Providers {
@@ -29,6 +72,129 @@ pub struct Providers {
     pub zhipu: ProviderConfig,
     pub moonshot: ProviderConfig,
     pub vllm: ProviderConfig,
+    pub ollama: OllamaConfig,
+    pub azure: AzureConfig,
+    pub custom: BTreeMap<String, CustomProviderConfig>,
+    /// Short names for models, such as `"fast": "groq/llama-3.3-70b-versatile"`
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Providers {
+    /// Providers picked by API key when no model prefix chooses one, in priority order
+    pub const PRIORITY: [&'static str; 10] = [
+        "openrouter", "anthropic", "openai", "groq", "gemini", "minimax", "deepseek", "zhipu", "moonshot", "azure",
+    ];
+
+    /// Local servers, configured by `api_base` rather than an API key
+    pub const LOCAL: [&'static str; 2] = ["vllm", "ollama"];
+
+    pub fn get(&self, name: &str) -> Option<&ProviderConfig> {
+        Some(match name {
+            "openrouter" => &self.openrouter,
+            "anthropic" => &self.anthropic,
+            "openai" => &self.openai,
+            "groq" => &self.groq,
+            "gemini" => &self.gemini,
+            "minimax" => &self.minimax,
+            "deepseek" => &self.deepseek,
+            "zhipu" => &self.zhipu,
+            "moonshot" => &self.moonshot,
+            "vllm" => &self.vllm,
+            _ => return None,
+        })
+    }
+
+    /// Environment variable holding a provider's API key, such as `OPENROUTER_API_KEY`
+    pub fn api_key_env(name: &str) -> String {
+        format!("{}_API_KEY", name.to_uppercase())
+    }
+
+    /// API key for a provider: its environment variable, then the config
+    /// (which may itself come from `${VAR}` or `api_key_file`)
+    pub fn api_key(&self, name: &str) -> Option<String> {
+        std::env::var(Self::api_key_env(name))
+            .ok()
+            .filter(|k| !k.is_empty())
+            .or_else(|| {
+                let key = match name {
+                    "azure" => &self.azure.api_key,
+                    _ => &self.get(name)?.api_key,
+                };
+                Some(key.clone()).filter(|k| !k.is_empty())
+            })
+    }
+
+    /// Whether a provider can be used: custom providers and local servers need no key
+    pub fn is_configured(&self, name: &str) -> bool {
+        match name {
+            "vllm" => self.vllm.api_base.is_some(),
+            "ollama" => self.ollama.api_base.is_some(),
+            "azure" => !self.azure.endpoint.is_empty() && self.api_key(name).is_some(),
+            _ if self.custom.contains_key(name) => true,
+            _ => self.api_key(name).is_some(),
+        }
+    }
+
+    /// Built-in provider serving a model, guessed from its name
+    pub fn guess(model: &str) -> Option<&'static str> {
+        let model = model.to_lowercase();
+        let prefixes = [
+            ("claude", "anthropic"),
+            ("gpt-", "openai"),
+            ("o1", "openai"),
+            ("o3", "openai"),
+            ("o4", "openai"),
+            ("gemini", "gemini"),
+            ("deepseek", "deepseek"),
+            ("glm", "zhipu"),
+            ("moonshot", "moonshot"),
+            ("kimi", "moonshot"),
+            ("abab", "minimax"),
+            ("minimax", "minimax"),
+        ];
+        prefixes
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map(|(_, provider)| *provider)
+    }
+
+    /// Provider for a model string, and the model name to send it. In order:
+    ///
+    /// 1. An alias from `aliases` is replaced with its target.
+    /// 2. A `<provider>/` prefix naming a configured provider picks it, and is
+    ///    stripped: `deepseek/deepseek-chat`, `siliconflow/qwen2.5-72b`.
+    /// 3. A custom provider listing the model in `models`, or Azure when it
+    ///    has a deployment for it.
+    /// 4. The provider guessed from the model name, if configured.
+    /// 5. OpenRouter, which serves every model, with the model unchanged.
+    /// 6. For names nothing else recognizes, the first configured provider
+    ///    in `PRIORITY` order, then local servers, then custom providers.
+    pub fn route(&self, model: &str) -> Option<(String, String)> {
+        let model = self.aliases.get(model).map(String::as_str).unwrap_or(model);
+        if let Some((prefix, rest)) = model.split_once('/')
+            && self.is_configured(prefix)
+        {
+            return Some((prefix.to_string(), rest.to_string()));
+        }
+        if let Some((name, _)) = self.custom.iter().find(|(_, p)| p.models.iter().any(|m| m == model)) {
+            return Some((name.clone(), model.to_string()));
+        }
+        if self.azure.deployments.contains_key(model) && self.is_configured("azure") {
+            return Some(("azure".to_string(), model.to_string()));
+        }
+
+        let name = match Self::guess(model) {
+            Some(guessed) if self.is_configured(guessed) => guessed,
+            _ if self.is_configured("openrouter") => "openrouter",
+            Some(_) => return None,
+            None => Self::PRIORITY
+                .into_iter()
+                .chain(Self::LOCAL)
+                .chain(self.custom.keys().map(String::as_str))
+                .find(|name| self.is_configured(name))?,
+        };
+        Some((name.to_string(), model.to_string()))
+    }
 }
 
 // Agent configurations
@@ -39,12 +205,60 @@ pub struct AgentDefaults {
     pub model: String,
     pub max_tokens: usize,
     pub temperature: f64,
+    /// `reasoning_effort` for OpenAI and Azure reasoning models: `low`, `medium` or `high`
+    pub reasoning_effort: String,
+    /// Token budget for Anthropic extended thinking; 0 turns it off
+    pub thinking_budget: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingsConfig {
+    pub enabled: bool,
+    pub api_base: String,
+    pub api_key: String,
+    pub model: String,
+    /// Seconds an embedding request may take before retrieval falls back to BM25 only
+    pub timeout_secs: u64,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            api_base: String::new(),
+            api_key: String::new(),
+            model: String::new(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Number of memory entries retrieved into the prompt
+    pub top_k: usize,
+    /// Days of daily notes included in the search corpus
+    pub recent_days: usize,
+    pub embeddings: EmbeddingsConfig,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            recent_days: 3,
+            embeddings: EmbeddingsConfig::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct Agents {
     pub defaults: AgentDefaults,
+    pub memory: MemoryConfig,
 }
 
 // Tool configurations
@@ -62,12 +276,135 @@ pub struct ProxyConfig {
     pub enabled: bool,
 }
 
+/// An MCP server, launched over stdio (`command`) or reached over streamable HTTP (`url`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpServerConfig {
+    pub enabled: bool,
+    pub command: String,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    /// Only expose these tools (empty = all). Entries may end in `*`.
+    pub allow_tools: Vec<String>,
+    /// Never expose these tools. Entries may end in `*`.
+    pub deny_tools: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            command: String::new(),
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: String::new(),
+            headers: HashMap::new(),
+            allow_tools: Vec::new(),
+            deny_tools: Vec::new(),
+            timeout_secs: 30,
+        }
+    }
+}
+
+/// How shell commands are isolated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxMode {
+    /// Run directly, with rlimits only. The deny list guards against
+    /// mistakes; use `allowed_commands` or `bwrap` to actually confine commands
+    #[default]
+    None,
+    /// Run under bubblewrap: read-only root, writable workspace, fresh /tmp
+    Bwrap,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecConfig {
+    pub sandbox: SandboxMode,
+    /// Wall-clock timeout in seconds
+    pub timeout_secs: u64,
+    /// Maximum bytes kept from each of stdout and stderr
+    pub max_output_bytes: usize,
+    /// CPU time limit in seconds (0 = unlimited)
+    pub cpu_secs: u64,
+    /// Address space limit in MiB (0 = unlimited)
+    pub memory_mb: u64,
+    /// Process limit for the user running openat (0 = unlimited)
+    pub max_processes: u64,
+    /// When non-empty, only these programs may be run. Names are looked up on
+    /// PATH; an absolute path such as `/usr/bin/git` admits only that file
+    pub allowed_commands: Vec<String>,
+    /// Allow network access inside the bubblewrap sandbox
+    pub network: bool,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            sandbox: SandboxMode::None,
+            timeout_secs: 60,
+            max_output_bytes: 10000,
+            cpu_secs: 60,
+            memory_mb: 2048,
+            max_processes: 0,
+            allowed_commands: Vec::new(),
+            network: true,
+        }
+    }
+}
+
+/// What happens when the agent calls a tool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalMode {
+    /// Run without asking
+    #[default]
+    Allow,
+    /// Ask the requesting user in chat and wait for an answer
+    Ask,
+    /// Never run
+    Deny,
+}
+
+/// Human-in-the-loop approval for tool calls
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalConfig {
+    /// Mode per tool name; entries may end in `*`. Unlisted tools are allowed.
+    pub tools: BTreeMap<String, ApprovalMode>,
+    /// Per-role overrides of `tools`, keyed by `permissions` role name
+    pub roles: BTreeMap<String, BTreeMap<String, ApprovalMode>>,
+    /// Seconds to wait for an answer before the call is cancelled
+    pub timeout_secs: u64,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            tools: BTreeMap::new(),
+            roles: BTreeMap::new(),
+            timeout_secs: 300,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct Tools {
     pub web_search: WebSearch,
     pub proxy: ProxyConfig,
     pub restrict_to_workspace: bool,
+    /// Globs outside the workspace that file tools may still use when restricted
+    pub allow_paths: Vec<String>,
+    /// Globs that file tools may never use
+    pub deny_paths: Vec<String>,
+    pub exec: ExecConfig,
+    pub approval: ApprovalConfig,
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
 }
 
 // Channel configurations
@@ -77,6 +414,8 @@ pub struct Tools {
 pub struct Telegram {
     pub enabled: bool,
     pub token: String,
+    /// Legacy allowlist; prefer `permissions.users`. Listed users get the `member` role
+    /// and nobody else on this channel is admitted.
     pub allowed_users: Vec<String>,
 }
 
@@ -86,6 +425,8 @@ pub struct WhatsApp {
     pub enabled: bool,
     pub bridge_url: String,
     pub phone_number: Option<String>,
+    /// Legacy allowlist; prefer `permissions.users`. Listed numbers get the `member` role
+    /// and nobody else on this channel is admitted.
     pub allowed_numbers: Vec<String>,
 }
 
@@ -96,6 +437,8 @@ pub struct QQ {
     pub api_url: String,
     pub event_url: String,
     pub access_token: String,
+    /// Legacy allowlist; prefer `permissions.users`. Listed users get the `member` role
+    /// and nobody else on this channel is admitted.
     pub allowed_users: Vec<String>,
 }
 
@@ -104,6 +447,8 @@ pub struct QQ {
 pub struct Discord {
     pub enabled: bool,
     pub token: String,
+    /// Legacy allowlist; prefer `permissions.users`. Listed users get the `member` role
+    /// and nobody else on this channel is admitted.
     pub allowed_users: Vec<String>,
     pub gateway_url: String,
     pub intents: i32,
@@ -121,6 +466,30 @@ impl Discord {
     }
 }
 
+/// Files users send in chats, saved to `workspace/inbox/<channel>/<chat>/`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AttachmentsConfig {
+    /// Largest file saved, in megabytes (0 = no limit)
+    pub max_size_mb: u64,
+    /// Mime types saved, such as `application/pdf`; entries may end in `*`,
+    /// like `image/*`. Empty = all types.
+    pub allowed_types: Vec<String>,
+    /// Days files the agent sent, such as voice replies, are kept in the
+    /// outbox before they are deleted (0 = keep them)
+    pub outbox_retention_days: u64,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        Self {
+            max_size_mb: 20,
+            allowed_types: Vec::new(),
+            outbox_retention_days: 7,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct Channels {
@@ -128,6 +497,81 @@ pub struct Channels {
     pub whatsapp: WhatsApp,
     pub qq: QQ,
     pub discord: Discord,
+    pub attachments: AttachmentsConfig,
+}
+
+/// What members of a role may do
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct RoleConfig {
+    /// Tools the role may call; entries may end in `*`. Unset = all tools.
+    pub tools: Option<Vec<String>>,
+    /// Models the role may select with `/model`; entries may end in `*`. Unset = all models.
+    pub models: Option<Vec<String>>,
+    /// Whether the role may use slash commands such as `/model`
+    pub commands: bool,
+    /// Messages per minute (0 = unlimited)
+    pub messages_per_minute: u32,
+    /// Messages per hour (0 = unlimited)
+    pub messages_per_hour: u32,
+}
+
+/// Who may talk to the bot, and with which role
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct PermissionsConfig {
+    /// Roles by name. `admin`, `member` and `guest` are predefined and can be overridden.
+    pub roles: BTreeMap<String, RoleConfig>,
+    /// Role of each user, keyed by `channel:user_id`, or `*:user_id` for every channel
+    pub users: BTreeMap<String, String>,
+    /// Role of everyone in a group chat, keyed by `channel:chat_id`
+    pub groups: BTreeMap<String, String>,
+    /// Role of anyone not listed. Empty (the default) = unknown users are
+    /// asked for a pairing code. Configs that relied on the old `member`
+    /// default must set it explicitly to keep admitting everyone.
+    pub default_role: String,
+}
+
+// Audio configurations
+
+/// Speech to text for voice messages, through Groq, OpenAI or a local
+/// whisper server with an OpenAI-compatible `/audio/transcriptions` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TranscriptionConfig {
+    /// `groq` or `openai`; empty = Groq if it has a key, else OpenAI.
+    /// Ignored when `api_base` is set.
+    pub provider: String,
+    /// OpenAI-compatible API root, such as `http://localhost:8000/v1`
+    pub api_base: Option<String>,
+    /// Empty = the provider's key from `providers`
+    pub api_key: String,
+    /// Empty = `whisper-large-v3` on Groq, `whisper-1` elsewhere
+    pub model: String,
+}
+
+/// Text to speech for voice replies, through OpenAI or a local server with
+/// an OpenAI-compatible `/audio/speech` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct SpeechConfig {
+    /// OpenAI-compatible API root; unset = OpenAI, if it has a key
+    pub api_base: Option<String>,
+    /// Empty = the OpenAI key from `providers`
+    pub api_key: String,
+    /// Empty = `tts-1`
+    pub model: String,
+    /// Empty = `alloy`
+    pub voice: String,
+    /// `opus` (the default, played as a voice note), `mp3`, `aac`, `flac` or `wav`
+    pub format: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct AudioConfig {
+    pub transcription: TranscriptionConfig,
+    pub speech: SpeechConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -137,74 +581,79 @@ pub struct Config {
     pub agents: Agents,
     pub tools: Tools,
     pub channels: Channels,
+    pub permissions: PermissionsConfig,
+    pub audio: AudioConfig,
+    /// Files `*_file` keys read secrets from, so tools can be kept out of them
+    #[serde(skip)]
+    pub secret_files: Vec<PathBuf>,
 }
 
 impl Config {
+    /// Load the config file, falling back to defaults when it is missing or
+    /// invalid. Problems are logged; use `validate::validate` to act on them.
     pub fn load() -> Self {
         let path = config_path();
         tracing::debug!("Config path: {:?}", path);
-        tracing::debug!("Config exists: {}", path.exists());
-        if path.exists() {
-            if let Ok(content) = fs::read_to_string(&path) {
-                tracing::debug!("Config content length: {}", content.len());
-                match serde_json::from_str::<Config>(&content) {
-                    Ok(config) => {
-                        tracing::debug!("Config parsed successfully");
-                        return config;
-                    }
-                    Err(e) => {
-                        tracing::debug!("Config parse failed: {}", e);
-                        tracing::debug!("First 500 chars of config: {}", &content[..std::cmp::min(500, content.len())]);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!("No config file, using default config");
+                return Self::default();
+            }
+            Err(e) => {
+                tracing::error!("Failed to read {}: {}; using default config", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        let format = source::Format::from_path(&path);
+        match validate::parse(&content, format, &source::config_dir(&path)) {
+            Ok((config, issues)) => {
+                for issue in issues {
+                    match issue.severity {
+                        validate::Severity::Error => tracing::error!("{}: {}", path.display(), issue),
+                        validate::Severity::Warning => tracing::warn!("{}: {}", path.display(), issue),
                     }
                 }
-            } else {
-                tracing::debug!("Failed to read config file");
+                config
+            }
+            Err(e) => {
+                tracing::error!("{}: {}; using default config", path.display(), e);
+                Self::default()
             }
         }
-        tracing::debug!("Using default config");
-        Self::default()
     }
 
+    /// Write the config in the format of the config file
     pub fn save(&self) -> anyhow::Result<()> {
         let path = config_path();
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
-        let content = serde_json::to_string_pretty(self)?;
+        let content = source::Format::from_path(&path).serialize(self)?;
         fs::write(&path, content)?;
         Ok(())
     }
 
-    pub fn get_api_key(&self) -> Option<&str> {
-        if !self.providers.openrouter.api_key.is_empty() {
-            Some(&self.providers.openrouter.api_key)
-        } else if !self.providers.anthropic.api_key.is_empty() {
-            Some(&self.providers.anthropic.api_key)
-        } else if !self.providers.openai.api_key.is_empty() {
-            Some(&self.providers.openai.api_key)
-        } else if !self.providers.groq.api_key.is_empty() {
-            Some(&self.providers.groq.api_key)
-        } else if !self.providers.gemini.api_key.is_empty() {
-            Some(&self.providers.gemini.api_key)
-        } else if !self.providers.minimax.api_key.is_empty() {
-            Some(&self.providers.minimax.api_key)
-        } else if !self.providers.deepseek.api_key.is_empty() {
-            Some(&self.providers.deepseek.api_key)
-        } else if !self.providers.zhipu.api_key.is_empty() {
-            Some(&self.providers.zhipu.api_key)
-        } else if !self.providers.moonshot.api_key.is_empty() {
-            Some(&self.providers.moonshot.api_key)
-        } else {
-            None
-        }
+    /// Key of the first provider with one, in priority order
+    pub fn get_api_key(&self) -> Option<String> {
+        Providers::PRIORITY.iter().find_map(|name| self.providers.api_key(name))
     }
 }
 
+/// Config file names looked for in `~/.openat`, in order
+const CONFIG_FILES: [&str; 4] = ["config.json", "config.yaml", "config.yml", "config.toml"];
+
 pub(crate) fn config_path() -> PathBuf {
     if let Ok(path) = std::env::var("OPENAT_CONFIG") {
         PathBuf::from(path)
     } else if let Ok(home) = std::env::var("HOME") {
-        PathBuf::from(home).join(".openat/config.json")
+        let dir = PathBuf::from(home).join(".openat");
+        CONFIG_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .unwrap_or_else(|| dir.join(CONFIG_FILES[0]))
     } else {
         PathBuf::from("config.json")
     }
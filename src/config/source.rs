@@ -0,0 +1,261 @@
+//! Config sources - file formats, `${VAR}` interpolation and `*_file` secrets.
+//!
+//! The config file can be JSON, YAML or TOML, chosen by its extension. After
+//! parsing, values are resolved before they are deserialized:
+//!
+//! - In every string, `${NAME}` is replaced with the environment variable
+//!   `NAME`, and `${NAME:-fallback}` uses `fallback` when it is unset or empty.
+//!   `$${` is a literal `${`.
+//! - A key `<field>_file` next to a string field `<field>` sets that field to
+//!   the contents of the named file, trimmed. Relative paths are taken from
+//!   the config directory. This works for any field, which covers every
+//!   `api_key`, `token` and `access_token`. Tools may not read these files.
+//!
+//! ```yaml
+//! providers:
+//!   openrouter:
+//!     api_key_file: /run/secrets/openrouter
+//! channels:
+//!   telegram:
+//!     enabled: true
+//!     token: ${TELEGRAM_TOKEN}
+//! ```
+
+use super::{Config, CustomProviderConfig, McpServerConfig, RoleConfig};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// File format of a config file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    /// Format for a path, by extension. Anything unknown is JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::Yaml,
+            Some("toml") => Self::Toml,
+            _ => Self::Json,
+        }
+    }
+
+    pub fn serialize(self, config: &Config) -> anyhow::Result<String> {
+        Ok(match self {
+            Self::Json => serde_json::to_string_pretty(config)?,
+            Self::Yaml => serde_yaml::to_string(config)?,
+            Self::Toml => toml::to_string_pretty(config)?,
+        })
+    }
+}
+
+/// A syntax error and its 1-based line, when known
+#[derive(Debug)]
+pub struct SyntaxError {
+    pub message: String,
+    pub line: Option<usize>,
+}
+
+/// Parse a config file into a JSON value. An empty file is an empty object.
+pub fn parse_value(content: &str, format: Format) -> Result<Value, SyntaxError> {
+    // serde_json and serde_yaml append the position to their messages; it is reported separately
+    let strip = |message: String| message.split(" at line ").next().unwrap_or_default().to_string();
+    let value = match format {
+        Format::Json => serde_json::from_str(content).map_err(|e| SyntaxError {
+            line: Some(e.line()).filter(|l| *l > 0),
+            message: strip(e.to_string()),
+        })?,
+        Format::Yaml => serde_yaml::from_str(content).map_err(|e| SyntaxError {
+            line: e.location().map(|l| l.line()),
+            message: strip(e.to_string()),
+        })?,
+        Format::Toml => toml::from_str(content).map_err(|e| SyntaxError {
+            line: e.span().map(|s| content[..s.start].matches('\n').count() + 1),
+            message: e.message().to_string(),
+        })?,
+    };
+    Ok(match value {
+        Value::Null => Value::Object(Default::default()),
+        value => value,
+    })
+}
+
+/// Default values of every config field, used to tell fields from map keys
+pub fn schema() -> Value {
+    serde_json::to_value(Config::default()).unwrap_or_default()
+}
+
+/// Fields of the values of maps keyed by user-chosen names
+pub fn map_entry_schema(path: &str) -> Option<Value> {
+    match path {
+        "providers.custom" => serde_json::to_value(CustomProviderConfig::default()).ok(),
+        "tools.mcp_servers" => serde_json::to_value(McpServerConfig::default()).ok(),
+        "permissions.roles" => serde_json::to_value(RoleConfig::default()).ok(),
+        _ => None,
+    }
+}
+
+pub fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// What `resolve` found in a config value
+#[derive(Debug, Default)]
+pub struct Resolved {
+    /// Problems as `(path, message)`; failed values are left empty
+    pub problems: Vec<(String, String)>,
+    /// Secret files named by `*_file` keys, whether or not they could be read
+    pub secret_files: Vec<PathBuf>,
+}
+
+/// Interpolate environment variables and read `*_file` secrets in place.
+pub fn resolve(value: &mut Value, dir: &Path) -> Resolved {
+    let mut resolved = Resolved::default();
+    resolve_inner(value, &schema(), "", dir, &mut resolved);
+    resolved
+}
+
+fn resolve_inner(value: &mut Value, schema: &Value, path: &str, dir: &Path, resolved: &mut Resolved) {
+    match value {
+        Value::String(s) => match interpolate(s) {
+            Ok(interpolated) => *s = interpolated,
+            Err(e) => {
+                resolved.problems.push((path.to_string(), e));
+                s.clear();
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                resolve_inner(item, &Value::Null, path, dir, resolved);
+            }
+        }
+        Value::Object(map) => {
+            let fields = match schema {
+                Value::Object(fields) => fields.clone(),
+                _ => Default::default(),
+            };
+            let entry = if fields.is_empty() { map_entry_schema(path) } else { None };
+            for (key, item) in map.iter_mut() {
+                let child = fields.get(key).or(entry.as_ref()).cloned().unwrap_or_default();
+                resolve_inner(item, &child, &join(path, key), dir, resolved);
+            }
+
+            // `<field>_file` only counts where `<field>` is a string field
+            let secrets: Vec<String> = map
+                .keys()
+                .filter_map(|k| k.strip_suffix("_file"))
+                .filter(|field| fields.get(*field).is_some_and(Value::is_string))
+                .map(str::to_string)
+                .collect();
+            for field in secrets {
+                let key = format!("{}_file", field);
+                let Some(Value::String(file)) = map.remove(&key) else {
+                    continue;
+                };
+                let file = dir.join(shellexpand::tilde(&file).as_ref());
+                match std::fs::read_to_string(&file) {
+                    Ok(secret) => {
+                        map.insert(field, Value::String(secret.trim().to_string()));
+                    }
+                    Err(e) => resolved.problems.push((join(path, &key), format!("cannot read {}: {}", file.display(), e))),
+                }
+                resolved.secret_files.push(file);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace `${NAME}` and `${NAME:-fallback}` with environment variables
+fn interpolate(s: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or("unclosed `${`")? + start;
+        let expr = &rest[start + 2..end];
+        let (name, fallback) = match expr.split_once(":-") {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (expr, None),
+        };
+        match (std::env::var(name), fallback) {
+            (Ok(v), Some(fallback)) if v.is_empty() => out.push_str(fallback),
+            (Ok(v), _) => out.push_str(&v),
+            (Err(_), Some(fallback)) => out.push_str(fallback),
+            (Err(_), None) => return Err(format!("environment variable {} is not set", name)),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Directory that relative secret file paths are taken from
+pub fn config_dir(path: &Path) -> PathBuf {
+    path.parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats() {
+        let yaml = "channels:\n  telegram:\n    enabled: true\n    token: abc\n";
+        let toml = "[channels.telegram]\nenabled = true\ntoken = \"abc\"\n";
+        let json = r#"{"channels": {"telegram": {"enabled": true, "token": "abc"}}}"#;
+        for (content, format) in [(yaml, Format::Yaml), (toml, Format::Toml), (json, Format::Json)] {
+            let value = parse_value(content, format).unwrap();
+            let config: Config = serde_json::from_value(value).unwrap();
+            assert!(config.channels.telegram.enabled);
+            assert_eq!(config.channels.telegram.token, "abc");
+        }
+
+        let e = parse_value("a = 1\nb = = 2\n", Format::Toml).unwrap_err();
+        assert_eq!(e.line, Some(2));
+        assert!(parse_value("", Format::Yaml).unwrap().is_object());
+        assert_eq!(Format::from_path(Path::new("/etc/openat/config.yml")), Format::Yaml);
+    }
+
+    #[test]
+    fn test_interpolation_and_secret_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("token"), "secret-token\n").unwrap();
+        // SAFETY: only this test reads these variables
+        unsafe { std::env::set_var("OPENAT_TEST_MODEL", "deepseek-chat") };
+
+        let mut value = serde_json::json!({
+            "agents": { "defaults": { "model": "${OPENAT_TEST_MODEL}" } },
+            "channels": {
+                "telegram": { "token_file": "token" },
+                "discord": { "token": "${OPENAT_TEST_UNSET:-none} $${HOME}" },
+                "qq": { "access_token": "${OPENAT_TEST_UNSET}" }
+            },
+            "tools": { "mcp_servers": { "fs": { "env": { "cert_file": "x.pem" } } } }
+        });
+        let resolved = resolve(&mut value, dir.path());
+        assert_eq!(resolved.problems.len(), 1);
+        assert_eq!(resolved.problems[0].0, "channels.qq.access_token");
+        assert_eq!(resolved.secret_files, vec![dir.path().join("token")]);
+
+        let config: Config = serde_json::from_value(value).unwrap();
+        assert_eq!(config.agents.defaults.model, "deepseek-chat");
+        assert_eq!(config.channels.telegram.token, "secret-token");
+        assert_eq!(config.channels.discord.token, "none ${HOME}");
+        // Map keys are not fields, so `_file` is left alone there
+        assert_eq!(config.tools.mcp_servers["fs"].env["cert_file"], "x.pem");
+    }
+}
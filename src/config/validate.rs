@@ -0,0 +1,453 @@
+//! Config validation - report mistakes in the config instead of running on defaults.
+//!
+//! Validation runs in two passes:
+//!
+//! 1. `parse` reads the file (see `source` for formats, interpolation and
+//!    secret files) and deserializes it. Syntax and type errors are reported
+//!    with their key path and line, and keys that no config struct knows
+//!    are reported as warnings (serde would silently drop them).
+//! 2. `check` looks at what the values mean: enabled channels need their
+//!    connection settings, the default model's provider needs a key, and
+//!    role names must exist. `check_jobs` does the same for cron jobs.
+//!
+//! `Config::load` logs what it finds and carries on; the gateway and
+//! `openat config-validate` refuse to go on when there are errors.
+
+use super::source::{self, Format};
+use super::{Config, Providers};
+use crate::core::permissions::builtin_roles;
+use crate::core::scheduler::{self, JobManager, ScheduledJob};
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the config
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Dotted key path, such as `channels.telegram.token`
+    pub path: String,
+    /// 1-based line in the config file, when known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.into(),
+            line: None,
+            message: message.into(),
+        }
+    }
+
+    fn warning(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(path, message)
+        }
+    }
+
+    fn at_line(mut self, line: Option<usize>) -> Self {
+        self.line = line;
+        self
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error")?,
+            Severity::Warning => write!(f, "warning")?,
+        }
+        if !self.path.is_empty() {
+            write!(f, " at {}", self.path)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, " (line {})", line)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Result of validating a config file
+#[derive(Debug)]
+pub struct Validation {
+    /// The parsed config, or the defaults when the file could not be parsed
+    pub config: Config,
+    pub issues: Vec<Issue>,
+}
+
+impl Validation {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error)
+    }
+}
+
+/// Validate the config file at `path` and the stored cron jobs
+pub fn validate(path: &Path) -> Validation {
+    let mut issues = Vec::new();
+    let config = match fs::read_to_string(path) {
+        Ok(content) => match parse(&content, Format::from_path(path), &source::config_dir(path)) {
+            Ok((config, warnings)) => {
+                issues.extend(warnings);
+                config
+            }
+            Err(e) => {
+                return Validation {
+                    config: Config::default(),
+                    issues: vec![e],
+                };
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            issues.push(Issue::warning("", format!("{} not found, using defaults", path.display())));
+            Config::default()
+        }
+        Err(e) => {
+            return Validation {
+                config: Config::default(),
+                issues: vec![Issue::error("", format!("cannot read {}: {}", path.display(), e))],
+            };
+        }
+    };
+
+    issues.extend(check(&config));
+    issues.extend(check_jobs(&JobManager::new().load_jobs()));
+    Validation { config, issues }
+}
+
+/// Read a config in `format`, resolving secret files relative to `dir`.
+/// Fails with a syntax or type error; otherwise returns the config with
+/// interpolation errors and warnings for unknown keys.
+pub fn parse(content: &str, format: Format, dir: &Path) -> Result<(Config, Vec<Issue>), Issue> {
+    let mut value = source::parse_value(content, format)
+        .map_err(|e| Issue::error("", e.message).at_line(e.line))?;
+
+    let resolved = source::resolve(&mut value, dir);
+    let mut issues: Vec<Issue> = resolved
+        .problems
+        .into_iter()
+        .map(|(path, message)| {
+            let line = find_line(content, &path);
+            Issue::error(path, message).at_line(line)
+        })
+        .collect();
+
+    let mut config: Config = serde_path_to_error::deserialize(value.clone()).map_err(|e| {
+        let path = e.path().to_string();
+        let path = if path == "." { String::new() } else { path };
+        let line = find_line(content, &path);
+        Issue::error(path, e.into_inner().to_string()).at_line(line)
+    })?;
+
+    unknown_keys(&value, &source::schema(), "", content, &mut issues);
+    config.secret_files = resolved.secret_files;
+    Ok((config, issues))
+}
+
+fn unknown_keys(value: &Value, schema: &Value, path: &str, content: &str, issues: &mut Vec<Issue>) {
+    let (Value::Object(map), Value::Object(fields)) = (value, schema) else {
+        return;
+    };
+
+    // An empty schema object is a map with user-chosen keys
+    if fields.is_empty() {
+        if let Some(entry) = source::map_entry_schema(path) {
+            for (key, item) in map {
+                unknown_keys(item, &entry, &source::join(path, key), content, issues);
+            }
+        }
+        return;
+    }
+
+    for (key, item) in map {
+        let child = source::join(path, key);
+        match fields.get(key) {
+            Some(field) => unknown_keys(item, field, &child, content, issues),
+            None => {
+                let message = match closest(key, fields.keys()) {
+                    Some(similar) => format!("unknown key is ignored; did you mean `{}`?", similar),
+                    None => "unknown key is ignored".to_string(),
+                };
+                issues.push(Issue::warning(&child, message).at_line(find_line(content, &child)));
+            }
+        }
+    }
+}
+
+/// Line of the last key in `path`, searching for each key after the one before it.
+/// Works for JSON, YAML and TOML alike since it only looks for the key names.
+fn find_line(content: &str, path: &str) -> Option<usize> {
+    if path.is_empty() {
+        return None;
+    }
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+    let mut offset = 0;
+    for key in path.split('.') {
+        let found = content[offset..].match_indices(key).find(|(i, _)| {
+            let start = offset + i;
+            let end = start + key.len();
+            !content[..start].ends_with(is_word) && !content[end..].starts_with(is_word)
+        })?;
+        offset += found.0;
+    }
+    Some(content[..offset].matches('\n').count() + 1)
+}
+
+/// The known key closest to a misspelled one, if any is close enough
+fn closest<'a>(key: &str, known: impl Iterator<Item = &'a String>) -> Option<&'a str> {
+    known
+        .map(|k| (edit_distance(key, k), k))
+        .filter(|(d, k)| *d <= 2.max(k.len() / 4))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, k)| k.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Check the meaning of config values
+pub fn check(config: &Config) -> Vec<Issue> {
+    let mut issues = Vec::new();
+    check_channels(config, &mut issues);
+    check_model(config, &mut issues);
+    check_permissions(config, &mut issues);
+
+    let azure = &config.providers.azure;
+    if azure.endpoint.is_empty() && (config.providers.api_key("azure").is_some() || !azure.deployments.is_empty()) {
+        issues.push(Issue::error("providers.azure.endpoint", "is empty"));
+    }
+    for (name, provider) in &config.providers.custom {
+        if provider.api_base.is_empty() {
+            issues.push(Issue::error(format!("providers.custom.{}.api_base", name), "is empty"));
+        }
+    }
+
+    let transcription = &config.audio.transcription;
+    if transcription.api_base.is_none() && !["", "groq", "openai"].contains(&transcription.provider.as_str()) {
+        issues.push(Issue::error(
+            "audio.transcription.provider",
+            format!("unknown provider `{}`; use `groq`, `openai` or set `api_base`", transcription.provider),
+        ));
+    }
+    let format = config.audio.speech.format.as_str();
+    if !["", "opus", "mp3", "aac", "flac", "wav"].contains(&format) {
+        issues.push(Issue::error(
+            "audio.speech.format",
+            format!("unknown format `{}`; use `opus`, `mp3`, `aac`, `flac` or `wav`", format),
+        ));
+    }
+
+    for (name, server) in &config.tools.mcp_servers {
+        if server.enabled && server.command.is_empty() && server.url.is_empty() {
+            issues.push(Issue::error(format!("tools.mcp_servers.{}", name), "needs a `command` or a `url`"));
+        }
+    }
+    issues
+}
+
+fn check_channels(config: &Config, issues: &mut Vec<Issue>) {
+    let channels = &config.channels;
+    let required = [
+        ("telegram", channels.telegram.enabled, "token", channels.telegram.token.is_empty()),
+        ("discord", channels.discord.enabled, "token", channels.discord.token.is_empty()),
+        ("qq", channels.qq.enabled, "event_url", channels.qq.event_url.is_empty()),
+        ("whatsapp", channels.whatsapp.enabled, "bridge_url", channels.whatsapp.bridge_url.is_empty()),
+    ];
+    for (channel, enabled, field, missing) in required {
+        if enabled && missing {
+            issues.push(Issue::error(
+                format!("channels.{}.{}", channel, field),
+                format!("{} is enabled but `{}` is empty", channel, field),
+            ));
+        }
+    }
+    for mime_type in &channels.attachments.allowed_types {
+        if !mime_type.contains('/') && mime_type != "*" {
+            issues.push(Issue::error(
+                "channels.attachments.allowed_types",
+                format!("`{}` is not a mime type like `application/pdf` or `image/*`", mime_type),
+            ));
+        }
+    }
+}
+
+fn check_model(config: &Config, issues: &mut Vec<Issue>) {
+    let providers = &config.providers;
+    let configured = Providers::PRIORITY.into_iter().chain(Providers::LOCAL).any(|name| providers.is_configured(name));
+    if !configured && providers.custom.is_empty() {
+        issues.push(Issue::error(
+            "providers",
+            "no provider has an API key; set `providers.<name>.api_key` or e.g. OPENROUTER_API_KEY",
+        ));
+        return;
+    }
+
+    let models = std::iter::once(("agents.defaults.model".to_string(), &config.agents.defaults.model))
+        .chain(providers.aliases.iter().map(|(alias, model)| (format!("providers.aliases.{}", alias), model)));
+    for (path, model) in models {
+        if providers.route(model).is_some() {
+            continue;
+        }
+        let message = match Providers::guess(model) {
+            Some(needed) => format!(
+                "model `{}` needs a {} key; set `providers.{}.api_key` or {}",
+                model,
+                needed,
+                needed,
+                Providers::api_key_env(needed)
+            ),
+            None => format!("no configured provider serves model `{}`", model),
+        };
+        issues.push(Issue::error(path, message));
+    }
+}
+
+fn check_permissions(config: &Config, issues: &mut Vec<Issue>) {
+    let permissions = &config.permissions;
+    let builtin = builtin_roles();
+    let known = |role: &str| builtin.contains_key(role) || permissions.roles.contains_key(role);
+
+    if !permissions.default_role.is_empty() && !known(&permissions.default_role) {
+        issues.push(Issue::error(
+            "permissions.default_role",
+            format!("unknown role `{}`", permissions.default_role),
+        ));
+    }
+    for (section, assignments) in [("users", &permissions.users), ("groups", &permissions.groups)] {
+        for (key, role) in assignments {
+            if !known(role) {
+                issues.push(Issue::error(
+                    format!("permissions.{}.{}", section, key),
+                    format!("unknown role `{}`", role),
+                ));
+            }
+        }
+    }
+    for role in config.tools.approval.roles.keys() {
+        if !known(role) {
+            issues.push(Issue::warning(
+                format!("tools.approval.roles.{}", role),
+                format!("unknown role `{}`", role),
+            ));
+        }
+    }
+}
+
+/// Check that stored cron jobs can be scheduled
+pub fn check_jobs(jobs: &[ScheduledJob]) -> Vec<Issue> {
+    jobs.iter()
+        .filter_map(|job| {
+            let expr = job.cron_expression.as_ref()?;
+            let e = scheduler::check_cron(expr).err()?;
+            Some(Issue::error(format!("cron job `{}` ({})", job.name, job.id), e))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_json(content: &str) -> Result<(Config, Vec<Issue>), Issue> {
+        parse(content, Format::Json, Path::new("/"))
+    }
+
+    #[test]
+    fn test_syntax_and_type_errors() {
+        let e = parse_json("{\n  \"channels\": {\n    \"telegram\": { \"enabled\": true, }\n  }\n}").unwrap_err();
+        assert_eq!(e.severity, Severity::Error);
+        assert_eq!(e.line, Some(3));
+
+        let e = parse_json("{\n  \"channels\": {\n    \"telegram\": { \"enabled\": \"yes\" }\n  }\n}").unwrap_err();
+        assert_eq!(e.path, "channels.telegram.enabled");
+        assert_eq!(e.line, Some(3));
+        assert!(e.message.contains("expected a boolean"), "{}", e.message);
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let content = r#"{
+  "channels": { "telegram": { "tokn": "x" } },
+  "tools": { "mcp_servers": { "fs": { "command": "mcp-fs", "argz": [] } } },
+  "permissions": { "users": { "telegram:1": "admin" } }
+}"#;
+        let (_, issues) = parse_json(content).unwrap();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].path, "channels.telegram.tokn");
+        assert_eq!(issues[0].line, Some(2));
+        assert!(issues[0].message.contains("`token`"));
+        assert_eq!(issues[1].path, "tools.mcp_servers.fs.argz");
+        assert_eq!(issues[1].line, Some(3));
+    }
+
+    #[test]
+    fn test_semantic_checks() {
+        let mut config = Config::default();
+        config.channels.telegram.enabled = true;
+        config.providers.deepseek.api_key = "sk".to_string();
+        config.agents.defaults.model = "glm-4".to_string();
+        config.permissions.users.insert("qq:1".to_string(), "owner".to_string());
+
+        let issues = check(&config);
+        let paths: Vec<&str> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert!(paths.contains(&"channels.telegram.token"));
+        assert!(paths.contains(&"agents.defaults.model"));
+        assert!(paths.contains(&"permissions.users.qq:1"));
+
+        config.agents.defaults.model = "deepseek-chat".to_string();
+        config.channels.telegram.token = "t".to_string();
+        config.permissions.roles.insert("owner".to_string(), crate::config::RoleConfig::default());
+        assert!(check(&config).is_empty());
+
+        // A custom provider serves its prefixed models, but needs an api_base
+        config.providers.custom.insert("siliconflow".to_string(), crate::config::CustomProviderConfig::default());
+        config.agents.defaults.model = "siliconflow/qwen2.5-72b".to_string();
+        let issues = check(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "providers.custom.siliconflow.api_base");
+
+        config.providers.custom.clear();
+        config.agents.defaults.model = "deepseek-chat".to_string();
+        config.audio.transcription.provider = "whisper".to_string();
+        assert_eq!(check(&config)[0].path, "audio.transcription.provider");
+        config.audio.transcription.api_base = Some("http://localhost:8000/v1".to_string());
+        config.audio.speech.format = "ogg".to_string();
+        assert_eq!(check(&config)[0].path, "audio.speech.format");
+        config.audio.speech.format = "opus".to_string();
+        config.channels.attachments.allowed_types = vec!["image/*".to_string(), "pdf".to_string()];
+        assert_eq!(check(&config)[0].path, "channels.attachments.allowed_types");
+        config.channels.attachments.allowed_types = vec!["image/*".to_string(), "application/pdf".to_string()];
+        assert!(check(&config).is_empty());
+    }
+
+    #[test]
+    fn test_cron_jobs() {
+        let mut job = ScheduledJob::new("daily".to_string(), "hi".to_string());
+        job.cron_expression = Some("0 9 * * *".to_string());
+        assert!(check_jobs(std::slice::from_ref(&job)).is_empty());
+        job.cron_expression = Some("0 25 * * *".to_string());
+        assert_eq!(check_jobs(&[job]).len(), 1);
+    }
+}
@@ -31,8 +31,8 @@ impl ContextBuilder {
         }
     }
 
-    /// Build the complete system prompt
-    pub async fn build_system_prompt(&self, _skill_names: Option<Vec<String>>) -> String {
+    /// Build the complete system prompt, retrieving memories relevant to `query`
+    pub async fn build_system_prompt(&self, query: &str, _skill_names: Option<Vec<String>>) -> String {
         let mut parts = Vec::new();
 
         // Core identity
@@ -44,30 +44,15 @@ impl ContextBuilder {
         }
 
         // Memory context
-        let memory_context = self.memory.get_context();
+        let memory_context = self.memory.get_context(query).await;
         if !memory_context.is_empty() {
             parts.push(format!("# Memory\n\n{}", memory_context));
         }
 
-        // Skills - progressive loading
-        // Always-loaded skills: include full content
-        let always_content = self.skills.get_always_load();
-        if !always_content.is_empty() {
-            parts.push(format!("# Active Skills\n\n{}", always_content.join("\n\n")));
-        }
-
-        // Available skills: only show summary
-        let skills_summary = self.build_skills_summary();
-        if !skills_summary.is_empty() {
-            let summary = format!(
-                r#"# Skills
-
-The following skills extend your capabilities. To use a skill, read its SKILL.md file using the read_file tool.
-
-{}"#,
-                skills_summary
-            );
-            parts.push(summary);
+        // Skills - always-loaded skills in full, available skills as summaries
+        let skills_section = self.skills.build_prompt_section();
+        if !skills_section.is_empty() {
+            parts.push(skills_section);
         }
 
         parts.join("\n\n---\n\n")
@@ -86,7 +71,7 @@ The following skills extend your capabilities. To use a skill, read its SKILL.md
         let runtime = format!("{} {}", os, arch);
 
         let workspace_path = self.workspace.display().to_string();
-        let memory_path = format!("{}/memory/MEMORY.md", workspace_path);
+        let memory_path = format!("{}/memory/entries.jsonl", workspace_path);
         let daily_path = format!("{}/memory/YYYY-MM-DD.md", workspace_path);
         let skills_path = format!("{}/skills/{{skill-name}}/SKILL.md", workspace_path);
 
@@ -117,7 +102,7 @@ Only use the 'message' tool when you need to send a message to a specific chat c
 For normal conversation, just respond with text - do not call the message tool.
 
 Always be helpful, accurate, and concise. When using tools, explain what you're doing.
-When remembering something, write to {}"#,
+When remembering something, use the 'remember' tool (entries are stored in {})"#,
             date_str,
             runtime,
             workspace_path,
@@ -147,28 +132,6 @@ When remembering something, write to {}"#,
             Some(parts.join("\n\n"))
         }
     }
-
-    /// Build a summary of available skills
-    pub fn build_skills_summary(&self) -> String {
-        let optional_skills = self.skills.get_optional();
-
-        if optional_skills.is_empty() {
-            return String::new();
-        }
-
-        let summaries: Vec<String> = optional_skills
-            .iter()
-            .map(|s| {
-                format!(
-                    "- **{}**: {}",
-                    s.name,
-                    s.description
-                )
-            })
-            .collect();
-
-        summaries.join("\n")
-    }
 }
 
 impl Default for ContextBuilder {
@@ -1,19 +1,139 @@
 //! Agent Executor - Core agent logic with tool support and message history.
 
-use crate::config::Config;
+use crate::channels::{inbox, voice};
+use crate::config::{ApprovalMode, Config};
+use crate::core::agent::memory::MemoryManager;
+use crate::core::agent::skills::SkillManager;
 use crate::core::bus::MessageBus;
+use crate::core::permissions::{Permissions, Role};
 use crate::core::session::{Session, SessionManager};
+use crate::llm::providers::{create_speaker, ProviderError, SpeechProvider};
 use crate::llm::LLMProvider;
-use crate::types::{InboundMessage, LLMResponse, Message, OutboundMessage, ToolCall, ToolDefinition, ToolResult};
+use crate::tools::approval::{Approval, ApprovalBroker};
+use crate::tools::message::MessageTool;
+use crate::tools::path_policy::PathPolicy;
+use crate::tools::sandbox::Sandbox;
+use crate::tools::{Tool, ToolRegistry};
+use crate::types::{ContentPart, InboundMessage, LLMResponse, Message, OutboundMessage, ToolCall, ToolDefinition, ToolResult};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
+use tokio::sync::RwLock;
+
+/// Lifetime of codes created with `/invite`
+const INVITE_MINUTES: i64 = 15;
+
+/// Characters of the reasoning trace shown with `/think on`
+const THINKING_SUMMARY_CHARS: usize = 280;
+
+/// The reasoning trace collapsed to one short line
+fn reasoning_summary(reasoning: &str) -> String {
+    let line = reasoning.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut summary: String = line.chars().take(THINKING_SUMMARY_CHARS).collect();
+    if summary.len() < line.len() {
+        summary.push('…');
+    }
+    format!("(Thinking: {})", summary)
+}
+
+/// Messages for the LLM: the system prompt and skills, then the history.
+/// Retrieved memories change with every message, so they go before the
+/// current message rather than in the system prompt, keeping the prefix
+/// providers cache the same from turn to turn.
+fn message_history(
+    system_prompt: &str,
+    skills: &str,
+    history: &[HashMap<String, String>],
+    memories: &str,
+) -> Vec<Message> {
+    let mut messages = Vec::new();
+
+    if skills.is_empty() {
+        messages.push(Message::system(system_prompt));
+    } else {
+        messages.push(Message::system(&format!("{}\n\n{}", system_prompt, skills)));
+    }
+
+    for msg in history {
+        let role = match msg.get("role").map(|s| s.as_str()).unwrap_or("user") {
+            "system" => crate::types::MessageRole::System,
+            "assistant" => crate::types::MessageRole::Assistant,
+            "tool" => crate::types::MessageRole::Tool,
+            _ => crate::types::MessageRole::User,
+        };
+
+        let content = msg.get("content").map(|s| s.to_string()).unwrap_or_default();
+
+        let message = Message {
+            role,
+            content,
+            parts: vec![],
+            name: None,
+            tool_calls: vec![],
+            tool_call_id: None,
+        };
+        messages.push(message);
+    }
+
+    if !memories.is_empty()
+        && let Some(current) = messages.last_mut().filter(|m| m.role == crate::types::MessageRole::User)
+    {
+        current.content = format!("{}\n{}", memories, current.content);
+    }
+
+    messages
+}
+
+/// The tool definitions a sender's role may use
+fn role_tools(definitions: Vec<ToolDefinition>, role: &Role) -> Vec<ToolDefinition> {
+    definitions.into_iter().filter(|t| role.can_use_tool(&t.name)).collect()
+}
+
+/// Images among a message's attachments, inlined for vision models
+async fn image_parts(media: &[String]) -> Vec<ContentPart> {
+    use base64::Engine;
+
+    let mut parts = Vec::new();
+    for path in media {
+        let Some(mime_type) = inbox::image_mime(std::path::Path::new(path)) else {
+            continue;
+        };
+        match fs::read(path).await {
+            Ok(bytes) => parts.push(ContentPart::Image {
+                mime_type: mime_type.to_string(),
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            }),
+            Err(e) => tracing::warn!("Failed to read attachment {}: {}", path, e),
+        }
+    }
+    parts
+}
 
 /// Agent executor that handles message processing with tools and history.
 pub struct AgentExecutor {
     provider: Box<dyn LLMProvider>,
     session_manager: SessionManager,
+    memory: MemoryManager,
+    skills: Arc<RwLock<SkillManager>>,
+    /// Tools from MCP servers, connected once at startup
+    external_tools: Vec<Arc<dyn Tool>>,
+    /// External and skill tools, refreshed every turn
+    tools: ToolRegistry,
+    /// Runner for `exec` and skill tool commands
+    sandbox: Sandbox,
+    /// Which paths file tools and `exec` working directories may use
+    paths: PathPolicy,
+    /// Asks users to confirm tool calls that need approval
+    approvals: ApprovalBroker,
+    /// Roles of senders: tools, models, commands and rate limits
+    permissions: Permissions,
+    /// Text to speech for voice replies
+    speaker: Option<Arc<dyn SpeechProvider>>,
+    /// How long voice replies stay in the outbox (zero = forever)
+    outbox_retention: Duration,
     system_prompt: String,
     workspace: PathBuf,
     bus: MessageBus,
@@ -33,6 +153,16 @@ impl AgentExecutor {
         Self {
             provider,
             session_manager: SessionManager::new(sessions_dir),
+            memory: MemoryManager::with_config(&workspace, &config.agents.memory),
+            skills: Arc::new(RwLock::new(SkillManager::new(&workspace))),
+            external_tools: Vec::new(),
+            tools: ToolRegistry::new(),
+            sandbox: Sandbox::new(&config.tools.exec, workspace.clone()),
+            paths: PathPolicy::new(&config.tools, workspace.clone()).with_protected(&config.secret_files),
+            approvals: ApprovalBroker::new(config.tools.approval.clone(), bus.clone()),
+            permissions: Permissions::new(config),
+            speaker: create_speaker(config),
+            outbox_retention: Duration::from_secs(config.channels.attachments.outbox_retention_days * 24 * 3600),
             system_prompt,
             workspace,
             bus: bus.clone(),
@@ -41,6 +171,32 @@ impl AgentExecutor {
         }
     }
 
+    /// Shared skill manager, so the gateway can watch the skills directory.
+    pub fn skills(&self) -> Arc<RwLock<SkillManager>> {
+        self.skills.clone()
+    }
+
+    /// Approval broker, so the gateway can route answers to waiting tool calls.
+    pub fn approvals(&self) -> ApprovalBroker {
+        self.approvals.clone()
+    }
+
+    /// Roles of chat users, so the gateway can admit or pair senders.
+    pub fn permissions(&self) -> Permissions {
+        self.permissions.clone()
+    }
+
+    /// Use a reloaded config's provider and default model from the next message on.
+    pub fn reload(&mut self, provider: Box<dyn LLMProvider>, config: &Config) {
+        self.provider = provider;
+        self.model = config.agents.defaults.model.clone();
+    }
+
+    /// Add tools provided outside the executor, such as MCP servers.
+    pub fn add_tools(&mut self, tools: Vec<Arc<dyn Tool>>) {
+        self.external_tools.extend(tools);
+    }
+
     /// Build the system prompt for the agent.
     fn build_system_prompt(workspace: &PathBuf) -> String {
         let now = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
@@ -59,11 +215,12 @@ You have access to tools that you can use:
 - exec: Execute shell commands
 - web_search: Search the web for information
 - web_fetch: Fetch and extract text from a URL
+- remember: Save a fact to long-term memory
 
 ## Guidelines
 - Use tools when needed to accomplish tasks
 - Always explain what you're doing
-- Write important information to files for memory"#,
+- Use the remember tool for facts worth keeping across conversations"#,
             now,
             workspace.display()
         )
@@ -71,6 +228,13 @@ You have access to tools that you can use:
 
     /// Handle an inbound message and produce an outbound response.
     pub async fn handle_message(&mut self, msg: &InboundMessage) -> Result<OutboundMessage, String> {
+        let Some(role) = self.permissions.role(&msg.channel, &msg.sender_id, &msg.chat_id) else {
+            return Err(format!("No role for {} user {}", msg.channel, msg.sender_id));
+        };
+        if let Err(e) = self.permissions.check_rate(&msg.channel, &msg.sender_id, &role) {
+            return Ok(self.reply(msg, &e).await);
+        }
+
         let session_key = msg.session_key();
 
         // Load or create session
@@ -78,17 +242,58 @@ You have access to tools that you can use:
             Session::new(session_key)
         });
 
-        // Add user message to history
-        session.add_message("user", &msg.content);
+        // Slash commands are answered directly and kept out of the history
+        if let Some(reply) = self.handle_command(msg, &role, &mut session) {
+            self.session_manager.save(&session);
+            return Ok(self.reply(msg, &reply).await);
+        }
 
-        // Build message history for LLM
-        let messages = self.build_message_history(&session);
+        // Add user message to history, naming its attachments so file tools can open them
+        session.add_message("user", &inbox::describe(&msg.content, &msg.media));
+
+        // Build message history for LLM, with memories relevant to this message
+        let memory_context = self.memory.get_context(&msg.content).await;
+        let skills_context = {
+            let skills = self.skills.read().await;
+            self.tools = ToolRegistry::new();
+            // Messages and their attachments go to this chat unless the model picks another
+            let mut message = MessageTool::new(&self.bus, self.paths.clone());
+            message.set_context(msg.channel.clone(), msg.chat_id.clone());
+            self.tools.register(Arc::new(message));
+            for tool in &self.external_tools {
+                if !self.tools.register(tool.clone()) {
+                    tracing::warn!("Duplicate tool '{}' ignored", tool.name());
+                }
+            }
+            skills.register_tools(&mut self.tools, &self.sandbox);
+            skills.build_prompt_section()
+        };
+        let history = session.get_history(self.max_history_messages);
+        let mut messages = message_history(&self.system_prompt, &skills_context, &history, &memory_context);
+        // Images attached to this message are sent with it, but not kept in the history
+        if let Some(current) = messages.last_mut() {
+            current.parts = image_parts(&msg.media).await;
+        }
 
-        // Get tool definitions
-        let tools = self.get_tool_definitions();
+        let tools = role_tools(self.get_tool_definitions(), &role);
+        // A scheduled job's model, then the chat's `/model`, then the default
+        let model = msg
+            .metadata
+            .get("model")
+            .or_else(|| session.metadata.get("model"))
+            .cloned()
+            .unwrap_or_else(|| self.model.clone());
 
         // Execute chat with tool support
-        let response = self.chat_with_tools(&messages, &tools).await?;
+        let response = match self.chat_with_tools(msg, &role, &model, &messages, &tools).await {
+            Ok(response) => response,
+            // Tell the sender, and leave the blocked message out of the history
+            Err(blocked @ ProviderError::ContentFilter { .. }) => {
+                tracing::warn!("{}", blocked);
+                return Ok(self.reply(msg, &format!("{}.", blocked)).await);
+            }
+            Err(e) => return Err(e.to_string()),
+        };
 
         // Add assistant response to history
         let response_content = response.content.clone().unwrap_or_default();
@@ -97,49 +302,144 @@ You have access to tools that you can use:
         // Save session
         self.session_manager.save(&session);
 
-        // Publish response to bus
-        let outbound = OutboundMessage::new(&msg.channel, &msg.chat_id, &response_content);
+        // Publish response to bus, after a reasoning summary if `/think` is on
+        let thinking = session.metadata.get("think").is_some_and(|t| t == "on");
+        let content = match response.reasoning.as_deref().filter(|_| thinking) {
+            Some(reasoning) => format!("{}\n\n{}", reasoning_summary(reasoning), response_content),
+            None => response_content.clone(),
+        };
+        let mut outbound = OutboundMessage::new(&msg.channel, &msg.chat_id, &content);
+        // Voice notes, and chats with `/voice on`, are answered with audio as well
+        let voice = msg.metadata.contains_key(voice::VOICE_NOTE)
+            || session.metadata.get("voice").is_some_and(|v| v == "on");
+        if voice {
+            outbound.media.extend(self.speak(msg, &response_content).await);
+        }
         self.bus.publish_outbound(outbound.clone()).await;
-
         Ok(outbound)
     }
 
-    /// Build message history for the LLM.
-    fn build_message_history(&self, session: &Session) -> Vec<Message> {
-        let mut messages = Vec::new();
-
-        // Add system prompt
-        messages.push(Message::system(&self.system_prompt));
+    /// Synthesize a voice reply into the chat's outbox. Returns its path.
+    async fn speak(&self, msg: &InboundMessage, text: &str) -> Option<String> {
+        let speaker = self.speaker.as_ref()?;
+        if text.trim().is_empty() {
+            return None;
+        }
+        let saved = match speaker.synthesize(text).await {
+            Ok(audio) => {
+                let dir = inbox::outbox_dir(&msg.channel, &msg.chat_id);
+                inbox::save_in(&dir, &format!("reply.{}", speaker.extension()), &audio).await
+            }
+            Err(e) => Err(format!("{} speech failed: {}", speaker.name(), e)),
+        };
+        // Replies already delivered are cleared out as new ones are saved
+        if !self.outbox_retention.is_zero() {
+            inbox::prune(&inbox::outbox_root(), self.outbox_retention);
+        }
+        match saved {
+            Ok(path) => Some(path.display().to_string()),
+            Err(e) => {
+                // The text reply still goes out
+                tracing::warn!("No voice reply: {}", e);
+                None
+            }
+        }
+    }
 
-        // Get recent history
-        let history = session.get_history(self.max_history_messages);
+    /// Publish a reply to the chat a message came from.
+    async fn reply(&self, msg: &InboundMessage, content: &str) -> OutboundMessage {
+        let outbound = OutboundMessage::new(&msg.channel, &msg.chat_id, content);
+        self.bus.publish_outbound(outbound.clone()).await;
+        outbound
+    }
 
-        // Convert history to Message structs
-        for msg in history {
-            let role = match msg.get("role").map(|s| s.as_str()).unwrap_or("user") {
-                "system" => crate::types::MessageRole::System,
-                "assistant" => crate::types::MessageRole::Assistant,
-                "tool" => crate::types::MessageRole::Tool,
-                _ => crate::types::MessageRole::User,
-            };
-
-            let content = msg.get("content").map(|s| s.to_string()).unwrap_or_default();
-
-            let message = Message {
-                role,
-                content,
-                name: None,
-                tool_calls: vec![],
-                tool_call_id: None,
-            };
-            messages.push(message);
+    /// Handle a slash command. Returns the reply, or `None` if the message is not a command.
+    fn handle_command(&self, msg: &InboundMessage, role: &Role, session: &mut Session) -> Option<String> {
+        let mut parts = msg.content.split_whitespace();
+        let command = parts.next()?;
+        if !["/model", "/invite", "/think", "/voice"].contains(&command) {
+            return None;
+        }
+        if !role.can_use_commands() {
+            return Some("You don't have permission to use commands.".to_string());
         }
 
-        messages
+        let reply = match (command, parts.next()) {
+            ("/think", setting) => {
+                let on = match setting {
+                    Some("on") => true,
+                    Some("off") => false,
+                    Some(_) => return Some("Usage: /think [on|off]".to_string()),
+                    None => session.metadata.get("think").is_none_or(|t| t != "on"),
+                };
+                session.metadata.insert("think".to_string(), if on { "on" } else { "off" }.to_string());
+                if on {
+                    "Reasoning summaries are on for this chat.".to_string()
+                } else {
+                    "Reasoning summaries are off.".to_string()
+                }
+            }
+            ("/voice", setting) => {
+                let on = match setting {
+                    Some("on") => true,
+                    Some("off") => false,
+                    Some(_) => return Some("Usage: /voice [on|off]".to_string()),
+                    None => session.metadata.get("voice").is_none_or(|v| v != "on"),
+                };
+                session.metadata.insert("voice".to_string(), if on { "on" } else { "off" }.to_string());
+                match (on, &self.speaker) {
+                    (true, Some(_)) => "Voice replies are on for this chat.".to_string(),
+                    (true, None) => "Voice replies are on, but no speech provider is configured.".to_string(),
+                    (false, _) => "Voice replies are off; voice notes are still answered with audio.".to_string(),
+                }
+            }
+            ("/invite", requested) => {
+                let invited = requested.unwrap_or("member");
+                if invited == "admin" && role.name != "admin" {
+                    return Some("Only admins can invite admins.".to_string());
+                }
+                match self.permissions.create_code(&msg.channel, invited, chrono::Duration::minutes(INVITE_MINUTES)) {
+                    Ok(code) => format!(
+                        "Pairing code for a new {} on {}: `{}`\nIt expires in {} minutes and works once.",
+                        code.role, code.channel, code.code, INVITE_MINUTES
+                    ),
+                    Err(e) => e,
+                }
+            }
+            (_, None) => {
+                let current = session.metadata.get("model").unwrap_or(&self.model);
+                format!("Current model: `{}`", current)
+            }
+            (_, Some("default")) => {
+                session.metadata.remove("model");
+                format!("Model reset to `{}`.", self.model)
+            }
+            (_, Some(model)) if !role.can_use_model(model) => {
+                format!("Model `{}` is not available to the {} role.", model, role.name)
+            }
+            (_, Some(model)) => {
+                session.metadata.insert("model".to_string(), model.to_string());
+                format!("Model set to `{}` for this chat.", model)
+            }
+        };
+        Some(reply)
     }
 
-    /// Get tool definitions for the LLM.
+    /// Get tool definitions for the LLM: built-ins followed by MCP and skill tools.
     fn get_tool_definitions(&self) -> Vec<ToolDefinition> {
+        let mut definitions = Self::builtin_tool_definitions();
+        for definition in self.tools.definitions() {
+            if definitions.iter().any(|d| d.name == definition.name) {
+                tracing::warn!("Tool '{}' shadows a built-in tool; ignoring", definition.name);
+                continue;
+            }
+            definitions.push(definition);
+        }
+        definitions
+    }
+
+    /// Definitions of the tools handled directly by `execute_tool`.
+    fn builtin_tool_definitions() -> Vec<ToolDefinition> {
         vec![
             ToolDefinition::new(
                 "read_file",
@@ -196,6 +496,10 @@ You have access to tools that you can use:
                         "cmd": {
                             "type": "string",
                             "description": "The command to execute"
+                        },
+                        "working_dir": {
+                            "type": "string",
+                            "description": "Optional working directory, defaults to the workspace"
                         }
                     },
                     "required": ["cmd"]
@@ -229,37 +533,69 @@ You have access to tools that you can use:
                     "required": ["url"]
                 }),
             ),
+            ToolDefinition::new(
+                "remember",
+                "Save a short, self-contained fact to long-term memory.",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "content": {
+                            "type": "string",
+                            "description": "The fact to remember"
+                        }
+                    },
+                    "required": ["content"]
+                }),
+            ),
         ]
     }
 
     /// Chat with tool support.
     async fn chat_with_tools(
         &mut self,
+        msg: &InboundMessage,
+        role: &Role,
+        model: &str,
         messages: &[Message],
         tools: &[ToolDefinition],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         let mut messages_json: Vec<Value> = messages.iter().map(|m| m.to_json()).collect();
         let tool_defs_json: Vec<Value> = tools.iter().map(|t| t.to_json()).collect();
 
         let mut iterations = 0;
         let max_iterations = 30;
+        let mut reasoning = Vec::new();
 
         while iterations < max_iterations {
             iterations += 1;
 
             match self
                 .provider
-                .chat(&messages_json, &self.get_model(), &tool_defs_json)
+                .chat(&messages_json, model, &tool_defs_json)
                 .await
             {
-                Ok(response) => {
+                Ok(mut response) => {
+                    reasoning.extend(response.reasoning.take());
+                    if let Some(usage) = response.usage {
+                        tracing::debug!(
+                            "{} used {} prompt ({} cached, {} written to cache) and {} completion tokens",
+                            model,
+                            usage.prompt_tokens,
+                            usage.cache_read_tokens,
+                            usage.cache_write_tokens,
+                            usage.completion_tokens
+                        );
+                    }
                     if response.tool_calls.is_empty() {
+                        response.reasoning = Some(reasoning.join("\n\n")).filter(|r| !r.is_empty());
                         return Ok(response);
                     }
 
-                    // Add assistant message with tool calls
+                    // Add assistant message with tool calls. The reasoning trace is
+                    // left out: DeepSeek rejects `reasoning_content` in input messages.
+                    // Signed thinking blocks are kept, since Anthropic requires them.
                     let content = response.content.clone().unwrap_or_default();
-                    messages_json.push(json!({
+                    let mut assistant = json!({
                         "role": "assistant",
                         "content": content,
                         "tool_calls": response.tool_calls.iter().map(|tc| {
@@ -272,12 +608,34 @@ You have access to tools that you can use:
                                 }
                             })
                         }).collect::<Vec<_>>()
-                    }));
+                    });
+                    if !response.thinking_blocks.is_empty() {
+                        assistant["thinking_blocks"] = json!(response.thinking_blocks);
+                    }
+                    messages_json.push(assistant);
 
                     // Execute tools
                     for tool_call in &response.tool_calls {
-                        tracing::info!("Executing tool: {} with args: {}", tool_call.name, tool_call.arguments);
-                        let result = self.execute_tool(&tool_call.name, &tool_call.arguments).await;
+                        let mode = if role.can_use_tool(&tool_call.name) {
+                            self.approvals.mode(&role.name, &tool_call.name)
+                        } else {
+                            ApprovalMode::Deny
+                        };
+                        let result = match mode {
+                            ApprovalMode::Deny => format!("Error: Tool '{}' is not permitted", tool_call.name),
+                            mode => {
+                                if mode == ApprovalMode::Ask {
+                                    let approval = self.approvals.request(msg, &tool_call.name, &tool_call.arguments).await;
+                                    if approval != Approval::Approved {
+                                        let reason = if approval == Approval::Denied { "denied" } else { "not answered in time" };
+                                        let content = format!("Stopped: running `{}` was {}.", tool_call.name, reason);
+                                        return Ok(LLMResponse::new(Some(content), vec![], "stop"));
+                                    }
+                                }
+                                tracing::info!("Executing tool: {} with args: {}", tool_call.name, tool_call.arguments);
+                                self.execute_tool(&tool_call.name, &tool_call.arguments).await
+                            }
+                        };
                         tracing::info!("Tool result: {}", result);
                         messages_json.push(json!({
                             "role": "tool",
@@ -291,12 +649,7 @@ You have access to tools that you can use:
             }
         }
 
-        Err("Maximum iteration limit reached".to_string())
-    }
-
-    /// Get the model name from config.
-    fn get_model(&self) -> String {
-        self.model.clone()
+        Err("Maximum iteration limit reached".into())
     }
 
     /// Execute a tool.
@@ -324,14 +677,9 @@ You have access to tools that you can use:
         match name {
             "read_file" => {
                 if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
-                    // Expand ~ to home directory
-                    let expanded_path = if path.starts_with("~") {
-                        match std::env::var("HOME") {
-                            Ok(home) => path.replace("~", &home),
-                            Err(_) => path.to_string(),
-                        }
-                    } else {
-                        path.to_string()
+                    let expanded_path = match self.paths.check(path) {
+                        Ok(resolved) => resolved.display().to_string(),
+                        Err(e) => return format!("Error: {}", e),
                     };
                     match fs::read_to_string(&expanded_path).await {
                         Ok(content) => content,
@@ -346,14 +694,9 @@ You have access to tools that you can use:
                 let content = args.get("content").and_then(|v| v.as_str());
 
                 if let (Some(path), Some(content)) = (path, content) {
-                    // Expand ~ to home directory
-                    let expanded_path = if path.starts_with("~") {
-                        match std::env::var("HOME") {
-                            Ok(home) => path.replace("~", &home),
-                            Err(_) => path.to_string(),
-                        }
-                    } else {
-                        path.to_string()
+                    let expanded_path = match self.paths.check(path) {
+                        Ok(resolved) => resolved.display().to_string(),
+                        Err(e) => return format!("Error: {}", e),
                     };
                     if let Some(parent) = std::path::PathBuf::from(&expanded_path).parent() {
                         let _ = fs::create_dir_all(parent).await;
@@ -368,14 +711,9 @@ You have access to tools that you can use:
             }
             "list_dir" => {
                 if let Some(path) = args.get("path").and_then(|v| v.as_str()) {
-                    // Expand ~ to home directory
-                    let expanded_path = if path.starts_with("~") {
-                        match std::env::var("HOME") {
-                            Ok(home) => path.replace("~", &home),
-                            Err(_) => path.to_string(),
-                        }
-                    } else {
-                        path.to_string()
+                    let expanded_path = match self.paths.check(path) {
+                        Ok(resolved) => resolved.display().to_string(),
+                        Err(e) => return format!("Error: {}", e),
                     };
                     match fs::read_dir(&expanded_path).await {
                         Ok(mut entries) => {
@@ -397,23 +735,16 @@ You have access to tools that you can use:
             }
             "exec" => {
                 if let Some(cmd) = args.get("cmd").and_then(|v| v.as_str()) {
-                    match tokio::process::Command::new("sh")
-                        .arg("-c")
-                        .arg(cmd)
-                        .current_dir(&self.workspace)
-                        .output()
-                        .await
-                    {
-                        Ok(output) => {
-                            let stdout = String::from_utf8_lossy(&output.stdout);
-                            let stderr = String::from_utf8_lossy(&output.stderr);
-                            if !stderr.is_empty() {
-                                format!("stdout:\n{}\nstderr:\n{}", stdout, stderr)
-                            } else {
-                                stdout.to_string()
-                            }
-                        }
-                        Err(e) => format!("Error executing command: {}", e),
+                    let cwd = match args.get("working_dir").and_then(|v| v.as_str()) {
+                        Some(dir) => match self.paths.check_dir(dir) {
+                            Ok(dir) => dir,
+                            Err(e) => return format!("Error: {}", e),
+                        },
+                        None => self.workspace.clone(),
+                    };
+                    match self.sandbox.run(cmd, &cwd, None, &[]).await {
+                        Ok(output) => output.to_text(),
+                        Err(e) => e,
                     }
                 } else {
                     "Error: cmd parameter required".to_string()
@@ -435,7 +766,23 @@ You have access to tools that you can use:
                     "Error: url parameter required".to_string()
                 }
             }
-            _ => format!("Error: Unknown tool '{}'", name),
+            "remember" => {
+                if let Some(content) = args.get("content").and_then(|v| v.as_str()) {
+                    match self.memory.remember(content) {
+                        Ok(entry) => format!("Remembered (id: {})", entry.id),
+                        Err(e) => format!("Error saving memory: {}", e),
+                    }
+                } else {
+                    "Error: content parameter required".to_string()
+                }
+            }
+            _ => match self.tools.get(name) {
+                Some(tool) => {
+                    let args = serde_json::to_string(&args).unwrap_or_else(|_| "{}".to_string());
+                    tool.execute(&args).await.unwrap_or_else(|e| format!("Error: {}", e))
+                }
+                None => format!("Error: Unknown tool '{}'", name),
+            },
         }
     }
 }
@@ -451,3 +798,42 @@ impl From<ToolCall> for ToolResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memories_keep_system_prompt_stable() {
+        let mut session = Session::new("telegram:1".to_string());
+        session.add_message("user", "what's my cat called?");
+        let first = message_history("You are openat.", "## Skills", &session.get_history(20), "## Relevant Memories\n\n- Cat is Miso\n");
+
+        session.add_message("assistant", "Miso.");
+        session.add_message("user", "and my dog?");
+        let second = message_history("You are openat.", "## Skills", &session.get_history(20), "## Relevant Memories\n\n- Dog is Rex\n");
+
+        assert_eq!(first[0].content, second[0].content);
+        assert_eq!(first[0].content, "You are openat.\n\n## Skills");
+        assert_eq!(second.len(), 4);
+        assert_eq!(second[3].content, "## Relevant Memories\n\n- Dog is Rex\n\nand my dog?");
+        // Earlier turns are sent as they were said
+        assert_eq!(second[1].content, "what's my cat called?");
+    }
+
+    #[test]
+    fn test_guest_role_gets_no_tools() {
+        let mut config = Config::default();
+        config.permissions.users.insert("telegram:1".to_string(), "guest".to_string());
+        config.permissions.users.insert("telegram:2".to_string(), "member".to_string());
+        let permissions = Permissions::new(&config);
+        let tools = |user: &str| {
+            let role = permissions.role("telegram", user, "c").unwrap();
+            role_tools(AgentExecutor::builtin_tool_definitions(), &role)
+        };
+
+        // Providers leave `tools` out of the request when this is empty
+        assert!(tools("1").is_empty());
+        assert_eq!(tools("2").len(), AgentExecutor::builtin_tool_definitions().len());
+    }
+}
@@ -1,10 +1,19 @@
 //! Memory system - persistent long-term and short-term memory for the agent.
 //!
-//! - LongTermMemory: Persistent across sessions (MEMORY.md)
+//! - MemoryStore: Discrete long-term entries (memory/entries.jsonl)
+//! - LongTermMemory: Legacy free-form notes (MEMORY.md), imported into the store
 //! - DailyNotes: Session-specific, dated notes
+//!
+//! Only the entries most relevant to the current message are retrieved into
+//! the prompt, ranked with BM25 and optionally fused with embedding similarity.
 
+use crate::config::MemoryConfig;
+use crate::core::agent::retrieval::{cosine_similarity, fuse_rankings, Bm25Index, EmbeddingClient};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
+use std::io::Write;
 use std::path::PathBuf;
 
 /// Long-term memory (persistent across sessions)
@@ -95,21 +104,20 @@ impl DailyNotes {
         fs::write(&path, new_content)
     }
 
-    /// Read recent notes (last N days)
+    /// Read recent notes (today and the N-1 days before it), newest first
     pub fn read_recent(&self, days: usize) -> Vec<(DateTime<Utc>, String)> {
         let mut notes = Vec::new();
         let today = Utc::now().date_naive();
 
         for i in 0..days {
-            if let Some(date) = today.pred_opt() {
-                let path = self.memory_dir.join(format!("{}.md", date.format("%Y-%m-%d")));
-                if path.exists() {
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        let naive = date.and_hms_opt(0, 0, 0).unwrap_or_else(|| chrono::NaiveDateTime::MIN);
-                        let dt = DateTime::from_naive_utc_and_offset(naive, chrono::offset::Utc);
-                        notes.push((dt, content));
-                    }
-                }
+            let Some(date) = today.checked_sub_days(chrono::Days::new(i as u64)) else {
+                break;
+            };
+            let path = self.memory_dir.join(format!("{}.md", date.format("%Y-%m-%d")));
+            if let Ok(content) = fs::read_to_string(&path) {
+                let naive = date.and_hms_opt(0, 0, 0).unwrap_or(chrono::NaiveDateTime::MIN);
+                let dt = DateTime::from_naive_utc_and_offset(naive, chrono::offset::Utc);
+                notes.push((dt, content));
             }
         }
 
@@ -117,49 +125,347 @@ impl DailyNotes {
     }
 }
 
+/// A single long-term memory entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub id: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Where the entry came from (agent, cli, import)
+    #[serde(default)]
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+    /// Cached embedding vector and the model that produced it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_model: Option<String>,
+}
+
+impl MemoryEntry {
+    pub fn new(content: &str, source: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string()[..8].to_string(),
+            content: content.trim().to_string(),
+            tags: Vec::new(),
+            source: source.to_string(),
+            created_at: Utc::now(),
+            embedding: None,
+            embedding_model: None,
+        }
+    }
+}
+
+/// JSONL-backed store of memory entries
+#[derive(Debug, Clone)]
+pub struct MemoryStore {
+    path: PathBuf,
+    legacy: LongTermMemory,
+}
+
+impl MemoryStore {
+    pub fn new(workspace: &PathBuf) -> Self {
+        let path = workspace.join("memory").join("entries.jsonl");
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        Self {
+            path,
+            legacy: LongTermMemory::new(workspace),
+        }
+    }
+
+    /// Path of the entries file
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Load all entries, importing MEMORY.md on first use
+    pub fn load(&self) -> Vec<MemoryEntry> {
+        if !self.path.exists() {
+            let imported = self.import_legacy();
+            if let Err(e) = self.save(&imported) {
+                tracing::warn!("Failed to create memory store: {}", e);
+            }
+            return imported;
+        }
+
+        let content = match fs::read_to_string(&self.path) {
+            Ok(c) => c,
+            Err(e) => {
+                tracing::warn!("Failed to read memory store: {}", e);
+                return Vec::new();
+            }
+        };
+
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!("Skipping malformed memory entry: {}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Replace all entries on disk. The file is replaced in one rename, so
+    /// readers never see it half written; use `update` to change entries.
+    fn save(&self, entries: &[MemoryEntry]) -> std::io::Result<()> {
+        let temp = self.path.with_extension("jsonl.tmp");
+        let mut file = fs::File::create(&temp)?;
+        for entry in entries {
+            if let Ok(line) = serde_json::to_string(entry) {
+                writeln!(file, "{}", line)?;
+            }
+        }
+        file.sync_all()?;
+        fs::rename(&temp, &self.path)
+    }
+
+    /// Load, change and save the entries while holding the store's lock, so
+    /// changes from the gateway and the `memory` CLI are not lost to each other
+    pub fn update<T>(&self, change: impl FnOnce(&mut Vec<MemoryEntry>) -> T) -> std::io::Result<T> {
+        let lock = fs::File::create(self.path.with_extension("lock"))?;
+        // Released when `lock` is dropped
+        lock.lock()?;
+        let mut entries = self.load();
+        let result = change(&mut entries);
+        self.save(&entries)?;
+        Ok(result)
+    }
+
+    /// Add a new entry
+    pub fn add(&self, content: &str, tags: Vec<String>, source: &str) -> std::io::Result<MemoryEntry> {
+        let mut entry = MemoryEntry::new(content, source);
+        entry.tags = tags;
+        self.update(|entries| entries.push(entry.clone()))?;
+        Ok(entry)
+    }
+
+    /// Remove an entry by id (or unique id prefix)
+    pub fn remove(&self, id: &str) -> std::io::Result<bool> {
+        self.update(|entries| {
+            let matches: Vec<usize> = entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| e.id.starts_with(id))
+                .map(|(i, _)| i)
+                .collect();
+            if matches.len() != 1 {
+                return false;
+            }
+            entries.remove(matches[0]);
+            true
+        })
+    }
+
+    /// Remove entries older than `older_than_days` and/or keep only the newest `keep`.
+    /// Returns the number of removed entries.
+    pub fn prune(&self, older_than_days: Option<u64>, keep: Option<usize>) -> std::io::Result<usize> {
+        self.update(|entries| {
+            let before = entries.len();
+
+            if let Some(days) = older_than_days {
+                let cutoff = Utc::now() - chrono::Duration::days(days as i64);
+                entries.retain(|e| e.created_at >= cutoff);
+            }
+            if let Some(keep) = keep {
+                entries.sort_by_key(|e| e.created_at);
+                let excess = entries.len().saturating_sub(keep);
+                entries.drain(..excess);
+            }
+
+            before - entries.len()
+        })
+    }
+
+    /// Split MEMORY.md into entries, one per paragraph
+    fn import_legacy(&self) -> Vec<MemoryEntry> {
+        self.legacy
+            .read()
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .filter(|p| !p.lines().all(|l| l.starts_with('#')))
+            .map(|p| MemoryEntry::new(p, "import"))
+            .collect()
+    }
+}
+
+/// A retrieved memory with its relevance score
+#[derive(Debug, Clone)]
+pub struct RetrievedMemory {
+    pub id: String,
+    pub content: String,
+    pub score: f32,
+}
+
 /// Memory manager combining all memory types
 #[derive(Debug, Clone)]
 pub struct MemoryManager {
-    long_term: LongTermMemory,
+    store: MemoryStore,
     daily: DailyNotes,
+    embeddings: Option<EmbeddingClient>,
+    top_k: usize,
+    recent_days: usize,
 }
 
 impl MemoryManager {
     pub fn new(workspace: &PathBuf) -> Self {
+        Self::with_config(workspace, &MemoryConfig::default())
+    }
+
+    pub fn with_config(workspace: &PathBuf, config: &MemoryConfig) -> Self {
         Self {
-            long_term: LongTermMemory::new(workspace),
+            store: MemoryStore::new(workspace),
             daily: DailyNotes::new(workspace),
+            embeddings: EmbeddingClient::from_config(&config.embeddings),
+            top_k: config.top_k,
+            recent_days: config.recent_days,
+        }
+    }
+
+    /// Access the underlying entry store
+    pub fn store(&self) -> &MemoryStore {
+        &self.store
+    }
+
+    /// Get the memories relevant to `query` for the prompt
+    pub async fn get_context(&self, query: &str) -> String {
+        let memories = self.retrieve(query, self.top_k).await;
+        if memories.is_empty() {
+            return String::new();
+        }
+
+        let mut context = String::from("## Relevant Memories\n\n");
+        for memory in memories {
+            context.push_str(&format!("- {}\n", memory.content.replace('\n', "\n  ")));
         }
+        context
     }
 
-    /// Get memory for context
-    pub fn get_context(&self) -> String {
-        let mut context = String::new();
+    /// Rank entries and recent daily notes against `query`
+    pub async fn retrieve(&self, query: &str, limit: usize) -> Vec<RetrievedMemory> {
+        if limit == 0 || query.trim().is_empty() {
+            return Vec::new();
+        }
+
+        let mut entries = self.store.load();
+        let mut documents: HashMap<String, String> = HashMap::new();
+        let mut index = Bm25Index::new();
+
+        for entry in &entries {
+            let text = format!("{} {}", entry.content, entry.tags.join(" "));
+            index.add(&entry.id, &text);
+            documents.insert(entry.id.clone(), entry.content.clone());
+        }
 
-        // Add long-term memory
-        let long_term = self.long_term.read();
-        if !long_term.is_empty() {
-            context.push_str("## Long-term Memory\n\n");
-            context.push_str(&long_term);
-            context.push_str("\n\n");
+        for (date, note) in self.daily.read_recent(self.recent_days) {
+            let day = date.format("%Y-%m-%d");
+            for (i, paragraph) in note.split("\n\n").map(str::trim).enumerate() {
+                if paragraph.is_empty() || paragraph.starts_with("# Daily Notes") {
+                    continue;
+                }
+                let id = format!("note:{}#{}", day, i);
+                index.add(&id, paragraph);
+                documents.insert(id, format!("({}) {}", day, paragraph));
+            }
         }
 
-        // Add recent daily notes (last 3 days)
-        let recent_notes = self.daily.read_recent(3);
-        if !recent_notes.is_empty() {
-            context.push_str("## Recent Notes\n\n");
-            for (_, note) in recent_notes {
-                context.push_str(&note);
-                context.push_str("\n\n");
+        let mut rankings = vec![index.search(query, limit * 4)];
+
+        if let Some(client) = &self.embeddings {
+            match self.rank_by_embedding(client, &mut entries, query, limit * 4).await {
+                Ok(ranking) => rankings.push(ranking),
+                Err(e) => tracing::warn!("Embedding retrieval failed, using BM25 only: {}", e),
             }
         }
 
-        context
+        let ranked = if rankings.len() == 1 {
+            let mut only = rankings.remove(0);
+            only.truncate(limit);
+            only
+        } else {
+            fuse_rankings(&rankings, limit)
+        };
+
+        ranked
+            .into_iter()
+            .filter_map(|(id, score)| {
+                documents.get(&id).map(|content| RetrievedMemory {
+                    id,
+                    content: content.clone(),
+                    score,
+                })
+            })
+            .collect()
+    }
+
+    /// Rank entries by embedding similarity, filling in missing vectors
+    async fn rank_by_embedding(
+        &self,
+        client: &EmbeddingClient,
+        entries: &mut [MemoryEntry],
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(String, f32)>, String> {
+        let stale: Vec<usize> = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.embedding.is_none() || e.embedding_model.as_deref() != Some(client.model()))
+            .map(|(i, _)| i)
+            .collect();
+
+        if !stale.is_empty() {
+            let texts: Vec<String> = stale.iter().map(|&i| entries[i].content.clone()).collect();
+            let vectors = client.embed(&texts).await?;
+            let mut embedded: HashMap<String, Vec<f32>> = HashMap::new();
+            for (i, vector) in stale.into_iter().zip(vectors) {
+                entries[i].embedding = Some(vector.clone());
+                entries[i].embedding_model = Some(client.model().to_string());
+                embedded.insert(entries[i].id.clone(), vector);
+            }
+            // The store may have changed during the request, so only the new
+            // vectors are written into its current entries
+            let cached = self.store.update(|current| {
+                for entry in current.iter_mut() {
+                    if let Some(vector) = embedded.remove(&entry.id) {
+                        entry.embedding = Some(vector);
+                        entry.embedding_model = Some(client.model().to_string());
+                    }
+                }
+            });
+            if let Err(e) = cached {
+                tracing::warn!("Failed to cache memory embeddings: {}", e);
+            }
+        }
+
+        let query_vector = client
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        let mut scored: Vec<(String, f32)> = entries
+            .iter()
+            .filter_map(|e| {
+                e.embedding
+                    .as_ref()
+                    .map(|v| (e.id.clone(), cosine_similarity(&query_vector, v)))
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        Ok(scored)
     }
 
     /// Remember something important
-    pub fn remember(&self, content: &str) -> std::io::Result<()> {
-        self.long_term.append(content)
+    pub fn remember(&self, content: &str) -> std::io::Result<MemoryEntry> {
+        self.store.add(content, Vec::new(), "agent")
     }
 
     /// Take a note for today
@@ -167,3 +473,177 @@ impl MemoryManager {
         self.daily.append_today(content)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace() -> tempfile::TempDir {
+        tempfile::tempdir().unwrap()
+    }
+
+    #[test]
+    fn test_read_recent_walks_back() {
+        let dir = workspace();
+        let ws = dir.path().to_path_buf();
+        let notes = DailyNotes::new(&ws);
+        let today = Utc::now().date_naive();
+        for i in 0..3u64 {
+            let date = today.checked_sub_days(chrono::Days::new(i)).unwrap();
+            fs::write(
+                ws.join("memory").join(format!("{}.md", date.format("%Y-%m-%d"))),
+                format!("day {}", i),
+            )
+            .unwrap();
+        }
+
+        let recent = notes.read_recent(3);
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].1, "day 0");
+        assert_eq!(recent[2].1, "day 2");
+        assert_eq!(notes.read_recent(2).len(), 2);
+    }
+
+    #[test]
+    fn test_store_imports_legacy_memory() {
+        let dir = workspace();
+        let ws = dir.path().to_path_buf();
+        LongTermMemory::new(&ws)
+            .write("# Memory\n\nUser lives in Berlin.\n\nUser prefers tea.")
+            .unwrap();
+
+        let entries = MemoryStore::new(&ws).load();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].source, "import");
+        assert!(ws.join("memory/entries.jsonl").exists());
+    }
+
+    #[test]
+    fn test_store_add_remove_prune() {
+        let dir = workspace();
+        let store = MemoryStore::new(&dir.path().to_path_buf());
+        let a = store.add("first", vec![], "cli").unwrap();
+        store.add("second", vec![], "cli").unwrap();
+        store.add("third", vec![], "cli").unwrap();
+
+        assert!(store.remove(&a.id).unwrap());
+        assert!(!store.remove("does-not-exist").unwrap());
+        assert_eq!(store.load().len(), 2);
+
+        assert_eq!(store.prune(None, Some(1)).unwrap(), 1);
+        assert_eq!(store.load()[0].content, "third");
+    }
+
+    #[tokio::test]
+    async fn test_get_context_returns_top_k() {
+        let dir = workspace();
+        let ws = dir.path().to_path_buf();
+        let config = MemoryConfig { top_k: 1, ..MemoryConfig::default() };
+        let memory = MemoryManager::with_config(&ws, &config);
+        memory.remember("The user's favourite editor is Helix").unwrap();
+        memory.remember("Standup meeting is at 10am").unwrap();
+
+        let context = memory.get_context("which editor should I configure?").await;
+        assert!(context.contains("Helix"));
+        assert!(!context.contains("Standup"));
+        assert!(memory.get_context("").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slow_embeddings_fall_back_to_bm25() {
+        // An endpoint that accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+        });
+
+        let dir = workspace();
+        let ws = dir.path().to_path_buf();
+        let mut config = MemoryConfig { top_k: 1, ..MemoryConfig::default() };
+        config.embeddings = crate::config::EmbeddingsConfig {
+            enabled: true,
+            api_base: format!("http://{}", addr),
+            model: "text-embedding-3-small".to_string(),
+            timeout_secs: 1,
+            ..Default::default()
+        };
+        let memory = MemoryManager::with_config(&ws, &config);
+        memory.remember("The user's favourite editor is Helix").unwrap();
+        memory.remember("Standup meeting is at 10am").unwrap();
+
+        let started = std::time::Instant::now();
+        let context = memory.get_context("which editor should I configure?").await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(context.contains("Helix"));
+    }
+
+    #[test]
+    fn test_concurrent_adds_are_kept() {
+        let dir = workspace();
+        let store = MemoryStore::new(&dir.path().to_path_buf());
+        let writers: Vec<_> = (0..4)
+            .map(|w| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    for i in 0..20 {
+                        store.add(&format!("fact {} from {}", i, w), vec![], "cli").unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(store.load().len(), 80);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_merge_into_current_store() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = workspace();
+        let ws = dir.path().to_path_buf();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let store = MemoryStore::new(&ws);
+        tokio::spawn(async move {
+            let mut added = false;
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+                // Something is remembered while the entries are being embedded
+                if !added {
+                    store.add("Added during the request", vec![], "cli").unwrap();
+                    added = true;
+                }
+                let body = r#"{"data":[{"index":0,"embedding":[1.0,0.0]}]}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        let mut config = MemoryConfig::default();
+        config.embeddings = crate::config::EmbeddingsConfig {
+            enabled: true,
+            api_base: format!("http://{}", addr),
+            model: "text-embedding-3-small".to_string(),
+            ..Default::default()
+        };
+        let memory = MemoryManager::with_config(&ws, &config);
+        memory.remember("The user's favourite editor is Helix").unwrap();
+        memory.get_context("editor").await;
+
+        let entries = memory.store().load();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].embedding.is_some());
+        assert_eq!(entries[1].content, "Added during the request");
+    }
+}
@@ -4,12 +4,14 @@
 //! - `AgentExecutor`: Full-featured agent with message bus integration
 //! - `SimpleAgent`: Lightweight agent for CLI usage
 //! - `ContextBuilder`: System prompt builder from bootstrap files, memory, and skills
+//! - `MemoryManager`: Long-term memory entries with local retrieval ranking
 //! - `SubagentManager`: Background subagent execution
 
 pub mod executor;
 pub mod simple;
 pub mod skills;
 pub mod memory;
+pub mod retrieval;
 pub mod context;
 pub mod subagent;
 
@@ -0,0 +1,314 @@
+//! Local retrieval for memory entries.
+//!
+//! - `Bm25Index`: Okapi BM25 ranking over tokenized documents
+//! - `EmbeddingClient`: Optional OpenAI-compatible `/embeddings` client
+//! - `fuse_rankings`: Reciprocal rank fusion of several rankings
+
+use crate::config::EmbeddingsConfig;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+
+/// Longest wait for a connection to the embeddings endpoint
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Split text into lowercase search tokens.
+///
+/// Latin words are split on non-alphanumeric characters. CJK text has no
+/// word boundaries, so each ideograph is emitted along with its bigram.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+            if let Some(p) = prev_cjk {
+                tokens.push(format!("{}{}", p, c));
+            }
+            prev_cjk = Some(c);
+        } else if c.is_alphanumeric() {
+            prev_cjk = None;
+            word.extend(c.to_lowercase());
+        } else {
+            prev_cjk = None;
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+        }
+    }
+
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+
+    tokens
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0x3040..=0x30FF | 0xAC00..=0xD7AF)
+}
+
+/// In-memory BM25 index
+#[derive(Debug, Default)]
+pub struct Bm25Index {
+    docs: Vec<(String, HashMap<String, usize>, usize)>,
+    doc_freq: HashMap<String, usize>,
+    total_len: usize,
+}
+
+impl Bm25Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a document to the index
+    pub fn add(&mut self, id: &str, text: &str) {
+        let tokens = tokenize(text);
+        let mut term_freq: HashMap<String, usize> = HashMap::new();
+        for token in &tokens {
+            *term_freq.entry(token.clone()).or_insert(0) += 1;
+        }
+        for term in term_freq.keys() {
+            *self.doc_freq.entry(term.clone()).or_insert(0) += 1;
+        }
+        self.total_len += tokens.len();
+        self.docs.push((id.to_string(), term_freq, tokens.len()));
+    }
+
+    /// Rank documents against a query, best first. Documents that share no
+    /// terms with the query are omitted.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<(String, f32)> {
+        if self.docs.is_empty() {
+            return Vec::new();
+        }
+
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+
+        let n = self.docs.len() as f32;
+        let avg_len = self.total_len as f32 / n;
+
+        let mut scored: Vec<(String, f32)> = self
+            .docs
+            .iter()
+            .filter_map(|(id, term_freq, len)| {
+                let mut score = 0.0;
+                for term in &terms {
+                    let tf = match term_freq.get(term) {
+                        Some(&tf) => tf as f32,
+                        None => continue,
+                    };
+                    let df = *self.doc_freq.get(term).unwrap_or(&0) as f32;
+                    let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    let norm = BM25_K1 * (1.0 - BM25_B + BM25_B * *len as f32 / avg_len.max(1.0));
+                    score += idf * tf * (BM25_K1 + 1.0) / (tf + norm);
+                }
+                (score > 0.0).then(|| (id.clone(), score))
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        scored
+    }
+}
+
+/// Cosine similarity between two vectors
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Merge several rankings with reciprocal rank fusion (k = 60).
+pub fn fuse_rankings(rankings: &[Vec<(String, f32)>], limit: usize) -> Vec<(String, f32)> {
+    let mut fused: HashMap<&str, f32> = HashMap::new();
+    for ranking in rankings {
+        for (rank, (id, _)) in ranking.iter().enumerate() {
+            *fused.entry(id.as_str()).or_insert(0.0) += 1.0 / (60.0 + rank as f32 + 1.0);
+        }
+    }
+
+    let mut result: Vec<(String, f32)> = fused
+        .into_iter()
+        .map(|(id, score)| (id.to_string(), score))
+        .collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    result.truncate(limit);
+    result
+}
+
+/// Client for an OpenAI-compatible `/embeddings` endpoint
+#[derive(Debug, Clone)]
+pub struct EmbeddingClient {
+    client: reqwest::Client,
+    api_base: String,
+    api_key: String,
+    model: String,
+}
+
+impl EmbeddingClient {
+    /// Client whose requests fail after `timeout`, so a slow endpoint
+    /// cannot hold up replies
+    pub fn new(api_base: String, api_key: String, model: String, timeout: Duration) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(timeout.min(CONNECT_TIMEOUT))
+            .timeout(timeout)
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            api_base: api_base.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        }
+    }
+
+    /// Create from config, if embeddings are enabled
+    pub fn from_config(config: &EmbeddingsConfig) -> Option<Self> {
+        if !config.enabled || config.api_base.is_empty() {
+            return None;
+        }
+        Some(Self::new(
+            config.api_base.clone(),
+            config.api_key.clone(),
+            config.model.clone(),
+            Duration::from_secs(config.timeout_secs),
+        ))
+    }
+
+    /// Model name, stored alongside cached vectors
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embed a batch of texts
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, String> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut request = self
+            .client
+            .post(format!("{}/embeddings", self.api_base))
+            .json(&json!({
+                "model": self.model,
+                "input": texts,
+            }));
+        if !self.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Embedding request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let error = response.text().await.unwrap_or_default();
+            return Err(format!("Embedding API error: {}", error));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Parse error: {}", e))?;
+
+        let mut data: Vec<(usize, Vec<f32>)> = body["data"]
+            .as_array()
+            .ok_or("Embedding response has no data")?
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let index = item["index"].as_u64().map(|n| n as usize).unwrap_or(i);
+                let vector = item["embedding"]
+                    .as_array()
+                    .map(|v| v.iter().filter_map(|x| x.as_f64()).map(|x| x as f32).collect())
+                    .unwrap_or_default();
+                (index, vector)
+            })
+            .collect();
+        data.sort_by_key(|(i, _)| *i);
+
+        if data.len() != texts.len() {
+            return Err(format!(
+                "Embedding API returned {} vectors for {} inputs",
+                data.len(),
+                texts.len()
+            ));
+        }
+
+        Ok(data.into_iter().map(|(_, v)| v).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_latin() {
+        assert_eq!(tokenize("Hello, World! rust-lang 2024"), vec!["hello", "world", "rust", "lang", "2024"]);
+    }
+
+    #[test]
+    fn test_tokenize_cjk_bigrams() {
+        let tokens = tokenize("喜欢咖啡");
+        assert!(tokens.contains(&"咖".to_string()));
+        assert!(tokens.contains(&"咖啡".to_string()));
+        assert!(tokens.contains(&"喜欢".to_string()));
+    }
+
+    #[test]
+    fn test_bm25_ranks_relevant_first() {
+        let mut index = Bm25Index::new();
+        index.add("a", "The user prefers dark roast coffee in the morning");
+        index.add("b", "Project deadline is next Friday");
+        index.add("c", "The user's cat is named Mochi");
+
+        let results = index.search("what coffee does the user like", 3);
+        assert_eq!(results[0].0, "a");
+        assert!(results.iter().all(|(id, _)| id != "b"));
+    }
+
+    #[test]
+    fn test_bm25_no_match() {
+        let mut index = Bm25Index::new();
+        index.add("a", "alpha beta");
+        assert!(index.search("gamma", 5).is_empty());
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_fuse_rankings() {
+        let a = vec![("x".to_string(), 3.0), ("y".to_string(), 2.0)];
+        let b = vec![("y".to_string(), 0.9), ("z".to_string(), 0.5)];
+        let fused = fuse_rankings(&[a, b], 3);
+        assert_eq!(fused[0].0, "y");
+        assert_eq!(fused.len(), 3);
+    }
+}
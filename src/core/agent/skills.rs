@@ -1,80 +1,176 @@
 //! Skills system - load and manage agent skills.
 //!
-//! Skills are reusable capabilities that can be enabled/disabled.
+//! Skills are reusable capabilities that can be enabled/disabled. Each skill
+//! lives in `workspace/skills/<name>/SKILL.md` with a YAML frontmatter block.
+//! Skills whose required binaries or environment variables are missing on
+//! this host are loaded but not offered to the model. Skills may also ship
+//! executable tools, declared under `tools:` in the frontmatter.
+//!
+//! Installed skills are tracked in `workspace/skills/.manifest.json`, which
+//! also records whether each skill is enabled.
 
+use crate::tools::sandbox::Sandbox;
+use crate::tools::skill_tool::{SkillTool, SkillToolSpec};
+use crate::tools::ToolRegistry;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
-use tokio::fs as async_fs;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
 
 /// Skill metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SkillMetadata {
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default, alias = "always")]
     pub always_load: bool,
+    #[serde(default)]
     pub requires: Option<SkillRequirements>,
+    #[serde(default)]
+    pub tools: Vec<SkillToolSpec>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SkillRequirements {
     pub bins: Vec<String>,
     pub env: Vec<String>,
 }
 
+impl SkillRequirements {
+    /// List requirements that are not satisfied on this host
+    pub fn missing(&self) -> Vec<String> {
+        let mut missing = Vec::new();
+        for bin in &self.bins {
+            if find_in_path(bin).is_none() {
+                missing.push(format!("binary `{}`", bin));
+            }
+        }
+        for var in &self.env {
+            if std::env::var(var).map(|v| v.is_empty()).unwrap_or(true) {
+                missing.push(format!("env `{}`", var));
+            }
+        }
+        missing
+    }
+}
+
+/// Find an executable on PATH
+pub fn find_in_path(bin: &str) -> Option<PathBuf> {
+    let candidate = Path::new(bin);
+    if candidate.components().count() > 1 {
+        return is_executable(candidate).then(|| candidate.to_path_buf());
+    }
+
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(bin))
+        .find(|p| is_executable(p))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 /// A skill that the agent can use
 #[derive(Debug, Clone)]
 pub struct Skill {
     pub name: String,
     pub description: String,
+    pub version: Option<String>,
     pub content: String,
     pub always_load: bool,
     pub path: PathBuf,
+    /// Unsatisfied requirements; empty when the skill is usable
+    pub missing: Vec<String>,
+    /// Executable tools declared by the skill
+    pub tools: Vec<SkillToolSpec>,
 }
 
 impl Skill {
     /// Load a skill from a directory
     pub async fn load(path: &Path) -> Option<Self> {
         let skill_file = path.join("SKILL.md");
-
         if !skill_file.exists() {
             return None;
         }
 
-        let content = match fs::read_to_string(&skill_file) {
-            Ok(c) => c,
-            Err(_) => return None,
-        };
+        match Self::load_checked(path) {
+            Ok(skill) => Some(skill),
+            Err(e) => {
+                warn!("Skipping skill at {}: {}", skill_file.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Load a skill from a directory, reporting why it could not be loaded
+    pub fn load_checked(path: &Path) -> Result<Self, String> {
+        let skill_file = path.join("SKILL.md");
+
+        let content = fs::read_to_string(&skill_file)
+            .map_err(|e| format!("cannot read {}: {}", skill_file.display(), e))?;
 
-        // Parse metadata from the content
         let (metadata, content) = Self::parse_metadata(&content)?;
 
-        Some(Self {
+        let missing = metadata
+            .requires
+            .as_ref()
+            .map(|r| r.missing())
+            .unwrap_or_default();
+
+        let tools = metadata
+            .tools
+            .into_iter()
+            .filter(|tool| match tool.validate() {
+                Ok(()) => true,
+                Err(e) => {
+                    warn!("Skill '{}': skipping tool: {}", metadata.name, e);
+                    false
+                }
+            })
+            .collect();
+
+        Ok(Self {
             name: metadata.name,
             description: metadata.description,
+            version: metadata.version,
             content,
             always_load: metadata.always_load,
             path: path.to_path_buf(),
+            missing,
+            tools,
         })
     }
 
-    /// Parse YAML frontmatter
-    fn parse_metadata(content: &str) -> Option<(SkillMetadata, String)> {
-        // Simple frontmatter parsing
-        if !content.starts_with("---") {
-            return None;
-        }
-
-        let end_marker = content[3..].find("---")?;
-        let yaml_part = &content[3..3 + end_marker];
-        let rest = &content[3 + end_marker + 3..];
-
-        let metadata: SkillMetadata = match serde_yaml::from_str(yaml_part) {
-            Ok(m) => m,
-            Err(_) => return None,
-        };
+    /// Split YAML frontmatter from the Markdown body and parse it
+    fn parse_metadata(content: &str) -> Result<(SkillMetadata, String), String> {
+        let (yaml, body) = split_frontmatter(content).ok_or("missing `---` frontmatter block")?;
+        let metadata: SkillMetadata =
+            serde_yaml::from_str(yaml).map_err(|e| format!("invalid frontmatter: {}", e))?;
+        Ok((metadata, body.trim().to_string()))
+    }
 
-        Some((metadata, rest.trim().to_string()))
+    /// Whether all requirements are met
+    pub fn is_available(&self) -> bool {
+        self.missing.is_empty()
     }
 
     /// Get the skill content for context
@@ -82,7 +178,9 @@ impl Skill {
         format!(
             r#"## Skill: {}
 
-{}{}"#,
+{}
+
+{}"#,
             self.name,
             self.description,
             self.content
@@ -90,12 +188,82 @@ impl Skill {
     }
 }
 
+/// Split a document into its frontmatter and body. The frontmatter must
+/// start on the first line and end at the next line consisting of `---`.
+fn split_frontmatter(content: &str) -> Option<(&str, &str)> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let rest = content
+        .strip_prefix("---\r\n")
+        .or_else(|| content.strip_prefix("---\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Manifest entry for an installed skill
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledSkill {
+    pub version: Option<String>,
+    pub source: String,
+    pub installed_at: DateTime<Utc>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Installed skills, keyed by skill directory name
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SkillManifest {
+    pub skills: BTreeMap<String, InstalledSkill>,
+}
+
+impl SkillManifest {
+    pub const FILE_NAME: &'static str = ".manifest.json";
+
+    /// Load the manifest from a skills directory; missing or invalid files yield an empty manifest
+    pub fn load(skills_dir: &Path) -> Self {
+        fs::read_to_string(skills_dir.join(Self::FILE_NAME))
+            .ok()
+            .and_then(|content| match serde_json::from_str(&content) {
+                Ok(manifest) => Some(manifest),
+                Err(e) => {
+                    warn!("Ignoring invalid skills manifest: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, skills_dir: &Path) -> std::io::Result<()> {
+        fs::create_dir_all(skills_dir)?;
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(skills_dir.join(Self::FILE_NAME), content)
+    }
+
+    /// Skills without a manifest entry (copied by hand) count as enabled
+    pub fn is_enabled(&self, dir_name: &str) -> bool {
+        self.skills.get(dir_name).map(|s| s.enabled).unwrap_or(true)
+    }
+}
+
 /// Skill loader and manager
 #[derive(Debug)]
 pub struct SkillManager {
     workspace_skills: PathBuf,
     always_load: Vec<Skill>,
     optional: Vec<Skill>,
+    unavailable: Vec<Skill>,
+    fingerprint: Vec<(PathBuf, SystemTime)>,
 }
 
 impl SkillManager {
@@ -106,33 +274,76 @@ impl SkillManager {
             workspace_skills,
             always_load: Vec::new(),
             optional: Vec::new(),
+            unavailable: Vec::new(),
+            fingerprint: Vec::new(),
         }
     }
 
     /// Load all skills from workspace
     pub async fn load_all(&mut self) {
-        if !self.workspace_skills.exists() {
-            return;
-        }
+        self.always_load.clear();
+        self.optional.clear();
+        self.unavailable.clear();
+        self.fingerprint = self.scan_fingerprint();
+        let manifest = SkillManifest::load(&self.workspace_skills);
 
         let entries = match fs::read_dir(&self.workspace_skills) {
             Ok(e) => e,
             Err(_) => return,
         };
 
-        for entry in entries.flatten() {
-            if entry.path().is_dir() {
-                if let Some(skill) = Skill::load(&entry.path()).await {
-                    if skill.always_load {
-                        self.always_load.push(skill);
-                    } else {
-                        self.optional.push(skill);
-                    }
+        let mut dirs: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.is_dir())
+            .collect();
+        dirs.sort();
+
+        for dir in dirs {
+            let dir_name = dir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            if dir_name.starts_with('.') || !manifest.is_enabled(&dir_name) {
+                continue;
+            }
+            if let Some(skill) = Skill::load(&dir).await {
+                if !skill.is_available() {
+                    debug!("Skill '{}' unavailable: missing {}", skill.name, skill.missing.join(", "));
+                    self.unavailable.push(skill);
+                } else if skill.always_load {
+                    self.always_load.push(skill);
+                } else {
+                    self.optional.push(skill);
                 }
             }
         }
     }
 
+    /// Reload skills if any SKILL.md was added, removed or modified.
+    /// Returns true when a reload happened.
+    pub async fn reload_if_changed(&mut self) -> bool {
+        if self.scan_fingerprint() == self.fingerprint {
+            return false;
+        }
+        self.load_all().await;
+        true
+    }
+
+    /// Modification times of the manifest and every SKILL.md in the skills directory
+    fn scan_fingerprint(&self) -> Vec<(PathBuf, SystemTime)> {
+        let mut fingerprint: Vec<(PathBuf, SystemTime)> = fs::read_dir(&self.workspace_skills)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|e| e.path().join("SKILL.md"))
+            .chain(std::iter::once(self.workspace_skills.join(SkillManifest::FILE_NAME)))
+            .filter_map(|p| {
+                let modified = p.metadata().and_then(|m| m.modified()).ok()?;
+                Some((p, modified))
+            })
+            .collect();
+        fingerprint.sort();
+        fingerprint
+    }
+
     /// Get skills that should always be loaded
     pub fn get_always_load(&self) -> Vec<String> {
         self.always_load
@@ -146,8 +357,182 @@ impl SkillManager {
         self.optional.iter().collect()
     }
 
+    /// Get every enabled skill whose requirements are met
+    pub fn get_available(&self) -> Vec<&Skill> {
+        self.always_load.iter().chain(&self.optional).collect()
+    }
+
+    /// Get skills whose requirements are not met on this host
+    pub fn get_unavailable(&self) -> Vec<&Skill> {
+        self.unavailable.iter().collect()
+    }
+
     /// Get a skill by name
     pub fn get_skill(&self, name: &str) -> Option<&Skill> {
         self.optional.iter().find(|s| s.name == name)
     }
+
+    /// Register the tools of all available skills, run through `sandbox`
+    pub fn register_tools(&self, registry: &mut ToolRegistry, sandbox: &Sandbox) {
+        for skill in self.get_available() {
+            for spec in &skill.tools {
+                let tool = SkillTool::new(spec.clone(), &skill.name, skill.path.clone(), sandbox);
+                if !registry.register(Arc::new(tool)) {
+                    warn!("Skill '{}': tool '{}' is already registered", skill.name, spec.name);
+                }
+            }
+        }
+    }
+
+    /// Build the skills section of the system prompt
+    pub fn build_prompt_section(&self) -> String {
+        let mut parts = Vec::new();
+
+        let always_content = self.get_always_load();
+        if !always_content.is_empty() {
+            parts.push(format!("# Active Skills\n\n{}", always_content.join("\n\n")));
+        }
+
+        if !self.optional.is_empty() {
+            let summaries: Vec<String> = self
+                .optional
+                .iter()
+                .map(|s| format!("- **{}**: {} ({})", s.name, s.description, s.path.join("SKILL.md").display()))
+                .collect();
+            parts.push(format!(
+                "# Skills\n\nThe following skills extend your capabilities. To use a skill, read its SKILL.md file using the read_file tool.\n\n{}",
+                summaries.join("\n")
+            ));
+        }
+
+        parts.join("\n\n")
+    }
+}
+
+/// Poll the skills directory and reload on changes
+pub fn spawn_watcher(skills: Arc<RwLock<SkillManager>>, every: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            ticker.tick().await;
+            let mut manager = skills.write().await;
+            if manager.reload_if_changed().await {
+                info!(
+                    "Skills reloaded: {} active, {} unavailable",
+                    manager.always_load.len() + manager.optional.len(),
+                    manager.unavailable.len()
+                );
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_skill(root: &Path, dir: &str, content: &str) {
+        let path = root.join("skills").join(dir);
+        fs::create_dir_all(&path).unwrap();
+        fs::write(path.join("SKILL.md"), content).unwrap();
+    }
+
+    #[test]
+    fn test_parse_metadata_yaml() {
+        let content = "---\nname: weather\ndescription: \"Check: the weather\"\nrequires:\n  bins: [curl]\n---\n# Weather\n\nUse --- carefully.\n";
+        let (meta, body) = Skill::parse_metadata(content).unwrap();
+        assert_eq!(meta.name, "weather");
+        assert_eq!(meta.description, "Check: the weather");
+        assert_eq!(meta.requires.unwrap().bins, vec!["curl"]);
+        assert!(!meta.always_load);
+        assert!(body.contains("Use --- carefully."));
+    }
+
+    #[test]
+    fn test_parse_metadata_errors() {
+        assert!(Skill::parse_metadata("# No frontmatter").is_err());
+        assert!(Skill::parse_metadata("---\nname: [unclosed\n---\nbody").is_err());
+        assert!(Skill::parse_metadata("---\ndescription: no name\n---\nbody").is_err());
+    }
+
+    #[test]
+    fn test_requirements_missing() {
+        let requires = SkillRequirements {
+            bins: vec!["sh".to_string(), "definitely-not-a-real-binary-xyz".to_string()],
+            env: vec!["OPENAT_TEST_UNSET_VARIABLE_XYZ".to_string()],
+        };
+        let missing = requires.missing();
+        assert_eq!(missing.len(), 2);
+        assert!(missing[0].contains("definitely-not-a-real-binary-xyz"));
+        assert!(missing[1].contains("OPENAT_TEST_UNSET_VARIABLE_XYZ"));
+    }
+
+    #[tokio::test]
+    async fn test_load_all_separates_unavailable() {
+        let dir = tempfile::tempdir().unwrap();
+        write_skill(dir.path(), "ok", "---\nname: ok\ndescription: works\n---\nbody");
+        write_skill(dir.path(), "broken", "---\nname: broken\nrequires:\n  bins: [definitely-not-a-real-binary-xyz]\n---\nbody");
+
+        let mut manager = SkillManager::new(&dir.path().to_path_buf());
+        manager.load_all().await;
+        assert_eq!(manager.get_optional().len(), 1);
+        assert_eq!(manager.get_unavailable().len(), 1);
+        assert!(!manager.build_prompt_section().contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn test_register_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        write_skill(dir.path(), "jira", "---\nname: jira\ntools:\n  - name: jira_search\n    description: Search issues\n    command: cat\n    parameters:\n      type: object\n      properties:\n        query: { type: string }\n  - name: bad name\n    command: cat\n---\nbody");
+
+        let mut manager = SkillManager::new(&dir.path().to_path_buf());
+        manager.load_all().await;
+        let mut registry = ToolRegistry::new();
+        let sandbox = Sandbox::new(&crate::config::ExecConfig::default(), dir.path().to_path_buf());
+        manager.register_tools(&mut registry, &sandbox);
+
+        let defs = registry.definitions();
+        assert_eq!(defs.len(), 1);
+        assert_eq!(defs[0].name, "jira_search");
+        assert_eq!(defs[0].parameters["properties"]["query"]["type"], "string");
+        let output = registry.get("jira_search").unwrap().execute(r#"{"query":"x"}"#).await.unwrap();
+        assert_eq!(output, r#"{"query":"x"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_skill_not_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        write_skill(dir.path(), "quiet", "---\nname: quiet\nversion: 1.2.0\n---\nbody");
+        let skills_dir = dir.path().join("skills");
+
+        let mut manifest = SkillManifest::default();
+        manifest.skills.insert("quiet".to_string(), InstalledSkill {
+            version: Some("1.2.0".to_string()),
+            source: "test".to_string(),
+            installed_at: Utc::now(),
+            enabled: false,
+        });
+        manifest.save(&skills_dir).unwrap();
+
+        let mut manager = SkillManager::new(&dir.path().to_path_buf());
+        manager.load_all().await;
+        assert!(manager.get_skill("quiet").is_none());
+
+        manifest.skills.get_mut("quiet").unwrap().enabled = true;
+        manifest.save(&skills_dir).unwrap();
+        manager.load_all().await;
+        assert_eq!(manager.get_skill("quiet").unwrap().version.as_deref(), Some("1.2.0"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_if_changed_picks_up_new_skill() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = SkillManager::new(&dir.path().to_path_buf());
+        manager.load_all().await;
+        assert!(!manager.reload_if_changed().await);
+
+        write_skill(dir.path(), "new", "---\nname: new\n---\nbody");
+        assert!(manager.reload_if_changed().await);
+        assert!(manager.get_skill("new").is_some());
+    }
 }
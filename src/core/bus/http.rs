@@ -0,0 +1,303 @@
+//! Local HTTP bridge into the message bus.
+//!
+//! The gateway listens on `127.0.0.1:<port>` and accepts
+//! `POST /api/messages` with an `OutboundMessage`-shaped JSON body
+//! (`channel`, `chat_id`, `content`), which is published to the outbound
+//! bus so the matching channel delivers it. Other processes, such as
+//! `openat mcp-serve`, use this to push notifications into chats.
+//!
+//! Any local process can reach the port, so requests must carry
+//! `Authorization: Bearer <token>` with the token from
+//! `~/.openat/gateway.token`. The file is created with mode 0600 by
+//! whichever of the gateway or `mcp-serve` starts first.
+
+use crate::core::bus::MessageBus;
+use crate::types::OutboundMessage;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+const MAX_BODY: usize = 1024 * 1024;
+const MAX_HEADERS: u64 = 16 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+struct PostMessage {
+    channel: String,
+    chat_id: String,
+    content: String,
+}
+
+/// Where the bridge's shared token is kept, next to the config file
+pub fn token_path() -> PathBuf {
+    crate::config::config_path().with_file_name("gateway.token")
+}
+
+/// Read the shared token, generating it on first use
+pub fn load_or_create_token(path: &Path) -> io::Result<String> {
+    if let Ok(token) = fs::read_to_string(path)
+        && !token.trim().is_empty()
+    {
+        return Ok(token.trim().to_string());
+    }
+
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| io::Error::other(e.to_string()))?;
+    let token: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    io::Write::write_all(&mut options.open(path)?, token.as_bytes())?;
+    Ok(token)
+}
+
+/// Bind the bridge and serve it in the background
+pub async fn serve(bus: MessageBus, addr: SocketAddr, token: String) -> io::Result<tokio::task::JoinHandle<()>> {
+    let listener = TcpListener::bind(addr).await?;
+    Ok(tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    warn!("Gateway API accept failed: {}", e);
+                    continue;
+                }
+            };
+            let bus = bus.clone();
+            let token = token.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, &bus, &token).await {
+                    debug!("Gateway API connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }))
+}
+
+/// A parsed request, or the status and body to reject it with
+enum Request {
+    Message(PostMessage),
+    Rejected(&'static str, String),
+}
+
+async fn handle_connection(stream: TcpStream, bus: &MessageBus, token: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader, token))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "request not received in time"))??;
+
+    let (status, body) = match request {
+        Request::Message(msg) => {
+            bus.publish_outbound(OutboundMessage::new(msg.channel, msg.chat_id, msg.content)).await;
+            ("202 Accepted", r#"{"ok":true}"#.to_string())
+        }
+        Request::Rejected(status, body) => (status, body),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn read_request(reader: &mut BufReader<TcpStream>, token: &str) -> io::Result<Request> {
+    // The request line and headers share one size budget
+    let mut head = reader.take(MAX_HEADERS);
+
+    let mut request_line = String::new();
+    head.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut content_length = 0usize;
+    let mut authorized = false;
+    loop {
+        let mut line = String::new();
+        if head.read_line(&mut line).await? == 0 {
+            if head.limit() == 0 {
+                return Ok(Request::Rejected(
+                    "431 Request Header Fields Too Large",
+                    r#"{"error":"headers too large"}"#.to_string(),
+                ));
+            }
+            break;
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("authorization") {
+                authorized = value
+                    .trim()
+                    .strip_prefix("Bearer ")
+                    .is_some_and(|given| same_token(given.trim(), token));
+            }
+        }
+    }
+
+    if !authorized {
+        return Ok(Request::Rejected("401 Unauthorized", r#"{"error":"missing or wrong token"}"#.to_string()));
+    }
+    if method != "POST" || path != "/api/messages" {
+        return Ok(Request::Rejected("404 Not Found", r#"{"error":"not found"}"#.to_string()));
+    }
+    if content_length > MAX_BODY {
+        return Ok(Request::Rejected("413 Payload Too Large", r#"{"error":"body too large"}"#.to_string()));
+    }
+
+    let mut body = vec![0u8; content_length];
+    reader.read_exact(&mut body).await?;
+    Ok(match serde_json::from_slice::<PostMessage>(&body) {
+        Ok(msg) if !msg.content.is_empty() => Request::Message(msg),
+        Ok(_) => Request::Rejected("400 Bad Request", r#"{"error":"content is empty"}"#.to_string()),
+        Err(e) => Request::Rejected("400 Bad Request", serde_json::json!({"error": e.to_string()}).to_string()),
+    })
+}
+
+/// Compare without returning early, so timing doesn't leak the token
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len() && given.bytes().zip(token.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Post a message to a running gateway
+pub async fn post_message(
+    gateway_url: &str,
+    token: &str,
+    channel: &str,
+    chat_id: &str,
+    content: &str,
+) -> Result<(), String> {
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/messages", gateway_url.trim_end_matches('/')))
+        .bearer_auth(token)
+        .json(&serde_json::json!({
+            "channel": channel,
+            "chat_id": chat_id,
+            "content": content,
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Gateway not reachable at {}: {}", gateway_url, e))?;
+
+    if !response.status().is_success() {
+        let error = response.text().await.unwrap_or_default();
+        return Err(format!("Gateway rejected message: {}", error));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn start(bus: &MessageBus) -> (String, tokio::task::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let server = serve(bus.clone(), addr, "secret".to_string()).await.unwrap();
+        (format!("http://{}", addr), server)
+    }
+
+    #[tokio::test]
+    async fn test_post_message_reaches_bus() {
+        let bus = MessageBus::new();
+        let mut rx = bus.subscribe_outbound();
+        let (url, server) = start(&bus).await;
+
+        post_message(&url, "secret", "discord", "123", "hello").await.unwrap();
+        let msg = rx.recv().await.unwrap();
+        assert_eq!(msg.channel, "discord");
+        assert_eq!(msg.chat_id, "123");
+        assert_eq!(msg.content, "hello");
+
+        assert!(post_message(&url, "secret", "discord", "123", "").await.is_err());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_token_are_refused() {
+        let bus = MessageBus::new();
+        let mut rx = bus.subscribe_outbound();
+        let (url, server) = start(&bus).await;
+
+        let err = post_message(&url, "wrong", "discord", "123", "hello").await.unwrap_err();
+        assert!(err.contains("token"), "{}", err);
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/messages", url))
+            .json(&serde_json::json!({"channel": "discord", "chat_id": "123", "content": "hello"}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+        assert!(rx.try_recv().is_err());
+        server.abort();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_requests_are_dropped() {
+        let bus = MessageBus::new();
+        let (url, server) = start(&bus).await;
+
+        // A client that never finishes its headers is cut off
+        let mut slow = TcpStream::connect(url.trim_start_matches("http://")).await.unwrap();
+        slow.write_all(b"POST /api/messages HTTP/1.1\r\n").await.unwrap();
+        let mut buf = Vec::new();
+        let read = tokio::time::timeout(READ_TIMEOUT * 2, slow.read_to_end(&mut buf)).await;
+        assert!(read.is_ok(), "connection still open after the read timeout");
+        assert!(buf.is_empty());
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_headers_are_rejected() {
+        let bus = MessageBus::new();
+        let (url, server) = start(&bus).await;
+
+        // Just over the budget, so the server has read everything we sent
+        let mut request = b"POST /api/messages HTTP/1.1\r\n".to_vec();
+        while request.len() <= MAX_HEADERS as usize {
+            request.extend_from_slice(b"X-Filler: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n");
+        }
+        let mut stream = TcpStream::connect(url.trim_start_matches("http://")).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 431"), "{}", response);
+        server.abort();
+    }
+
+    #[test]
+    fn test_token_is_created_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gateway.token");
+        let token = load_or_create_token(&path).unwrap();
+        assert_eq!(token.len(), 64);
+        assert_eq!(load_or_create_token(&path).unwrap(), token);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
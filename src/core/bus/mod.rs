@@ -2,6 +2,8 @@
 //!
 //! Provides async channels for inbound/outbound messages and events.
 
+pub mod http;
+
 use crate::types::{Event, InboundMessage, OutboundMessage};
 use tokio::sync::broadcast;
 use tracing::{debug, info};
@@ -60,6 +62,7 @@ impl MessageBus {
             Event::Connect { channel, .. } => channel.clone(),
             Event::Disconnect { channel, .. } => channel.clone(),
             Event::Error { channel, .. } => channel.clone(),
+            Event::ConfigReload { .. } | Event::ConfigError { .. } => "config".to_string(),
         };
 
         debug!("Publishing event: {:?}", event);
@@ -2,6 +2,8 @@
 
 pub mod agent;
 pub mod bus;
+pub mod permissions;
+pub mod reload;
 pub mod scheduler;
 pub mod session;
 
@@ -0,0 +1,415 @@
+//! Permissions module - roles for chat users across channels.
+//!
+//! # Resolution
+//!
+//! The role of a message sender is the first match of:
+//!
+//! 1. `permissions.users["<channel>:<user_id>"]`
+//! 2. `permissions.users["*:<user_id>"]`
+//! 3. Users paired with a code (see `pairing`)
+//! 4. `permissions.groups["<channel>:<chat_id>"]`
+//! 5. `member`, if the user is on the channel's legacy `allowed_users` list
+//! 6. `permissions.default_role`, unless the channel has a legacy allowlist
+//!
+//! Senders without a role are asked for a pairing code. `default_role` is
+//! empty by default, so nobody gets tools without being listed or paired;
+//! it used to be `member`, which let anyone use every tool. Roles control which
+//! tools and models can be used, slash commands and message rate limits.
+
+pub mod pairing;
+
+use crate::config::{Config, PermissionsConfig, RoleConfig};
+use crate::types::InboundMessage;
+use pairing::{PairingCode, PairingStore};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+/// How often an unknown user is reminded to send a pairing code
+const PAIRING_PROMPT_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Roles available without any configuration
+pub fn builtin_roles() -> BTreeMap<String, RoleConfig> {
+    BTreeMap::from([
+        (
+            "admin".to_string(),
+            RoleConfig {
+                commands: true,
+                ..Default::default()
+            },
+        ),
+        ("member".to_string(), RoleConfig::default()),
+        (
+            "guest".to_string(),
+            RoleConfig {
+                tools: Some(Vec::new()),
+                models: Some(Vec::new()),
+                messages_per_minute: 5,
+                messages_per_hour: 60,
+                ..Default::default()
+            },
+        ),
+    ])
+}
+
+fn matches(patterns: &Option<Vec<String>>, name: &str) -> bool {
+    match patterns {
+        None => true,
+        Some(patterns) => patterns.iter().any(|p| match p.strip_suffix('*') {
+            Some(prefix) => name.starts_with(prefix),
+            None => p == name,
+        }),
+    }
+}
+
+/// A resolved role
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub name: String,
+    pub config: RoleConfig,
+}
+
+impl Role {
+    pub fn can_use_tool(&self, tool: &str) -> bool {
+        matches(&self.config.tools, tool)
+    }
+
+    pub fn can_use_model(&self, model: &str) -> bool {
+        matches(&self.config.models, model)
+    }
+
+    pub fn can_use_commands(&self) -> bool {
+        self.config.commands
+    }
+}
+
+/// What to do with a message from a sender
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    /// The sender has a role; hand the message to the agent
+    Allowed,
+    /// Answer the sender directly, without involving the agent
+    Reply(String),
+    /// Drop the message
+    Ignore,
+}
+
+/// Paired users, cached until `pairing.json` changes
+#[derive(Debug, Default)]
+struct PairedUsers {
+    modified: Option<SystemTime>,
+    users: BTreeMap<String, String>,
+}
+
+/// Role rules taken from the config
+#[derive(Debug)]
+struct Rules {
+    config: PermissionsConfig,
+    /// Legacy `allowed_users` lists by channel
+    legacy: HashMap<String, Vec<String>>,
+}
+
+impl Rules {
+    fn new(config: &Config) -> Self {
+        let mut permissions = config.permissions.clone();
+        for (name, role) in builtin_roles() {
+            permissions.roles.entry(name).or_insert(role);
+        }
+
+        let channels = &config.channels;
+        let legacy = [
+            ("telegram", &channels.telegram.allowed_users),
+            ("whatsapp", &channels.whatsapp.allowed_numbers),
+            ("qq", &channels.qq.allowed_users),
+            ("discord", &channels.discord.allowed_users),
+        ]
+        .into_iter()
+        .filter(|(_, users)| !users.is_empty())
+        .map(|(channel, users)| (channel.to_string(), users.clone()))
+        .collect();
+
+        Self {
+            config: permissions,
+            legacy,
+        }
+    }
+}
+
+/// Role lookup and rate limiting. Clones share their rules, so `update`
+/// applies a reloaded config everywhere.
+#[derive(Debug, Clone)]
+pub struct Permissions {
+    rules: Arc<RwLock<Rules>>,
+    /// Recent message times by `channel:user_id`
+    usage: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+    pairing_path: PathBuf,
+    paired: Arc<Mutex<PairedUsers>>,
+    /// When unknown users were last asked for a pairing code
+    prompted: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Permissions {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            rules: Arc::new(RwLock::new(Rules::new(config))),
+            usage: Arc::new(Mutex::new(HashMap::new())),
+            pairing_path: PairingStore::path(),
+            paired: Arc::new(Mutex::new(PairedUsers::default())),
+            prompted: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Replace the roles and allowlists with those of a reloaded config
+    pub fn update(&self, config: &Config) {
+        *self.rules.write().unwrap() = Rules::new(config);
+    }
+
+    /// Use a different pairing file
+    #[cfg(test)]
+    pub fn with_pairing_path(mut self, path: PathBuf) -> Self {
+        self.pairing_path = path;
+        self.paired = Arc::new(Mutex::new(PairedUsers::default()));
+        self
+    }
+
+    /// Name of the role for a sender, if they may use the bot at all
+    pub fn role_name(&self, channel: &str, user_id: &str, chat_id: &str) -> Option<String> {
+        let rules = self.rules.read().unwrap();
+        let config = &rules.config;
+        let assigned = config
+            .users
+            .get(&format!("{}:{}", channel, user_id))
+            .or_else(|| config.users.get(&format!("*:{}", user_id)))
+            .cloned()
+            .or_else(|| self.paired_role(&format!("{}:{}", channel, user_id)))
+            .or_else(|| config.groups.get(&format!("{}:{}", channel, chat_id)).cloned());
+        if assigned.is_some() {
+            return assigned;
+        }
+
+        match rules.legacy.get(channel) {
+            Some(users) if users.iter().any(|u| u == user_id) => Some("member".to_string()),
+            Some(_) => None,
+            None if config.default_role.is_empty() => None,
+            None => Some(config.default_role.clone()),
+        }
+    }
+
+    fn paired_role(&self, key: &str) -> Option<String> {
+        let modified = std::fs::metadata(&self.pairing_path).and_then(|m| m.modified()).ok();
+        let mut paired = self.paired.lock().unwrap();
+        if paired.modified != modified {
+            paired.users = PairingStore::load(&self.pairing_path).users;
+            paired.modified = modified;
+        }
+        paired.users.get(key).cloned()
+    }
+
+    /// Role for a sender, if they may use the bot at all
+    pub fn role(&self, channel: &str, user_id: &str, chat_id: &str) -> Option<Role> {
+        let name = self.role_name(channel, user_id, chat_id)?;
+        let config = self.rules.read().unwrap().config.roles.get(&name).cloned();
+        match config {
+            Some(config) => Some(Role { name, config }),
+            None => {
+                tracing::warn!("Unknown role '{}' for {}:{}", name, channel, user_id);
+                None
+            }
+        }
+    }
+
+    pub fn is_allowed(&self, channel: &str, user_id: &str, chat_id: &str) -> bool {
+        self.role(channel, user_id, chat_id).is_some()
+    }
+
+    /// Decide what to do with a message. Unknown senders can pair by sending a code.
+    pub fn admit(&self, msg: &InboundMessage) -> Admission {
+        if self.is_allowed(&msg.channel, &msg.sender_id, &msg.chat_id) {
+            return Admission::Allowed;
+        }
+
+        if pairing::looks_like_code(&msg.content) {
+            let mut store = PairingStore::load(&self.pairing_path);
+            let Some(role) = store.redeem(&msg.content, &msg.channel, &msg.sender_id) else {
+                return Admission::Reply("That pairing code is invalid or has expired.".to_string());
+            };
+            if let Err(e) = store.save(&self.pairing_path) {
+                tracing::error!("Failed to save pairing file: {}", e);
+                return Admission::Reply("Pairing failed, please try again later.".to_string());
+            }
+            tracing::info!("Paired {}:{} as {}", msg.channel, msg.sender_id, role);
+            return Admission::Reply(format!("You're paired with the {} role. Say hi!", role));
+        }
+
+        let key = format!("{}:{}", msg.channel, msg.sender_id);
+        let mut prompted = self.prompted.lock().unwrap();
+        let now = Instant::now();
+        if prompted.get(&key).is_some_and(|t| now.duration_since(*t) < PAIRING_PROMPT_INTERVAL) {
+            return Admission::Ignore;
+        }
+        prompted.insert(key, now);
+        Admission::Reply("Hi! I only talk to people I know. Please send me your pairing code.".to_string())
+    }
+
+    /// Create a pairing code for `channel` granting `role`
+    pub fn create_code(&self, channel: &str, role: &str, ttl: chrono::Duration) -> Result<PairingCode, String> {
+        if !self.rules.read().unwrap().config.roles.contains_key(role) {
+            return Err(format!("Unknown role '{}'", role));
+        }
+        let mut store = PairingStore::load(&self.pairing_path);
+        let code = store.create_code(channel, role, ttl);
+        store
+            .save(&self.pairing_path)
+            .map_err(|e| format!("Failed to save pairing file: {}", e))?;
+        Ok(code)
+    }
+
+    /// Count a message against the role's rate limits, failing when a limit is reached
+    pub fn check_rate(&self, channel: &str, user_id: &str, role: &Role) -> Result<(), String> {
+        let per_minute = role.config.messages_per_minute as usize;
+        let per_hour = role.config.messages_per_hour as usize;
+        if per_minute == 0 && per_hour == 0 {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut usage = self.usage.lock().unwrap();
+        let times = usage.entry(format!("{}:{}", channel, user_id)).or_default();
+        while times.front().is_some_and(|t| now.duration_since(*t) >= HOUR) {
+            times.pop_front();
+        }
+
+        let last_minute = times.iter().filter(|t| now.duration_since(**t) < MINUTE).count();
+        if per_minute > 0 && last_minute >= per_minute {
+            return Err(format!("Rate limit reached ({} messages per minute). Please wait a moment.", per_minute));
+        }
+        if per_hour > 0 && times.len() >= per_hour {
+            return Err(format!("Rate limit reached ({} messages per hour). Please try again later.", per_hour));
+        }
+        times.push_back(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config that admits unknown users as members
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.permissions.default_role = "member".to_string();
+        config.permissions.users.insert("discord:1".to_string(), "admin".to_string());
+        config.permissions.users.insert("*:2".to_string(), "guest".to_string());
+        config.permissions.groups.insert("qq:room".to_string(), "guest".to_string());
+        config
+    }
+
+    #[test]
+    fn test_role_resolution() {
+        let permissions = Permissions::new(&config());
+        assert_eq!(permissions.role_name("discord", "1", "c").as_deref(), Some("admin"));
+        assert_eq!(permissions.role_name("telegram", "1", "c").as_deref(), Some("member"));
+        assert_eq!(permissions.role_name("telegram", "2", "c").as_deref(), Some("guest"));
+        assert_eq!(permissions.role_name("qq", "3", "room").as_deref(), Some("guest"));
+        assert_eq!(permissions.role_name("qq", "3", "dm").as_deref(), Some("member"));
+
+        // Unknown users get no role unless the config grants one
+        let mut closed = Config::default();
+        closed.permissions.users.insert("discord:1".to_string(), "admin".to_string());
+        let permissions = Permissions::new(&closed);
+        assert!(!permissions.is_allowed("qq", "3", "dm"));
+        assert!(permissions.is_allowed("discord", "1", "c"));
+    }
+
+    #[test]
+    fn test_update_applies_to_clones() {
+        let permissions = Permissions::new(&config());
+        let shared = permissions.clone();
+        assert_eq!(shared.role_name("telegram", "7", "c").as_deref(), Some("member"));
+
+        let mut reloaded = config();
+        reloaded.permissions.users.insert("telegram:7".to_string(), "admin".to_string());
+        permissions.update(&reloaded);
+        assert_eq!(shared.role_name("telegram", "7", "c").as_deref(), Some("admin"));
+    }
+
+    #[test]
+    fn test_legacy_allowlist() {
+        let mut config = config();
+        config.channels.telegram.allowed_users = vec!["5".to_string()];
+        let permissions = Permissions::new(&config);
+        assert_eq!(permissions.role_name("telegram", "5", "c").as_deref(), Some("member"));
+        assert_eq!(permissions.role_name("telegram", "6", "c"), None);
+        // Explicit assignments still win
+        assert_eq!(permissions.role_name("telegram", "2", "c").as_deref(), Some("guest"));
+        assert_eq!(permissions.role_name("discord", "6", "c").as_deref(), Some("member"));
+    }
+
+    #[test]
+    fn test_role_capabilities() {
+        let mut config = config();
+        config.permissions.roles.insert(
+            "dev".to_string(),
+            RoleConfig {
+                tools: Some(vec!["read_*".to_string(), "exec".to_string()]),
+                models: Some(vec!["deepseek/*".to_string()]),
+                ..Default::default()
+            },
+        );
+        config.permissions.users.insert("discord:4".to_string(), "dev".to_string());
+        let permissions = Permissions::new(&config);
+
+        let dev = permissions.role("discord", "4", "c").unwrap();
+        assert!(dev.can_use_tool("read_file") && dev.can_use_tool("exec"));
+        assert!(!dev.can_use_tool("write_file"));
+        assert!(dev.can_use_model("deepseek/deepseek-chat"));
+        assert!(!dev.can_use_model("gpt-4o"));
+        assert!(!dev.can_use_commands());
+
+        let guest = permissions.role("telegram", "2", "c").unwrap();
+        assert!(!guest.can_use_tool("read_file"));
+        assert!(permissions.role("discord", "1", "c").unwrap().can_use_commands());
+
+        config.permissions.users.insert("discord:9".to_string(), "missing".to_string());
+        assert!(Permissions::new(&config).role("discord", "9", "c").is_none());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let permissions = Permissions::new(&config());
+        let guest = permissions.role("telegram", "2", "c").unwrap();
+        for _ in 0..5 {
+            assert!(permissions.check_rate("telegram", "2", &guest).is_ok());
+        }
+        assert!(permissions.check_rate("telegram", "2", &guest).is_err());
+
+        let member = permissions.role("telegram", "3", "c").unwrap();
+        for _ in 0..100 {
+            assert!(permissions.check_rate("telegram", "3", &member).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_pairing_admission() {
+        let dir = tempfile::tempdir().unwrap();
+        let permissions = Permissions::new(&Config::default()).with_pairing_path(dir.path().join("pairing.json"));
+
+        let hello = InboundMessage::new("telegram", "5", "c", "hello");
+        assert!(matches!(permissions.admit(&hello), Admission::Reply(_)));
+        assert_eq!(permissions.admit(&hello), Admission::Ignore);
+        let wrong = InboundMessage::new("telegram", "5", "c", "ABCDEFGH");
+        assert!(matches!(permissions.admit(&wrong), Admission::Reply(_)));
+
+        assert!(permissions.create_code("telegram", "nobody", chrono::Duration::minutes(5)).is_err());
+        let code = permissions.create_code("telegram", "guest", chrono::Duration::minutes(5)).unwrap();
+        let pair = InboundMessage::new("telegram", "5", "c", &code.code);
+        assert!(matches!(permissions.admit(&pair), Admission::Reply(_)));
+        assert_eq!(permissions.role_name("telegram", "5", "c").as_deref(), Some("guest"));
+        assert_eq!(permissions.admit(&hello), Admission::Allowed);
+        assert!(!permissions.is_allowed("discord", "5", "c"));
+    }
+}
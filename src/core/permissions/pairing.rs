@@ -0,0 +1,135 @@
+//! Pairing codes - admit new chat users without editing the config.
+//!
+//! `openat pair --channel telegram` or an admin's `/invite` creates a
+//! short-lived code. When an unknown user sends it to the bot, their
+//! `channel:user_id` is added to `pairing.json` next to the config file
+//! with the code's role. The file is re-read when it changes, so pairings
+//! made by the CLI or another gateway component apply immediately.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Characters used in codes; no 0/O or 1/I to avoid misreading
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+pub const CODE_LEN: usize = 8;
+
+/// An unredeemed pairing code
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairingCode {
+    pub code: String,
+    pub channel: String,
+    pub role: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Persisted codes and paired users
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PairingStore {
+    pub codes: Vec<PairingCode>,
+    /// Role of each paired user, keyed by `channel:user_id`
+    pub users: BTreeMap<String, String>,
+}
+
+impl PairingStore {
+    pub fn path() -> PathBuf {
+        crate::config::config_path().with_file_name("pairing.json")
+    }
+
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        fs::write(path, content)
+    }
+
+    /// Create a code for `channel` that grants `role` for `ttl`
+    pub fn create_code(&mut self, channel: &str, role: &str, ttl: Duration) -> PairingCode {
+        self.codes.retain(|c| c.expires_at > Utc::now());
+        // 256 is a multiple of the alphabet size, so `%` keeps each character equally likely
+        let mut bytes = [0u8; CODE_LEN];
+        getrandom::fill(&mut bytes).expect("no OS random source for pairing codes");
+        let code = bytes
+            .iter()
+            .map(|b| CODE_ALPHABET[*b as usize % CODE_ALPHABET.len()] as char)
+            .collect();
+        let code = PairingCode {
+            code,
+            channel: channel.to_string(),
+            role: role.to_string(),
+            expires_at: Utc::now() + ttl,
+        };
+        self.codes.push(code.clone());
+        code
+    }
+
+    /// Redeem a code for a user, returning the granted role
+    pub fn redeem(&mut self, code: &str, channel: &str, user_id: &str) -> Option<String> {
+        let code = code.trim().to_uppercase();
+        self.codes.retain(|c| c.expires_at > Utc::now());
+        let index = self.codes.iter().position(|c| c.code == code && c.channel == channel)?;
+        let granted = self.codes.remove(index);
+        self.users.insert(format!("{}:{}", channel, user_id), granted.role.clone());
+        Some(granted.role)
+    }
+}
+
+/// Whether a message could be a pairing code
+pub fn looks_like_code(content: &str) -> bool {
+    let content = content.trim();
+    content.len() == CODE_LEN && content.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_redeem() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pairing.json");
+
+        let mut store = PairingStore::load(&path);
+        let code = store.create_code("telegram", "member", Duration::minutes(10));
+        assert!(looks_like_code(&code.code));
+        store.save(&path).unwrap();
+
+        let mut store = PairingStore::load(&path);
+        assert!(store.redeem(&code.code, "discord", "42").is_none());
+        assert_eq!(store.redeem(&code.code.to_lowercase(), "telegram", "42").as_deref(), Some("member"));
+        assert_eq!(store.users["telegram:42"], "member");
+        // Codes are single use
+        assert!(store.redeem(&code.code, "telegram", "43").is_none());
+    }
+
+    #[test]
+    fn test_expired_code() {
+        let mut store = PairingStore::default();
+        let code = store.create_code("qq", "guest", Duration::seconds(-1));
+        assert!(store.redeem(&code.code, "qq", "1").is_none());
+        assert!(store.codes.is_empty());
+    }
+
+    #[test]
+    fn test_codes_use_the_whole_alphabet() {
+        let mut store = PairingStore::default();
+        let codes: Vec<String> = (0..2000)
+            .map(|_| store.create_code("qq", "guest", Duration::minutes(1)).code)
+            .collect();
+        for position in 0..CODE_LEN {
+            let seen: std::collections::BTreeSet<u8> = codes.iter().map(|c| c.as_bytes()[position]).collect();
+            assert_eq!(seen.len(), CODE_ALPHABET.len(), "position {} is biased", position);
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Config reload - watch the config file and work out what changed.
+//!
+//! `spawn_watcher` polls the config file's modification time and, on Unix,
+//! also reloads on SIGHUP. Each new version is validated first: a config
+//! with errors is rejected with an `Event::ConfigError` and the running one
+//! stays in place. Valid configs are handed to the gateway, which applies
+//! the `Change`s found by `diff` and publishes an `Event::ConfigReload`.
+
+use crate::config::{validate, Config};
+use crate::core::bus::MessageBus;
+use crate::types::Event;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+/// Channels, as named in the config
+const CHANNELS: [&str; 4] = ["telegram", "whatsapp", "qq", "discord"];
+
+/// A part of the config that changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Providers or the default model
+    Provider,
+    /// Roles, user and group assignments, or a legacy allowlist
+    Permissions,
+    /// A channel's settings other than its allowlist
+    Channel(&'static str),
+    /// A section that only takes effect after a restart
+    NeedsRestart(&'static str),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Provider => write!(f, "provider and model"),
+            Change::Permissions => write!(f, "permissions"),
+            Change::Channel(name) => write!(f, "channels.{}", name),
+            Change::NeedsRestart(section) => write!(f, "{} (needs a restart)", section),
+        }
+    }
+}
+
+fn value<T: Serialize>(section: &T) -> Value {
+    serde_json::to_value(section).unwrap_or_default()
+}
+
+/// Remove a channel's legacy allowlist from its settings and return it
+fn take_allowlist(channel: &mut Value) -> Value {
+    let Some(fields) = channel.as_object_mut() else {
+        return Value::Null;
+    };
+    fields
+        .remove("allowed_users")
+        .or_else(|| fields.remove("allowed_numbers"))
+        .unwrap_or_default()
+}
+
+/// What differs between the running config and a new one, in the order it should be applied
+pub fn diff(old: &Config, new: &Config) -> Vec<Change> {
+    let mut changes = Vec::new();
+    if value(&old.providers) != value(&new.providers) || value(&old.agents.defaults) != value(&new.agents.defaults) {
+        changes.push(Change::Provider);
+    }
+
+    let (old_channels, new_channels) = (value(&old.channels), value(&new.channels));
+    let mut allowlists_changed = false;
+    // Running channels save attachments under these limits
+    let attachments_changed = old_channels["attachments"] != new_channels["attachments"];
+    let mut channels = Vec::new();
+    for name in CHANNELS {
+        let (mut before, mut after) = (old_channels[name].clone(), new_channels[name].clone());
+        allowlists_changed |= take_allowlist(&mut before) != take_allowlist(&mut after);
+        if before != after || (attachments_changed && after["enabled"] == true) {
+            channels.push(Change::Channel(name));
+        }
+    }
+    if allowlists_changed || value(&old.permissions) != value(&new.permissions) {
+        changes.push(Change::Permissions);
+    }
+    changes.extend(channels);
+
+    if value(&old.tools) != value(&new.tools) {
+        changes.push(Change::NeedsRestart("tools"));
+    }
+    if value(&old.agents.memory) != value(&new.agents.memory) {
+        changes.push(Change::NeedsRestart("agents.memory"));
+    }
+    if value(&old.audio) != value(&new.audio) {
+        changes.push(Change::NeedsRestart("audio"));
+    }
+    changes
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Wait for SIGHUP. Never resolves if the handler could not be installed.
+#[cfg(unix)]
+async fn hangup(signal: &mut Option<tokio::signal::unix::Signal>) {
+    match signal {
+        Some(signal) => {
+            signal.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn hangup(_: &mut Option<()>) {
+    std::future::pending().await
+}
+
+/// Watch the config file at `path`, checking every `every` and on SIGHUP.
+/// Valid new configs are sent on the returned receiver; invalid ones are
+/// reported on the bus.
+pub fn spawn_watcher(
+    path: PathBuf,
+    every: Duration,
+    bus: MessageBus,
+) -> (tokio::task::JoinHandle<()>, mpsc::UnboundedReceiver<Config>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let task = tokio::spawn(async move {
+        #[cfg(unix)]
+        let mut signal = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+        #[cfg(not(unix))]
+        let mut signal = None;
+
+        let mut last = modified(&path);
+        let mut ticker = tokio::time::interval(every);
+        loop {
+            let forced = tokio::select! {
+                _ = ticker.tick() => false,
+                _ = hangup(&mut signal) => true,
+            };
+            let current = modified(&path);
+            if !forced && current == last {
+                continue;
+            }
+            last = current;
+
+            info!("Reloading config from {}", path.display());
+            let validation = validate::validate(&path);
+            if validation.has_errors() {
+                let errors: Vec<String> = validation
+                    .issues
+                    .iter()
+                    .filter(|i| i.severity == validate::Severity::Error)
+                    .map(ToString::to_string)
+                    .collect();
+                error!("Config not reloaded: {}", errors.join("; "));
+                bus.publish_event(Event::ConfigError { errors }).await;
+                continue;
+            }
+            for issue in &validation.issues {
+                warn!("{}: {}", path.display(), issue);
+            }
+            if tx.send(validation.config).is_err() {
+                break;
+            }
+        }
+    });
+    (task, rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let old = Config::default();
+        assert!(diff(&old, &old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.agents.defaults.model = "deepseek-chat".to_string();
+        new.channels.telegram.token = "t".to_string();
+        new.channels.discord.allowed_users = vec!["1".to_string()];
+        new.tools.restrict_to_workspace = true;
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::Provider,
+                Change::Permissions,
+                Change::Channel("telegram"),
+                Change::NeedsRestart("tools"),
+            ]
+        );
+
+        // New attachment limits restart the enabled channels
+        let mut limited = old.clone();
+        limited.channels.qq.enabled = true;
+        let mut new = limited.clone();
+        new.channels.attachments.max_size_mb = 5;
+        assert_eq!(diff(&limited, &new), vec![Change::Channel("qq")]);
+    }
+}
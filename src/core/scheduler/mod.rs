@@ -40,6 +40,9 @@ pub struct ScheduledJob {
     pub deliver_response: bool,
     pub deliver_to: Option<String>,
     pub deliver_channel: Option<String>,
+    /// Model for this job's runs, instead of the chat's or the default
+    #[serde(default)]
+    pub model: Option<String>,
     pub created_at: DateTime<Utc>,
     pub last_run: Option<DateTime<Utc>>,
     pub next_run: Option<DateTime<Utc>>,
@@ -62,6 +65,7 @@ impl ScheduledJob {
             deliver_response: false,
             deliver_to: None,
             deliver_channel: None,
+            model: None,
             created_at: now,
             last_run: None,
             next_run: None,
@@ -108,18 +112,40 @@ impl ScheduledJob {
     }
 }
 
-/// Simple cron expression parser
-fn parse_cron(expr: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+/// Field value matching any time
+const CRON_ANY: u32 = 255;
+
+/// Parse `minute hour day month weekday`. Each field is a number or `*`.
+fn parse_cron_fields(expr: &str) -> Result<[u32; 5], String> {
     let parts: Vec<&str> = expr.split_whitespace().collect();
     if parts.len() != 5 {
         return Err("Invalid cron expression: expected 5 fields".to_string());
     }
 
-    let min = parts[0].parse::<u32>().map_err(|_| "Invalid minute")?;
-    let hour = parts[1].parse::<u32>().map_err(|_| "Invalid hour")?;
-    let day = parts[2].parse::<u32>().map_err(|_| "Invalid day")?;
-    let mon = parts[3].parse::<u32>().map_err(|_| "Invalid month")?;
-    let wday = parts[4].parse::<u32>().map_err(|_| "Invalid weekday")?;
+    let names = ["minute", "hour", "day", "month", "weekday"];
+    let ranges = [(0, 59), (0, 23), (1, 31), (1, 12), (0, 6)];
+    let mut fields = [CRON_ANY; 5];
+    for (i, part) in parts.iter().enumerate() {
+        if *part == "*" {
+            continue;
+        }
+        let (lo, hi) = ranges[i];
+        match part.parse::<u32>() {
+            Ok(value) if (lo..=hi).contains(&value) => fields[i] = value,
+            _ => return Err(format!("Invalid {} '{}': expected {}-{} or *", names[i], part, lo, hi)),
+        }
+    }
+    Ok(fields)
+}
+
+/// Check that a cron expression can be scheduled
+pub fn check_cron(expr: &str) -> Result<(), String> {
+    parse_cron_fields(expr).map(|_| ())
+}
+
+/// Simple cron expression parser
+fn parse_cron(expr: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let [min, hour, day, mon, wday] = parse_cron_fields(expr)?;
 
     // Very basic next occurrence calculation
     let mut next = now;
@@ -132,11 +158,11 @@ fn parse_cron(expr: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
         let next_month = next.month();
         let next_wday = next.weekday().num_days_from_sunday() as u32;
 
-        if min == next_min || min == 255 {
-            if hour == next_hour || hour == 255 {
-                if day == next_day as u32 || day == 255 {
-                    if mon == next_month as u32 || mon == 255 {
-                        if wday == next_wday || wday == 255 {
+        if min == next_min || min == CRON_ANY {
+            if hour == next_hour || hour == CRON_ANY {
+                if day == next_day as u32 || day == CRON_ANY {
+                    if mon == next_month as u32 || mon == CRON_ANY {
+                        if wday == next_wday || wday == CRON_ANY {
                             return Ok(next);
                         }
                     }
@@ -301,7 +327,10 @@ impl Scheduler {
         let channel = job.deliver_channel.clone().unwrap_or_else(|| "scheduler".to_string());
         let chat_id = job.deliver_to.clone().unwrap_or_else(|| "default".to_string());
 
-        let message = InboundMessage::new(&channel, "scheduler", &chat_id, &job.message);
+        let mut message = InboundMessage::new(&channel, "scheduler", &chat_id, &job.message);
+        if let Some(model) = &job.model {
+            message.metadata.insert("model".to_string(), model.clone());
+        }
 
         self.bus.publish_inbound(message).await;
 
@@ -1,7 +1,19 @@
 //! Anthropic provider - Claude API.
+//!
+//! Messages and tools arrive in the OpenAI format and are converted to the
+//! Messages API. Prompt caching uses three `cache_control` breakpoints: the
+//! last tool definition, the system prompt and the last message, so each
+//! tool-loop iteration and each new turn re-reads the shared prefix from the
+//! cache instead of paying for it again. Images in user messages become
+//! `image` blocks with a base64 or URL source.
+//!
+//! With extended thinking, the signed `thinking` and `redacted_thinking`
+//! blocks of a tool-use turn are kept as the assistant message's
+//! `thinking_blocks` and replayed first in that turn, as the API requires.
 
-use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
+use crate::llm::providers::openai_compat::{content_images, content_text, extract_tool_args, split_data_url};
+use crate::llm::providers::{LLMProvider, ProviderError};
+use crate::types::{LLMResponse, ToolCall, Usage};
 use serde_json::{json, Value};
 
 /// Anthropic provider
@@ -9,6 +21,8 @@ use serde_json::{json, Value};
 pub struct AnthropicProvider {
     api_key: String,
     api_base: String,
+    /// Extended thinking budget in tokens; 0 is off
+    thinking_budget: u32,
 }
 
 impl AnthropicProvider {
@@ -16,8 +30,140 @@ impl AnthropicProvider {
         Self {
             api_key,
             api_base: "https://api.anthropic.com/v1".to_string(),
+            thinking_budget: 0,
         }
     }
+
+    pub fn with_thinking_budget(mut self, budget: u32) -> Self {
+        self.thinking_budget = budget;
+        self
+    }
+
+    /// Messages API request body
+    fn request_body(&self, messages: &[Value], model: &str, tools: &[Value]) -> Value {
+        let system = messages
+            .iter()
+            .filter(|m| m["role"] == "system")
+            .filter_map(|m| m["content"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let mut body = json!({
+            "model": model,
+            "messages": convert_messages(messages),
+            "max_tokens": 4096
+        });
+        if !system.is_empty() {
+            body["system"] = json!([{"type": "text", "text": system, "cache_control": ephemeral()}]);
+        }
+        if !tools.is_empty() {
+            body["tools"] = convert_tools(tools);
+        }
+        if self.thinking_budget > 0 {
+            // The budget counts towards max_tokens, which must stay above it
+            body["thinking"] = json!({"type": "enabled", "budget_tokens": self.thinking_budget});
+            body["max_tokens"] = json!(self.thinking_budget + 4096);
+        }
+        body
+    }
+}
+
+fn ephemeral() -> Value {
+    json!({"type": "ephemeral"})
+}
+
+/// Convert OpenAI-style tool definitions, caching up to the last one
+fn convert_tools(tools: &[Value]) -> Value {
+    let mut converted: Vec<Value> = tools
+        .iter()
+        .map(|t| {
+            let function = &t["function"];
+            json!({
+                "name": function["name"],
+                "description": function["description"],
+                "input_schema": function["parameters"],
+            })
+        })
+        .collect();
+    if let Some(last) = converted.last_mut() {
+        last["cache_control"] = ephemeral();
+    }
+    json!(converted)
+}
+
+/// Convert OpenAI-style messages to content blocks. Tool calls become
+/// `tool_use` blocks and tool results become `tool_result` blocks in a user
+/// message. The last block is a cache breakpoint for the history so far.
+fn convert_messages(messages: &[Value]) -> Vec<Value> {
+    let mut converted: Vec<Value> = Vec::new();
+    for m in messages.iter().filter(|m| m["role"] != "system") {
+        let text = content_text(&m["content"]);
+        let text = text.as_str();
+        let (role, blocks) = match m["role"].as_str() {
+            Some("tool") => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": m["tool_call_id"],
+                    "content": text,
+                })],
+            ),
+            Some("assistant") => {
+                let mut blocks: Vec<Value> = m["thinking_blocks"].as_array().cloned().unwrap_or_default();
+                if !text.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for tc in m["tool_calls"].as_array().into_iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": tc["id"],
+                        "name": tc["function"]["name"],
+                        "input": extract_tool_args(&tc["function"]["arguments"]),
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            _ => {
+                // Images go before the text that asks about them
+                let mut blocks: Vec<Value> = content_images(&m["content"]).into_iter().map(image_block).collect();
+                if !text.is_empty() || blocks.is_empty() {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                ("user", blocks)
+            }
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // Consecutive messages with the same role, such as several tool results, are merged
+        match converted.last_mut() {
+            Some(last) if last["role"] == role => {
+                if let Some(content) = last["content"].as_array_mut() {
+                    content.extend(blocks);
+                }
+            }
+            _ => converted.push(json!({"role": role, "content": blocks})),
+        }
+    }
+
+    if let Some(block) = converted
+        .last_mut()
+        .and_then(|m| m["content"].as_array_mut())
+        .and_then(|blocks| blocks.last_mut())
+    {
+        block["cache_control"] = ephemeral();
+    }
+    converted
+}
+
+/// An `image` block for an image URL or `data:` URL
+fn image_block(url: &str) -> Value {
+    let source = match split_data_url(url) {
+        Some((media_type, data)) => json!({"type": "base64", "media_type": media_type, "data": data}),
+        None => json!({"type": "url", "url": url}),
+    };
+    json!({"type": "image", "source": source})
 }
 
 #[async_trait::async_trait]
@@ -27,43 +173,26 @@ impl LLMProvider for AnthropicProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
-        let client = reqwest::Client::new();
-
-        // Convert messages to Anthropic format
-        let anthropic_messages: Vec<Value> = messages.iter()
-            .filter(|m| m["role"] != "system")
-            .cloned()
-            .collect();
-
-        let system_message = messages.iter()
-            .find(|m| m["role"] == "system")
-            .and_then(|m| m["content"].as_str())
-            .unwrap_or("");
-
-        let body = json!({
-            "model": model,
-            "messages": anthropic_messages,
-            "system": system_message,
-            "tools": if tools.is_empty() { json!(null) } else { json!(tools) },
-            "max_tokens": 4096
-        });
-
-        let response = client
-            .post(&self.api_base)
+    ) -> Result<LLMResponse, ProviderError> {
+        let response = reqwest::Client::new()
+            .post(format!("{}/messages", self.api_base))
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
-            .json(&body)
+            .json(&self.request_body(messages, model, tools))
             .send()
             .await
             .map_err(|e| format!("Request failed: {}", e))?;
 
         if !response.status().is_success() {
             let error = response.text().await.unwrap_or_default();
-            return Err(format!("API error: {}", error));
+            return Err(format!("API error: {}", error).into());
         }
 
-        parse_response(response).await
+        let response_json: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Parse error: {}", e))?;
+        Ok(parse_response(&response_json))
     }
 
     fn name(&self) -> &str {
@@ -75,40 +204,165 @@ impl LLMProvider for AnthropicProvider {
     }
 }
 
-async fn parse_response(response: reqwest::Response) -> Result<LLMResponse, String> {
-    let response_json: Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Parse error: {}", e))?;
-
-    let content = response_json["content"][0]["text"].as_str()
-        .map(|s| s.to_string());
-
-    let tool_calls: Vec<crate::types::ToolCall> = if let Some(tc_array) = response_json["content"].as_array() {
-        tc_array.iter()
-            .filter(|tc| tc["type"] == "tool_use")
-            .map(|tc| crate::types::ToolCall {
-                id: tc["id"].as_str().unwrap_or("").to_string(),
-                name: tc["name"].as_str().unwrap_or("").to_string(),
-                arguments: if tc["input"].is_null() || !tc["input"].is_object() {
-                    json!({})
-                } else {
-                    tc["input"].clone()
-                },
-            })
-            .collect()
-    } else {
-        vec![]
+fn parse_response(response_json: &Value) -> LLMResponse {
+    // With extended thinking, `thinking` blocks come before the text
+    let blocks = |kind: &str, field: &str| -> Option<String> {
+        let parts: Vec<&str> = response_json["content"].as_array()?
+            .iter()
+            .filter(|b| b["type"] == kind)
+            .filter_map(|b| b[field].as_str())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join("\n\n"))
     };
+    let content = blocks("text", "text");
+    let reasoning = blocks("thinking", "thinking");
+    let thinking_blocks: Vec<Value> = response_json["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|b| b["type"] == "thinking" || b["type"] == "redacted_thinking")
+        .cloned()
+        .collect();
+
+    let tool_calls: Vec<ToolCall> = response_json["content"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|tc| tc["type"] == "tool_use")
+        .map(|tc| ToolCall {
+            id: tc["id"].as_str().unwrap_or("").to_string(),
+            name: tc["name"].as_str().unwrap_or("").to_string(),
+            arguments: if tc["input"].is_object() { tc["input"].clone() } else { json!({}) },
+        })
+        .collect();
 
     let finish_reason = response_json["stop_reason"]
         .as_str()
         .unwrap_or("stop")
         .to_string();
 
-    Ok(LLMResponse {
+    // `input_tokens` excludes cached tokens; the prompt total includes them
+    let usage = response_json["usage"].as_object().map(|usage| {
+        let count = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0);
+        let (read, written) = (count("cache_read_input_tokens"), count("cache_creation_input_tokens"));
+        Usage {
+            prompt_tokens: count("input_tokens") + read + written,
+            completion_tokens: count("output_tokens"),
+            cache_read_tokens: read,
+            cache_write_tokens: written,
+        }
+    });
+
+    LLMResponse {
         content,
         tool_calls,
         finish_reason,
-    })
+        usage,
+        reasoning,
+        thinking_blocks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_body_cache_breakpoints() {
+        let provider = AnthropicProvider::new("key".to_string());
+        let messages = [
+            json!({"role": "system", "content": "You are openat."}),
+            json!({"role": "user", "content": "list files"}),
+            json!({"role": "assistant", "content": "", "tool_calls": [
+                {"id": "t1", "type": "function", "function": {"name": "list_dir", "arguments": {"path": "."}}},
+                {"id": "t2", "type": "function", "function": {"name": "exec", "arguments": "{\"command\":\"ls\"}"}}
+            ]}),
+            json!({"role": "tool", "tool_call_id": "t1", "name": "list_dir", "content": "a.txt"}),
+            json!({"role": "tool", "tool_call_id": "t2", "name": "exec", "content": "a.txt"}),
+        ];
+        let tools = [json!({"type": "function", "function": {
+            "name": "exec", "description": "Run a command", "parameters": {"type": "object"}
+        }})];
+        let body = provider.request_body(&messages, "claude-sonnet-4-5", &tools);
+
+        assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(body["tools"][0]["input_schema"]["type"], "object");
+        assert_eq!(body["tools"][0]["cache_control"]["type"], "ephemeral");
+
+        let converted = body["messages"].as_array().unwrap();
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[1]["content"][1]["input"]["command"], "ls");
+        // Both tool results share one user message, which ends in the breakpoint
+        let results = converted[2]["content"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].get("cache_control").is_none());
+        assert_eq!(results[1]["cache_control"]["type"], "ephemeral");
+    }
+
+    #[test]
+    fn test_convert_image_parts() {
+        let converted = convert_messages(&[json!({"role": "user", "content": [
+            {"type": "text", "text": "What is this?"},
+            {"type": "image_url", "image_url": {"url": "data:image/jpeg;base64,/9j/4AAQ"}},
+            {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}
+        ]})]);
+        let blocks = converted[0]["content"].as_array().unwrap();
+        assert_eq!(blocks[0]["source"], json!({"type": "base64", "media_type": "image/jpeg", "data": "/9j/4AAQ"}));
+        assert_eq!(blocks[1]["source"], json!({"type": "url", "url": "https://example.com/cat.png"}));
+        assert_eq!(blocks[2]["text"], "What is this?");
+    }
+
+    #[test]
+    fn test_parse_response_usage() {
+        let response = parse_response(&json!({
+            "content": [
+                {"type": "thinking", "thinking": "Check the files."},
+                {"type": "text", "text": "Done."}
+            ],
+            "stop_reason": "end_turn",
+            "usage": {
+                "input_tokens": 20,
+                "output_tokens": 5,
+                "cache_read_input_tokens": 1800,
+                "cache_creation_input_tokens": 200
+            }
+        }));
+        assert_eq!(response.content.as_deref(), Some("Done."));
+        assert_eq!(response.reasoning.as_deref(), Some("Check the files."));
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 2020);
+        assert_eq!(usage.cache_read_tokens, 1800);
+        assert_eq!(usage.cache_write_tokens, 200);
+    }
+
+    #[test]
+    fn test_thinking_tool_round_trip() {
+        let provider = AnthropicProvider::new("key".to_string()).with_thinking_budget(2048);
+        let response = parse_response(&json!({
+            "content": [
+                {"type": "thinking", "thinking": "List the files first.", "signature": "sig-1"},
+                {"type": "redacted_thinking", "data": "opaque"},
+                {"type": "tool_use", "id": "t1", "name": "list_dir", "input": {"path": "."}}
+            ],
+            "stop_reason": "tool_use"
+        }));
+        assert_eq!(response.thinking_blocks.len(), 2);
+
+        // The assistant message as the executor writes it for the next request
+        let messages = [
+            json!({"role": "user", "content": "list files"}),
+            json!({
+                "role": "assistant",
+                "content": "",
+                "thinking_blocks": response.thinking_blocks,
+                "tool_calls": [{"id": "t1", "type": "function", "function": {"name": "list_dir", "arguments": {"path": "."}}}]
+            }),
+            json!({"role": "tool", "tool_call_id": "t1", "name": "list_dir", "content": "a.txt"}),
+        ];
+        let body = provider.request_body(&messages, "claude-sonnet-4-5", &[]);
+        let turn = body["messages"][1]["content"].as_array().unwrap();
+        assert_eq!(turn[0], json!({"type": "thinking", "thinking": "List the files first.", "signature": "sig-1"}));
+        assert_eq!(turn[1], json!({"type": "redacted_thinking", "data": "opaque"}));
+        assert_eq!(turn[2]["type"], "tool_use");
+    }
 }
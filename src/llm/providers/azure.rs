@@ -0,0 +1,216 @@
+//! Azure OpenAI provider - models served from deployments of an Azure resource.
+//!
+//! Requests go to `{endpoint}/openai/deployments/{deployment}/chat/completions`
+//! with an `api-version` query and an `api-key` header. The body and reply
+//! are the OpenAI chat completions format. Prompts or replies blocked by
+//! Azure's content filter are returned as `ProviderError::ContentFilter`
+//! with the filtered categories and their severity.
+
+use crate::config::AzureConfig;
+use crate::llm::providers::openai_compat::{parse_openai_json, request_body};
+use crate::llm::providers::{LLMProvider, ProviderError};
+use crate::types::LLMResponse;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const DEFAULT_API_VERSION: &str = "2024-10-21";
+
+/// Categories Azure's content filter blocked and their severity, from a
+/// `content_filter_result(s)` object
+fn filtered_categories(results: &Value) -> Vec<(String, String)> {
+    results
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter(|(_, result)| result["filtered"].as_bool() == Some(true))
+        .map(|(category, result)| {
+            let severity = result["severity"].as_str().unwrap_or("detected");
+            (category.clone(), severity.to_string())
+        })
+        .collect()
+}
+
+/// The content filter error for an error response, when the filter caused it
+fn filtered_prompt(body: &Value) -> Option<ProviderError> {
+    let error = &body["error"];
+    (error["code"] == "content_filter").then(|| ProviderError::ContentFilter {
+        prompt: true,
+        categories: filtered_categories(&error["innererror"]["content_filter_result"]),
+    })
+}
+
+/// The content filter error for a completed reply that the filter cut off
+fn filtered_reply(body: &Value) -> Option<ProviderError> {
+    let choice = &body["choices"][0];
+    (choice["finish_reason"] == "content_filter").then(|| ProviderError::ContentFilter {
+        prompt: false,
+        categories: filtered_categories(&choice["content_filter_results"]),
+    })
+}
+
+/// Azure OpenAI provider
+#[derive(Debug, Clone)]
+pub struct AzureProvider {
+    api_key: String,
+    endpoint: String,
+    api_version: String,
+    deployments: BTreeMap<String, String>,
+    reasoning_effort: String,
+}
+
+impl AzureProvider {
+    pub fn new(api_key: String, config: &AzureConfig) -> Self {
+        let api_version = match config.api_version.as_str() {
+            "" => DEFAULT_API_VERSION,
+            version => version,
+        };
+        Self {
+            api_key,
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            api_version: api_version.to_string(),
+            deployments: config.deployments.clone(),
+            reasoning_effort: String::new(),
+        }
+    }
+
+    /// Reasoning effort for o-series deployments
+    pub fn with_reasoning_effort(mut self, effort: &str) -> Self {
+        self.reasoning_effort = effort.to_string();
+        self
+    }
+
+    /// Chat completions URL of the deployment serving `model`
+    fn chat_url(&self, model: &str) -> String {
+        let deployment = self.deployments.get(model).map(String::as_str).unwrap_or(model);
+        format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint, deployment, self.api_version
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for AzureProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        model: &str,
+        tools: &[Value],
+    ) -> Result<LLMResponse, ProviderError> {
+        let mut body = request_body(messages, model, tools);
+        if !self.reasoning_effort.is_empty() {
+            body["reasoning_effort"] = json!(self.reasoning_effort);
+        }
+        let response = reqwest::Client::new()
+            .post(self.chat_url(model))
+            .header("api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        let success = response.status().is_success();
+        let text = response.text().await.map_err(|e| format!("Read error: {}", e))?;
+        let body: Value = serde_json::from_str(&text).unwrap_or_default();
+        if !success {
+            return Err(filtered_prompt(&body).unwrap_or_else(|| format!("Azure API error: {}", text).into()));
+        }
+        if let Some(filtered) = filtered_reply(&body) {
+            return Err(filtered);
+        }
+        Ok(parse_openai_json(&body))
+    }
+
+    fn name(&self) -> &str {
+        "azure"
+    }
+
+    fn api_base(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_url() {
+        let config = AzureConfig {
+            endpoint: "https://contoso.openai.azure.com/".to_string(),
+            deployments: [("gpt-4o".to_string(), "chat-prod".to_string())].into(),
+            ..Default::default()
+        };
+        let provider = AzureProvider::new("key".to_string(), &config);
+        assert_eq!(
+            provider.chat_url("gpt-4o"),
+            "https://contoso.openai.azure.com/openai/deployments/chat-prod/chat/completions?api-version=2024-10-21"
+        );
+        assert!(provider.chat_url("gpt-4o-mini").contains("/deployments/gpt-4o-mini/"));
+    }
+
+    #[test]
+    fn test_content_filter_errors() {
+        let body = json!({"error": {
+            "code": "content_filter",
+            "message": "The response was filtered",
+            "innererror": {
+                "code": "ResponsibleAIPolicyViolation",
+                "content_filter_result": {
+                    "hate": {"filtered": false, "severity": "safe"},
+                    "violence": {"filtered": true, "severity": "medium"},
+                    "jailbreak": {"filtered": true, "detected": true}
+                }
+            }
+        }});
+        let filtered = filtered_prompt(&body).unwrap();
+        assert_eq!(
+            filtered.to_string(),
+            "Content filter blocked the prompt: jailbreak (detected), violence (medium)"
+        );
+
+        let reply = json!({"choices": [{"finish_reason": "content_filter", "content_filter_results": {}}]});
+        assert_eq!(filtered_reply(&reply).unwrap().to_string(), "Content filter blocked the reply");
+        assert!(filtered_prompt(&json!({"error": {"code": "429"}})).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_content_filter_through_chat() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 64 * 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            let body = json!({"error": {
+                "code": "content_filter",
+                "innererror": {"content_filter_result": {"violence": {"filtered": true, "severity": "high"}}}
+            }})
+            .to_string();
+            let response = format!(
+                "HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            let _ = stream.shutdown().await;
+        });
+
+        let config = AzureConfig {
+            endpoint: format!("http://{}", addr),
+            ..Default::default()
+        };
+        let provider: Box<dyn LLMProvider> = Box::new(AzureProvider::new("key".to_string(), &config));
+        let error = provider.chat(&[json!({"role": "user", "content": "hi"})], "gpt-4o", &[]).await.unwrap_err();
+        match error {
+            ProviderError::ContentFilter { prompt, categories } => {
+                assert!(prompt);
+                assert_eq!(categories, vec![("violence".to_string(), "high".to_string())]);
+            }
+            other => panic!("expected a content filter error, got {}", other),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Custom provider - any OpenAI-compatible API declared under `providers.custom`.
+
+use crate::config::CustomProviderConfig;
+use crate::llm::providers::openai_compat::OpenAICompatConfig;
+use crate::llm::providers::{LLMProvider, ProviderError};
+use crate::types::LLMResponse;
+use serde_json::Value;
+
+/// OpenAI-compatible provider configured at runtime
+#[derive(Debug, Clone)]
+pub struct CustomProvider {
+    config: OpenAICompatConfig,
+}
+
+impl CustomProvider {
+    pub fn new(name: &str, provider: &CustomProviderConfig) -> Self {
+        let mut config = OpenAICompatConfig::new(
+            provider.api_key.clone(),
+            provider.api_base.trim_end_matches('/').to_string(),
+            name,
+        );
+        for (key, value) in &provider.headers {
+            config = config.with_header(key.as_str(), value.clone());
+        }
+        Self { config }
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for CustomProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        model: &str,
+        tools: &[Value],
+    ) -> Result<LLMResponse, ProviderError> {
+        self.config.chat_impl(messages, model, tools).await
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn api_base(&self) -> &str {
+        &self.config.api_base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_provider() {
+        let config = CustomProviderConfig {
+            api_base: "https://api.siliconflow.cn/v1/".to_string(),
+            headers: [("X-Team".to_string(), "bots".to_string())].into(),
+            ..Default::default()
+        };
+        let provider = CustomProvider::new("siliconflow", &config);
+        assert_eq!(provider.name(), "siliconflow");
+        assert_eq!(provider.api_base(), "https://api.siliconflow.cn/v1");
+        assert_eq!(provider.config.extra_headers["X-Team"], "bots");
+    }
+}
@@ -1,9 +1,9 @@
 //! DeepSeek provider - DeepSeek Chat API.
 
-use crate::llm::providers::openai_compat::OpenAICompatConfig;
+use crate::llm::providers::openai_compat::{request_body, OpenAICompatConfig};
 use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
-use serde_json::{json, Value};
+use crate::llm::providers::{LLMProvider, ProviderError};
+use serde_json::Value;
 
 /// DeepSeek provider
 #[derive(Debug, Clone)]
@@ -31,9 +31,10 @@ impl LLMProvider for DeepSeekProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
-        // DeepSeek uses "deepseek-chat" as default model
-        let model_name = if model.is_empty() || model.starts_with("deepseek-") {
+    ) -> Result<LLMResponse, ProviderError> {
+        // DeepSeek uses "deepseek-chat" as default model; `deepseek-reasoner`
+        // returns its trace as `reasoning_content`
+        let model_name = if model.is_empty() {
             "deepseek-chat".to_string()
         } else {
             model.to_string()
@@ -43,12 +44,7 @@ impl LLMProvider for DeepSeekProvider {
         let client = reqwest::Client::new();
         let config = &self.config;
 
-        let body = json!({
-            "model": model_name,
-            "messages": messages,
-            "tools": tools,
-            "tool_choice": if tools.is_empty() { json!(null) } else { json!("auto") }
-        });
+        let body = request_body(messages, &model_name, tools);
 
         let mut request = client
             .post(&config.chat_url())
@@ -56,7 +52,7 @@ impl LLMProvider for DeepSeekProvider {
 
         // Add extra headers
         for (key, value) in &config.extra_headers {
-            request = request.header(*key, value);
+            request = request.header(key, value);
         }
 
         let response = request
@@ -67,14 +63,14 @@ impl LLMProvider for DeepSeekProvider {
 
         if !response.status().is_success() {
             let error = response.text().await.unwrap_or_default();
-            return Err(format!("DeepSeek API error: {}", error));
+            return Err(format!("DeepSeek API error: {}", error).into());
         }
 
-        crate::llm::providers::openai_compat::parse_openai_response(response).await
+        crate::llm::providers::openai_compat::parse_openai_response(response).await.map_err(ProviderError::from)
     }
 
     fn name(&self) -> &str {
-        self.config.name
+        &self.config.name
     }
 
     fn api_base(&self) -> &str {
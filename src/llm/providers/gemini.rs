@@ -1,7 +1,8 @@
 //! Gemini provider - Google AI.
 
 use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
+use crate::llm::providers::openai_compat::{content_images, content_text, split_data_url};
+use crate::llm::providers::{LLMProvider, ProviderError};
 use serde_json::{json, Value};
 
 /// Gemini provider
@@ -27,7 +28,7 @@ impl LLMProvider for GeminiProvider {
         messages: &[Value],
         model: &str,
         _tools: &[Value],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         let client = reqwest::Client::new();
 
         // Gemini has different format
@@ -36,9 +37,7 @@ impl LLMProvider for GeminiProvider {
             .map(|m| {
                 json!({
                     "role": if m["role"] == "user" { "user" } else { "model" },
-                    "parts": [{
-                        "text": m["content"]
-                    }]
+                    "parts": convert_parts(&m["content"])
                 })
             })
             .collect();
@@ -68,10 +67,10 @@ impl LLMProvider for GeminiProvider {
 
         if !response.status().is_success() {
             let error = response.text().await.unwrap_or_default();
-            return Err(format!("API error: {}", error));
+            return Err(format!("API error: {}", error).into());
         }
 
-        parse_response(response).await
+        parse_response(response).await.map_err(ProviderError::from)
     }
 
     fn name(&self) -> &str {
@@ -83,6 +82,19 @@ impl LLMProvider for GeminiProvider {
     }
 }
 
+/// Text and inline images of a message. Gemini cannot fetch arbitrary image
+/// URLs, so those are passed on as text.
+fn convert_parts(content: &Value) -> Vec<Value> {
+    let mut parts = vec![json!({ "text": content_text(content) })];
+    for url in content_images(content) {
+        parts.push(match split_data_url(url) {
+            Some((mime_type, data)) => json!({ "inline_data": { "mime_type": mime_type, "data": data } }),
+            None => json!({ "text": format!("Image: {}", url) }),
+        });
+    }
+    parts
+}
+
 async fn parse_response(response: reqwest::Response) -> Result<LLMResponse, String> {
     let response_json: Value = response
         .json()
@@ -99,5 +111,24 @@ async fn parse_response(response: reqwest::Response) -> Result<LLMResponse, Stri
         content: Some(content),
         tool_calls: vec![],
         finish_reason: "stop".to_string(),
+        usage: None,
+        reasoning: None,
+        thinking_blocks: vec![],
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_parts() {
+        let parts = convert_parts(&json!([
+            {"type": "text", "text": "Read the sign"},
+            {"type": "image_url", "image_url": {"url": "data:image/webp;base64,UklGR"}}
+        ]));
+        assert_eq!(parts[0]["text"], "Read the sign");
+        assert_eq!(parts[1]["inline_data"], json!({"mime_type": "image/webp", "data": "UklGR"}));
+        assert_eq!(convert_parts(&json!("hi")), vec![json!({"text": "hi"})]);
+    }
+}
@@ -2,7 +2,7 @@
 
 use crate::llm::providers::openai_compat::OpenAICompatConfig;
 use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
+use crate::llm::providers::{LLMProvider, ProviderError};
 use serde_json::Value;
 
 /// Groq provider
@@ -30,12 +30,12 @@ impl LLMProvider for GroqProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         self.config.chat_impl(messages, model, tools).await
     }
 
     fn name(&self) -> &str {
-        self.config.name
+        &self.config.name
     }
 
     fn api_base(&self) -> &str {
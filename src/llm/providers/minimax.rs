@@ -1,8 +1,9 @@
 //! MiniMax provider - Chinese LLM.
 
 use crate::types::{LLMResponse, ToolCall};
-use crate::llm::providers::LLMProvider;
-use serde_json::{json, Value};
+use crate::llm::providers::{LLMProvider, ProviderError};
+use crate::llm::providers::openai_compat::request_body;
+use serde_json::Value;
 
 /// MiniMax provider
 #[derive(Debug)]
@@ -27,19 +28,14 @@ impl LLMProvider for MiniMaxProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         let client = reqwest::Client::new();
 
         // MiniMax expects model ID without provider prefix
         let model_id = model.split('/').last().unwrap_or(model);
 
         // MiniMax uses OpenAI-compatible API
-        let body = json!({
-            "model": model_id,
-            "messages": messages,
-            "tools": tools,
-            "tool_choice": if tools.is_empty() { json!(null) } else { json!("auto") }
-        });
+        let body = request_body(messages, model_id, tools);
 
         let response = client
             .post(&self.api_base)
@@ -58,7 +54,7 @@ impl LLMProvider for MiniMaxProvider {
         tracing::debug!("MiniMax response body: {}", text);
 
         if !status.is_success() {
-            return Err(format!("API error (status {}): {}", status, text));
+            return Err(format!("API error (status {}): {}", status, text).into());
         }
 
         // Parse the text as JSON
@@ -99,6 +95,9 @@ impl LLMProvider for MiniMaxProvider {
             content,
             tool_calls,
             finish_reason,
+            usage: None,
+            reasoning: None,
+            thinking_blocks: vec![],
         })
     }
 
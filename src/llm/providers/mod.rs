@@ -1,14 +1,19 @@
 //! LLM providers implementations.
 
 mod anthropic;
+mod azure;
+mod custom;
 mod deepseek;
 mod gemini;
 mod groq;
 mod minimax;
 mod moonshot;
+mod ollama;
 mod openai;
 mod openai_compat;
 mod openrouter;
+mod router;
+mod speech;
 mod transcription;
 mod vllm;
 mod zhipu;
@@ -16,19 +21,74 @@ mod zhipu;
 pub use openai_compat::OpenAICompatConfig;
 
 pub use anthropic::AnthropicProvider;
+pub use azure::AzureProvider;
+pub use custom::CustomProvider;
 pub use deepseek::DeepSeekProvider;
 pub use gemini::GeminiProvider;
 pub use groq::GroqProvider;
 pub use minimax::MiniMaxProvider;
 pub use moonshot::MoonshotProvider;
+pub use ollama::OllamaProvider;
 pub use openai::OpenAIProvider;
 pub use openrouter::OpenRouterProvider;
-pub use transcription::GroqTranscriptionProvider;
+pub use router::RouterProvider;
+pub use speech::{OpenAISpeechProvider, SpeechProvider};
+pub use transcription::{GroqTranscriptionProvider, OpenAITranscriptionProvider, TranscriptionProvider};
 pub use vllm::VLLMProvider;
 pub use zhipu::ZhipuProvider;
 
 use crate::config::Config;
 use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+/// Why a chat request failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProviderError {
+    /// The provider's content filter blocked the prompt or the reply
+    ContentFilter {
+        /// Whether the prompt was blocked, rather than the reply
+        prompt: bool,
+        /// Filtered categories and their severity, such as `("violence", "medium")`
+        categories: Vec<(String, String)>,
+    },
+    /// Any other failure, as a message
+    Other(String),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ContentFilter { prompt, categories } => {
+                let what = if *prompt { "the prompt" } else { "the reply" };
+                write!(f, "Content filter blocked {}", what)?;
+                let categories: Vec<String> = categories
+                    .iter()
+                    .map(|(category, severity)| format!("{} ({})", category, severity))
+                    .collect();
+                if !categories.is_empty() {
+                    write!(f, ": {}", categories.join(", "))?;
+                }
+                Ok(())
+            }
+            Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+impl From<String> for ProviderError {
+    fn from(message: String) -> Self {
+        Self::Other(message)
+    }
+}
+
+impl From<&str> for ProviderError {
+    fn from(message: &str) -> Self {
+        Self::Other(message.to_string())
+    }
+}
 
 /// Trait for LLM providers
 #[async_trait::async_trait]
@@ -39,7 +99,7 @@ pub trait LLMProvider: Send + Sync {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<crate::types::LLMResponse, String>;
+    ) -> Result<crate::types::LLMResponse, ProviderError>;
 
     /// Provider name
     fn name(&self) -> &str;
@@ -48,67 +108,88 @@ pub trait LLMProvider: Send + Sync {
     fn api_base(&self) -> &str;
 }
 
-/// Get API key from env or config - helper function
-fn get_api_key(env_key: &str, config: &Config) -> Option<String> {
-    std::env::var(env_key).ok().filter(|k| !k.is_empty())
-}
-
-/// Create a provider based on configuration priority
-pub fn create_provider(config: &Config) -> Box<dyn LLMProvider> {
-    // Priority: OpenRouter > Anthropic > OpenAI > Groq > Gemini > MiniMax > DeepSeek > Zhipu > Moonshot
-    if let Some(key) = get_api_key("OPENROUTER_API_KEY", config) {
-        tracing::debug!("Using OpenRouter from env");
-        return Box::new(OpenRouterProvider::new(key));
-    }
-    if let Some(key) = get_api_key("ANTHROPIC_API_KEY", config) {
-        return Box::new(AnthropicProvider::new(key));
-    }
-    if let Some(key) = get_api_key("OPENAI_API_KEY", config) {
-        return Box::new(OpenAIProvider::new(key, config.providers.openai.api_base.clone()));
+/// Create the provider named `name`, if it is configured.
+/// Keys come from `Providers::api_key`: the environment, then the config.
+fn build_provider(name: &str, config: &Config) -> Option<Box<dyn LLMProvider>> {
+    let providers = &config.providers;
+    if let Some(custom) = providers.custom.get(name) {
+        return Some(Box::new(CustomProvider::new(name, custom)));
     }
-    if let Some(key) = get_api_key("GROQ_API_KEY", config) {
-        return Box::new(GroqProvider::new(key));
+    if name == "ollama" {
+        return Some(Box::new(OllamaProvider::new(&providers.ollama)));
     }
-    if let Some(key) = get_api_key("GEMINI_API_KEY", config) {
-        return Box::new(GeminiProvider::new(key));
+    if name == "vllm" {
+        let key = providers.api_key(name).unwrap_or_default();
+        return Some(Box::new(VLLMProvider::new(key, providers.vllm.api_base.clone(), None)));
     }
-    if let Some(key) = get_api_key("MINIMAX_API_KEY", config) {
-        return Box::new(MiniMaxProvider::new(key));
-    }
-    if let Some(key) = get_api_key("DEEPSEEK_API_KEY", config) {
-        return Box::new(DeepSeekProvider::new(key, None));
-    }
-    if let Some(key) = get_api_key("ZHIPU_API_KEY", config) {
-        return Box::new(zhipu::ZhipuProvider::new(key, None));
+    let key = providers.api_key(name)?;
+    let api_base = providers.get(name).and_then(|p| p.api_base.clone());
+    let defaults = &config.agents.defaults;
+    Some(match name {
+        "openrouter" => Box::new(OpenRouterProvider::new(key)),
+        "anthropic" => Box::new(AnthropicProvider::new(key).with_thinking_budget(defaults.thinking_budget)),
+        "openai" => Box::new(OpenAIProvider::new(key, api_base).with_reasoning_effort(&defaults.reasoning_effort)),
+        "groq" => Box::new(GroqProvider::new(key)),
+        "gemini" => Box::new(GeminiProvider::new(key)),
+        "minimax" => Box::new(MiniMaxProvider::new(key)),
+        "deepseek" => Box::new(DeepSeekProvider::new(key, None)),
+        "zhipu" => Box::new(ZhipuProvider::new(key, None)),
+        "moonshot" => Box::new(MoonshotProvider::new(key, None)),
+        "azure" => Box::new(AzureProvider::new(key, &providers.azure).with_reasoning_effort(&defaults.reasoning_effort)),
+        _ => return None,
+    })
+}
+
+/// Create a provider that routes each model to the configured provider
+/// serving it; see `Providers::route`.
+pub fn create_provider(config: &Config) -> Box<dyn LLMProvider> {
+    Box::new(RouterProvider::new(config))
+}
+
+/// Create the transcription provider for voice messages, if one is configured.
+/// See `TranscriptionConfig`.
+pub fn create_transcriber(config: &Config) -> Option<Arc<dyn TranscriptionProvider>> {
+    let settings = &config.audio.transcription;
+    let key = |provider: &str| match settings.api_key.as_str() {
+        "" => config.providers.api_key(provider),
+        key => Some(key.to_string()),
+    };
+    if let Some(api_base) = &settings.api_base {
+        let api_key = key("openai").unwrap_or_default();
+        return Some(Arc::new(OpenAITranscriptionProvider::new(api_base, api_key, &settings.model)));
     }
-    if let Some(key) = get_api_key("MOONSHOT_API_KEY", config) {
-        return Box::new(moonshot::MoonshotProvider::new(key, None));
+    let groq = || -> Option<Arc<dyn TranscriptionProvider>> {
+        let provider = GroqTranscriptionProvider::new(Some(key("groq")?)).with_model(&settings.model);
+        Some(Arc::new(provider))
+    };
+    let openai = || -> Option<Arc<dyn TranscriptionProvider>> {
+        let api_base = config.providers.openai.api_base.as_deref().unwrap_or("https://api.openai.com/v1");
+        Some(Arc::new(OpenAITranscriptionProvider::new(api_base, key("openai")?, &settings.model)))
+    };
+    match settings.provider.as_str() {
+        "groq" => groq(),
+        "openai" => openai(),
+        _ => groq().or_else(openai),
     }
+}
 
-    // Fall back to config file values
-    if !config.providers.openrouter.api_key.is_empty() {
-        Box::new(OpenRouterProvider::new(config.providers.openrouter.api_key.clone()))
-    } else if !config.providers.anthropic.api_key.is_empty() {
-        Box::new(AnthropicProvider::new(config.providers.anthropic.api_key.clone()))
-    } else if !config.providers.openai.api_key.is_empty() {
-        Box::new(OpenAIProvider::new(
-            config.providers.openai.api_key.clone(),
-            config.providers.openai.api_base.clone(),
-        ))
-    } else if !config.providers.groq.api_key.is_empty() {
-        Box::new(GroqProvider::new(config.providers.groq.api_key.clone()))
-    } else if !config.providers.gemini.api_key.is_empty() {
-        Box::new(GeminiProvider::new(config.providers.gemini.api_key.clone()))
-    } else if !config.providers.minimax.api_key.is_empty() {
-        Box::new(minimax::MiniMaxProvider::new(config.providers.minimax.api_key.clone()))
-    } else if !config.providers.deepseek.api_key.is_empty() {
-        Box::new(DeepSeekProvider::new(config.providers.deepseek.api_key.clone(), None))
-    } else if !config.providers.zhipu.api_key.is_empty() {
-        Box::new(zhipu::ZhipuProvider::new(config.providers.zhipu.api_key.clone(), None))
-    } else if !config.providers.moonshot.api_key.is_empty() {
-        Box::new(moonshot::MoonshotProvider::new(config.providers.moonshot.api_key.clone(), None))
-    } else {
-        // No provider configured
-        Box::new(OpenRouterProvider::new(String::new()))
-    }
+/// Create the speech provider for voice replies, if one is configured: a
+/// server at `audio.speech.api_base`, or OpenAI when it has a key.
+pub fn create_speaker(config: &Config) -> Option<Arc<dyn SpeechProvider>> {
+    let settings = &config.audio.speech;
+    let api_key = match settings.api_key.as_str() {
+        "" => config.providers.api_key("openai"),
+        key => Some(key.to_string()),
+    };
+    let (api_base, api_key) = match &settings.api_base {
+        Some(api_base) => (api_base.as_str(), api_key.unwrap_or_default()),
+        None => (config.providers.openai.api_base.as_deref().unwrap_or("https://api.openai.com/v1"), api_key?),
+    };
+    Some(Arc::new(OpenAISpeechProvider::new(
+        api_base,
+        api_key,
+        &settings.model,
+        &settings.voice,
+        &settings.format,
+    )))
 }
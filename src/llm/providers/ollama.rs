@@ -0,0 +1,265 @@
+//! Ollama provider - local models through Ollama's native API.
+//!
+//! Uses `/api/chat`, which takes OpenAI-style tools but returns tool call
+//! arguments as objects and reports token counts as `prompt_eval_count` and
+//! `eval_count`. With `stream` set, the reply is read as newline-delimited
+//! JSON chunks while it is generated. `/api/tags` lists the local models.
+
+use crate::config::OllamaConfig;
+use crate::llm::providers::openai_compat::{content_images, content_text, extract_tool_args, split_data_url};
+use crate::llm::providers::{LLMProvider, ProviderError};
+use crate::types::{LLMResponse, ToolCall, Usage};
+use serde_json::{json, Value};
+
+const DEFAULT_API_BASE: &str = "http://localhost:11434";
+
+/// A model available on the Ollama server
+#[derive(Debug, Clone)]
+pub struct OllamaModel {
+    pub name: String,
+    /// Size on disk, in bytes
+    pub size: u64,
+}
+
+/// Ollama provider
+#[derive(Debug, Clone)]
+pub struct OllamaProvider {
+    api_base: String,
+    keep_alive: String,
+    stream: bool,
+}
+
+impl OllamaProvider {
+    pub fn new(config: &OllamaConfig) -> Self {
+        let api_base = config.api_base.as_deref().unwrap_or(DEFAULT_API_BASE);
+        Self {
+            api_base: api_base.trim_end_matches('/').to_string(),
+            keep_alive: config.keep_alive.clone(),
+            stream: config.stream,
+        }
+    }
+
+    /// Models pulled on the server
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>, String> {
+        let response = reqwest::get(format!("{}/api/tags", self.api_base))
+            .await
+            .map_err(|e| format!("Ollama not reachable at {}: {}", self.api_base, e))?;
+        if !response.status().is_success() {
+            let error = response.text().await.unwrap_or_default();
+            return Err(format!("Ollama API error: {}", error));
+        }
+        let tags: Value = response.json().await.map_err(|e| format!("Parse error: {}", e))?;
+        Ok(tags["models"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|m| OllamaModel {
+                name: m["name"].as_str().unwrap_or_default().to_string(),
+                size: m["size"].as_u64().unwrap_or(0),
+            })
+            .collect())
+    }
+}
+
+/// Convert OpenAI-style messages: tool call arguments become objects and
+/// inline images move to `images`
+fn convert_messages(messages: &[Value]) -> Vec<Value> {
+    messages
+        .iter()
+        .map(|m| {
+            let mut message = json!({
+                "role": m["role"],
+                "content": content_text(&m["content"]),
+            });
+            let images: Vec<&str> = content_images(&m["content"])
+                .into_iter()
+                .filter_map(|url| Some(split_data_url(url)?.1))
+                .collect();
+            if !images.is_empty() {
+                message["images"] = json!(images);
+            }
+            if let Some(calls) = m["tool_calls"].as_array() {
+                message["tool_calls"] = calls
+                    .iter()
+                    .map(|tc| {
+                        json!({
+                            "function": {
+                                "name": tc["function"]["name"],
+                                "arguments": extract_tool_args(&tc["function"]["arguments"]),
+                            }
+                        })
+                    })
+                    .collect();
+            }
+            if m["role"] == "tool" && m["name"].is_string() {
+                message["tool_name"] = m["name"].clone();
+            }
+            message
+        })
+        .collect()
+}
+
+/// Add one response chunk to the reply. Non-streaming replies are a single chunk.
+fn add_chunk(reply: &mut LLMResponse, chunk: &Value) -> Result<(), String> {
+    if let Some(error) = chunk["error"].as_str() {
+        return Err(format!("Ollama API error: {}", error));
+    }
+    let message = &chunk["message"];
+    if let Some(content) = message["content"].as_str() {
+        reply.content.get_or_insert_with(String::new).push_str(content);
+    }
+    if let Some(thinking) = message["thinking"].as_str().filter(|t| !t.is_empty()) {
+        reply.reasoning.get_or_insert_with(String::new).push_str(thinking);
+    }
+    for tc in message["tool_calls"].as_array().into_iter().flatten() {
+        reply.tool_calls.push(ToolCall {
+            id: format!("call_{}", reply.tool_calls.len()),
+            name: tc["function"]["name"].as_str().unwrap_or_default().to_string(),
+            arguments: extract_tool_args(&tc["function"]["arguments"]),
+        });
+    }
+    if chunk["done"].as_bool() == Some(true) {
+        reply.finish_reason = chunk["done_reason"].as_str().unwrap_or("stop").to_string();
+        reply.usage = Some(Usage {
+            prompt_tokens: chunk["prompt_eval_count"].as_u64().unwrap_or(0),
+            completion_tokens: chunk["eval_count"].as_u64().unwrap_or(0),
+            ..Default::default()
+        });
+    }
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for OllamaProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        model: &str,
+        tools: &[Value],
+    ) -> Result<LLMResponse, ProviderError> {
+        let mut body = json!({
+            "model": model,
+            "messages": convert_messages(messages),
+            "stream": self.stream,
+        });
+        if !tools.is_empty() {
+            body["tools"] = json!(tools);
+        }
+        if !self.keep_alive.is_empty() {
+            // Ollama takes a duration string, or a number of seconds
+            body["keep_alive"] = match self.keep_alive.parse::<i64>() {
+                Ok(seconds) => json!(seconds),
+                Err(_) => json!(self.keep_alive),
+            };
+        }
+
+        let mut response = reqwest::Client::new()
+            .post(format!("{}/api/chat", self.api_base))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("Request failed: {}", e))?;
+        if !response.status().is_success() {
+            let error = response.text().await.unwrap_or_default();
+            return Err(format!("Ollama API error: {}", error).into());
+        }
+
+        let mut reply = LLMResponse::new(None, vec![], "stop");
+        let mut pending = Vec::new();
+        while let Some(bytes) = response.chunk().await.map_err(|e| format!("Read error: {}", e))? {
+            pending.extend_from_slice(&bytes);
+            while let Some(end) = pending.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = pending.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                let chunk: Value = serde_json::from_slice(&line).map_err(|e| format!("Parse error: {}", e))?;
+                add_chunk(&mut reply, &chunk)?;
+            }
+        }
+        if !pending.iter().all(u8::is_ascii_whitespace) {
+            let chunk: Value = serde_json::from_slice(&pending).map_err(|e| format!("Parse error: {}", e))?;
+            add_chunk(&mut reply, &chunk)?;
+        }
+        Ok(reply)
+    }
+
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn api_base(&self) -> &str {
+        &self.api_base
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve one canned response per connection, like a local Ollama would
+    async fn stand_in(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for body in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![0u8; 64 * 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                stream.shutdown().await.unwrap();
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_streamed_chat_and_tags() {
+        let chat = concat!(
+            r#"{"message":{"role":"assistant","content":"Let me "},"done":false}"#, "\n",
+            r#"{"message":{"role":"assistant","content":"check.","tool_calls":[{"function":{"name":"read_file","arguments":{"path":"a.txt"}}}]},"done":false}"#, "\n",
+            r#"{"message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":26,"eval_count":12}"#, "\n",
+        );
+        let tags = r#"{"models":[{"name":"llama3.2:latest","size":2019393189}]}"#;
+        let api_base = stand_in(vec![chat, tags]).await;
+
+        let provider = OllamaProvider::new(&OllamaConfig {
+            api_base: Some(api_base),
+            keep_alive: "10m".to_string(),
+            stream: true,
+        });
+        let reply = provider.chat(&[json!({"role": "user", "content": "hi"})], "llama3.2", &[]).await.unwrap();
+        assert_eq!(reply.content.as_deref(), Some("Let me check."));
+        assert_eq!(reply.tool_calls.len(), 1);
+        assert_eq!(reply.tool_calls[0].arguments["path"], "a.txt");
+        assert_eq!(reply.usage, Some(Usage { prompt_tokens: 26, completion_tokens: 12, ..Default::default() }));
+
+        let models = provider.list_models().await.unwrap();
+        assert_eq!(models[0].name, "llama3.2:latest");
+    }
+
+    #[test]
+    fn test_convert_messages() {
+        let messages = convert_messages(&[
+            json!({"role": "assistant", "content": "", "tool_calls": [
+                {"id": "1", "type": "function", "function": {"name": "exec", "arguments": "{\"command\":\"ls\"}"}}
+            ]}),
+            json!({"role": "tool", "tool_call_id": "1", "name": "exec", "content": "a.txt"}),
+            json!({"role": "user", "content": [
+                {"type": "text", "text": "And this?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K"}}
+            ]}),
+        ]);
+        assert_eq!(messages[0]["tool_calls"][0]["function"]["arguments"]["command"], "ls");
+        assert_eq!(messages[1]["tool_name"], "exec");
+        assert_eq!(messages[2]["content"], "And this?");
+        assert_eq!(messages[2]["images"], json!(["iVBORw0K"]));
+    }
+}
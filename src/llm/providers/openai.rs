@@ -2,7 +2,7 @@
 
 use crate::llm::providers::openai_compat::OpenAICompatConfig;
 use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
+use crate::llm::providers::{LLMProvider, ProviderError};
 use serde_json::Value;
 
 /// OpenAI provider
@@ -21,6 +21,12 @@ impl OpenAIProvider {
             ),
         }
     }
+
+    /// Reasoning effort for o-series models
+    pub fn with_reasoning_effort(mut self, effort: &str) -> Self {
+        self.config = self.config.with_reasoning_effort(effort);
+        self
+    }
 }
 
 #[async_trait::async_trait]
@@ -30,12 +36,12 @@ impl LLMProvider for OpenAIProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         self.config.chat_impl(messages, model, tools).await
     }
 
     fn name(&self) -> &str {
-        self.config.name
+        &self.config.name
     }
 
     fn api_base(&self) -> &str {
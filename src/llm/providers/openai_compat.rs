@@ -3,7 +3,8 @@
 //! This module provides shared functionality for providers that use the OpenAI
 //! chat completions API format (OpenAI, Groq, DeepSeek, Zhipu, Moonshot, etc.)
 
-use crate::types::{LLMResponse, ToolCall};
+use crate::llm::providers::ProviderError;
+use crate::types::{LLMResponse, ToolCall, Usage};
 use reqwest::Client;
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -14,9 +15,19 @@ pub async fn parse_openai_response(response: reqwest::Response) -> Result<LLMRes
         .json()
         .await
         .map_err(|e| format!("Parse error: {}", e))?;
+    Ok(parse_openai_json(&response_json))
+}
 
+/// Parse a chat completions response body
+pub fn parse_openai_json(response_json: &Value) -> LLMResponse {
     let choice = &response_json["choices"][0];
     let content = choice["message"]["content"].as_str().map(|s| s.to_string());
+    // DeepSeek calls the trace `reasoning_content`; OpenRouter calls it `reasoning`
+    let reasoning = ["reasoning_content", "reasoning"]
+        .iter()
+        .find_map(|key| choice["message"][key].as_str())
+        .filter(|r| !r.is_empty())
+        .map(str::to_string);
 
     let tool_calls: Vec<ToolCall> = if let Some(tc_array) = choice["message"]["tool_calls"].as_array() {
         tc_array
@@ -36,11 +47,27 @@ pub async fn parse_openai_response(response: reqwest::Response) -> Result<LLMRes
         .unwrap_or("stop")
         .to_string();
 
-    Ok(LLMResponse {
+    // Prefix caching is automatic: OpenAI reports hits under `prompt_tokens_details`,
+    // DeepSeek as `prompt_cache_hit_tokens`
+    let usage = response_json["usage"].as_object().map(|usage| Usage {
+        prompt_tokens: usage.get("prompt_tokens").and_then(Value::as_u64).unwrap_or(0),
+        completion_tokens: usage.get("completion_tokens").and_then(Value::as_u64).unwrap_or(0),
+        cache_read_tokens: usage
+            .get("prompt_cache_hit_tokens")
+            .or_else(|| usage.get("prompt_tokens_details")?.get("cached_tokens"))
+            .and_then(Value::as_u64)
+            .unwrap_or(0),
+        cache_write_tokens: 0,
+    });
+
+    LLMResponse {
         content,
         tool_calls,
         finish_reason,
-    })
+        usage,
+        reasoning,
+        thinking_blocks: vec![],
+    }
 }
 
 /// Base configuration for OpenAI-compatible providers
@@ -48,24 +75,33 @@ pub async fn parse_openai_response(response: reqwest::Response) -> Result<LLMRes
 pub struct OpenAICompatConfig {
     pub api_key: String,
     pub api_base: String,
-    pub name: &'static str,
-    pub extra_headers: HashMap<&'static str, String>,
+    pub name: String,
+    pub extra_headers: HashMap<String, String>,
+    /// Sent as `reasoning_effort` when set
+    pub reasoning_effort: Option<String>,
 }
 
 impl OpenAICompatConfig {
     /// Create a new config
-    pub fn new(api_key: String, api_base: String, name: &'static str) -> Self {
+    pub fn new(api_key: String, api_base: String, name: impl Into<String>) -> Self {
         Self {
             api_key,
             api_base,
-            name,
+            name: name.into(),
             extra_headers: HashMap::new(),
+            reasoning_effort: None,
         }
     }
 
     /// Add extra header
-    pub fn with_header(mut self, key: &'static str, value: String) -> Self {
-        self.extra_headers.insert(key, value);
+    pub fn with_header(mut self, key: impl Into<String>, value: String) -> Self {
+        self.extra_headers.insert(key.into(), value);
+        self
+    }
+
+    /// Set the reasoning effort; empty leaves it unset
+    pub fn with_reasoning_effort(mut self, effort: &str) -> Self {
+        self.reasoning_effort = Some(effort.to_string()).filter(|e| !e.is_empty());
         self
     }
 
@@ -85,9 +121,23 @@ impl OpenAICompatConfig {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
-        chat_request(self, messages, model, tools).await
+    ) -> Result<LLMResponse, ProviderError> {
+        Ok(chat_request(self, messages, model, tools).await?)
+    }
+}
+
+/// Chat completions request body, with tools when there are any
+pub fn request_body(messages: &[Value], model: &str, tools: &[Value]) -> Value {
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+    });
+    // APIs reject an empty `tools` array, which roles without tools would send
+    if !tools.is_empty() {
+        body["tools"] = json!(tools);
+        body["tool_choice"] = json!("auto");
     }
+    body
 }
 
 /// Helper function to perform chat request (used by providers)
@@ -99,12 +149,10 @@ pub async fn chat_request(
 ) -> Result<LLMResponse, String> {
     let client = Client::new();
 
-    let body = json!({
-        "model": model,
-        "messages": messages,
-        "tools": tools,
-        "tool_choice": if tools.is_empty() { json!(null) } else { json!("auto") }
-    });
+    let mut body = request_body(messages, model, tools);
+    if let Some(effort) = &config.reasoning_effort {
+        body["reasoning_effort"] = json!(effort);
+    }
 
     let mut request = client
         .post(&config.chat_url())
@@ -113,7 +161,7 @@ pub async fn chat_request(
 
     // Add extra headers
     for (key, value) in &config.extra_headers {
-        request = request.header(*key, value);
+        request = request.header(key, value);
     }
 
     let response = request
@@ -161,6 +209,35 @@ pub fn extract_tool_args(args: &Value) -> Value {
     }
 }
 
+/// Text of a message's `content`, which is a string or an array of parts
+pub fn content_text(content: &Value) -> String {
+    match content.as_array() {
+        Some(parts) => parts
+            .iter()
+            .filter(|p| p["type"] == "text")
+            .filter_map(|p| p["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => content.as_str().unwrap_or_default().to_string(),
+    }
+}
+
+/// Image URLs among a message's content parts; inline images are `data:` URLs
+pub fn content_images(content: &Value) -> Vec<&str> {
+    content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|p| p["type"] == "image_url")
+        .filter_map(|p| p["image_url"]["url"].as_str())
+        .collect()
+}
+
+/// Mime type and base64 data of a `data:` URL
+pub fn split_data_url(url: &str) -> Option<(&str, &str)> {
+    url.strip_prefix("data:")?.split_once(";base64,")
+}
+
 /// Macro to create a simple OpenAI-compatible provider
 #[macro_export]
 macro_rules! make_openai_provider {
@@ -200,7 +277,7 @@ macro_rules! make_openai_provider {
             }
 
             fn name(&self) -> &str {
-                self.config.name
+                &self.config.name
             }
 
             fn api_base(&self) -> &str {
@@ -248,6 +325,20 @@ mod tests {
         assert_eq!(config.chat_url(), "https://api.example.com/v1/chat/completions");
     }
 
+    #[test]
+    fn test_parse_reasoning_and_usage() {
+        let response = parse_openai_json(&json!({
+            "choices": [{
+                "message": {"content": "42", "reasoning_content": "6 times 7"},
+                "finish_reason": "stop"
+            }],
+            "usage": {"prompt_tokens": 10, "completion_tokens": 5, "prompt_tokens_details": {"cached_tokens": 8}}
+        }));
+        assert_eq!(response.content.as_deref(), Some("42"));
+        assert_eq!(response.reasoning.as_deref(), Some("6 times 7"));
+        assert_eq!(response.usage, Some(Usage { prompt_tokens: 10, completion_tokens: 5, cache_read_tokens: 8, cache_write_tokens: 0 }));
+    }
+
     #[test]
     fn test_auth_value() {
         let config = OpenAICompatConfig::new(
@@ -257,4 +348,17 @@ mod tests {
         );
         assert_eq!(config.auth_value(), "Bearer my-key");
     }
+
+    #[test]
+    fn test_request_body_without_tools() {
+        let messages = [json!({"role": "user", "content": "hi"})];
+        let body = request_body(&messages, "gpt-4o", &[]);
+        assert!(body.get("tools").is_none());
+        assert!(body.get("tool_choice").is_none());
+
+        let tools = vec![json!({"type": "function", "function": {"name": "read_file"}})];
+        let body = request_body(&messages, "gpt-4o", &tools);
+        assert_eq!(body["tools"][0]["function"]["name"], "read_file");
+        assert_eq!(body["tool_choice"], "auto");
+    }
 }
@@ -0,0 +1,114 @@
+//! Router provider - sends each model to the provider serving it.
+//!
+//! Holds every configured provider and picks one per request with
+//! `Providers::route`, so different chats, subagents and scheduled jobs can
+//! use different models and vendors in one process.
+
+use crate::config::{Config, Providers};
+use crate::llm::providers::{build_provider, LLMProvider, ProviderError};
+use crate::types::LLMResponse;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Provider dispatching by `provider/model` prefix, alias or model name
+pub struct RouterProvider {
+    providers: BTreeMap<String, Box<dyn LLMProvider>>,
+    config: Providers,
+    default_model: String,
+}
+
+impl RouterProvider {
+    pub fn new(config: &Config) -> Self {
+        let names = Providers::PRIORITY
+            .into_iter()
+            .chain(Providers::LOCAL)
+            .chain(config.providers.custom.keys().map(String::as_str));
+        let providers = names
+            .filter(|name| config.providers.is_configured(name))
+            .filter_map(|name| Some((name.to_string(), build_provider(name, config)?)))
+            .collect();
+        Self {
+            providers,
+            config: config.providers.clone(),
+            default_model: config.agents.defaults.model.clone(),
+        }
+    }
+
+    /// The provider for `model` and the model name to send it
+    fn route(&self, model: &str) -> Result<(&dyn LLMProvider, String), String> {
+        let model = if model.is_empty() { &self.default_model } else { model };
+        let (name, model) = self
+            .config
+            .route(model)
+            .ok_or_else(|| format!("No configured provider serves model `{}`", model))?;
+        let provider = self
+            .providers
+            .get(&name)
+            .ok_or_else(|| format!("Provider {} is not available", name))?;
+        Ok((provider.as_ref(), model))
+    }
+}
+
+#[async_trait::async_trait]
+impl LLMProvider for RouterProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        model: &str,
+        tools: &[Value],
+    ) -> Result<LLMResponse, ProviderError> {
+        let (provider, model) = self.route(model)?;
+        tracing::debug!("Routing model {} to {}", model, provider.name());
+        provider.chat(messages, &model, tools).await
+    }
+
+    fn name(&self) -> &str {
+        "router"
+    }
+
+    /// API base of the provider serving the default model
+    fn api_base(&self) -> &str {
+        self.route(&self.default_model).map(|(p, _)| p.api_base()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CustomProviderConfig;
+
+    fn routed(router: &RouterProvider, model: &str) -> Option<(String, String)> {
+        router.route(model).ok().map(|(p, m)| (p.name().to_string(), m))
+    }
+
+    #[test]
+    fn test_routing() {
+        let mut config = Config::default();
+        config.providers.openrouter.api_key = "or".to_string();
+        config.providers.deepseek.api_key = "ds".to_string();
+        config.providers.custom.insert(
+            "siliconflow".to_string(),
+            CustomProviderConfig {
+                api_base: "https://api.siliconflow.cn/v1".to_string(),
+                models: vec!["Qwen/Qwen2.5-72B-Instruct".to_string()],
+                ..Default::default()
+            },
+        );
+        config.providers.aliases.insert("cheap".to_string(), "deepseek/deepseek-chat".to_string());
+        let router = RouterProvider::new(&config);
+
+        let expect = |provider: &str, model: &str| Some((provider.to_string(), model.to_string()));
+        assert_eq!(routed(&router, "deepseek-chat"), expect("deepseek", "deepseek-chat"));
+        assert_eq!(routed(&router, "cheap"), expect("deepseek", "deepseek-chat"));
+        assert_eq!(routed(&router, "siliconflow/qwen2.5-72b"), expect("siliconflow", "qwen2.5-72b"));
+        assert_eq!(routed(&router, "Qwen/Qwen2.5-72B-Instruct"), expect("siliconflow", "Qwen/Qwen2.5-72B-Instruct"));
+        // Prefixes of providers without a key are left for OpenRouter
+        assert_eq!(routed(&router, "minimax/abab6.5s-chat"), expect("openrouter", "minimax/abab6.5s-chat"));
+        assert_eq!(routed(&router, "abab6.5s-chat"), expect("openrouter", "abab6.5s-chat"));
+
+        config.providers.openrouter.api_key.clear();
+        let router = RouterProvider::new(&config);
+        assert_eq!(routed(&router, "abab6.5s-chat"), None);
+        assert_eq!(routed(&router, "llama3").map(|(_, m)| m).as_deref(), Some("llama3"));
+    }
+}
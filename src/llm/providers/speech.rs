@@ -0,0 +1,126 @@
+//! Speech providers - text to speech for voice replies.
+//!
+//! Uses the OpenAI `/audio/speech` endpoint, which local TTS servers also
+//! implement. The default `opus` format is an ogg/opus file, which Telegram
+//! and WhatsApp play as a voice note.
+
+/// Text-to-speech provider
+#[async_trait::async_trait]
+pub trait SpeechProvider: Send + Sync {
+    /// Synthesize speech, returning the audio file's bytes
+    async fn synthesize(&self, text: &str) -> Result<Vec<u8>, String>;
+
+    /// File extension of the audio, such as `ogg`
+    fn extension(&self) -> &str;
+
+    /// Provider name
+    fn name(&self) -> &str;
+}
+
+/// Speech through OpenAI or any OpenAI-compatible server
+#[derive(Debug, Clone)]
+pub struct OpenAISpeechProvider {
+    api_key: String,
+    api_url: String,
+    model: String,
+    voice: String,
+    format: String,
+}
+
+impl OpenAISpeechProvider {
+    /// `api_base` is the API root, such as `https://api.openai.com/v1`.
+    /// Empty settings use `tts-1`, the `alloy` voice and `opus`.
+    pub fn new(api_base: &str, api_key: String, model: &str, voice: &str, format: &str) -> Self {
+        let or = |value: &str, default: &str| if value.is_empty() { default } else { value }.to_string();
+        Self {
+            api_key,
+            api_url: format!("{}/audio/speech", api_base.trim_end_matches('/')),
+            model: or(model, "tts-1"),
+            voice: or(voice, "alloy"),
+            format: or(format, "opus"),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SpeechProvider for OpenAISpeechProvider {
+    async fn synthesize(&self, text: &str) -> Result<Vec<u8>, String> {
+        let mut request = reqwest::Client::new()
+            .post(&self.api_url)
+            .json(&serde_json::json!({
+                "model": self.model,
+                "voice": self.voice,
+                "input": text,
+                "response_format": self.format,
+            }))
+            .timeout(std::time::Duration::from_secs(60));
+        // Local TTS servers usually need no key
+        if !self.api_key.is_empty() {
+            request = request.header("Authorization", format!("Bearer {}", self.api_key));
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("API error {}: {}", status, body));
+        }
+        let audio = response.bytes().await.map_err(|e| format!("Read error: {}", e))?;
+        Ok(audio.to_vec())
+    }
+
+    fn extension(&self) -> &str {
+        // Opus comes in an ogg container
+        match self.format.as_str() {
+            "opus" => "ogg",
+            format => format,
+        }
+    }
+
+    fn name(&self) -> &str {
+        "openai"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_synthesize() {
+        // A local TTS server returning a few bytes of audio
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            while !request.ends_with(b"}") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let response = "HTTP/1.1 200 OK\r\nContent-Type: audio/ogg\r\nContent-Length: 4\r\nConnection: close\r\n\r\nOggS";
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let provider = OpenAISpeechProvider::new(&format!("http://{}/v1", addr), String::new(), "", "nova", "");
+        assert_eq!(provider.extension(), "ogg");
+        assert_eq!(provider.synthesize("Hello there").await.unwrap(), b"OggS");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/audio/speech"));
+        let body: serde_json::Value = serde_json::from_str(&request[request.find('{').unwrap()..]).unwrap();
+        assert_eq!(body, serde_json::json!({
+            "model": "tts-1", "voice": "nova", "input": "Hello there", "response_format": "opus"
+        }));
+    }
+}
@@ -1,10 +1,106 @@
-//! Groq Whisper transcription provider.
+//! Transcription providers - speech to text for voice messages.
 //!
-//! Uses Groq's Whisper API for fast audio transcription.
+//! Groq and OpenAI both serve Whisper through the OpenAI
+//! `/audio/transcriptions` endpoint, which local whisper servers also
+//! implement. Uploads are labelled with the mime type of their extension,
+//! since voice notes are usually ogg/opus rather than wav.
 
 use std::path::Path;
 use tracing::{debug, error, warn};
 
+/// Speech-to-text provider
+#[async_trait::async_trait]
+pub trait TranscriptionProvider: Send + Sync {
+    /// Transcribe an audio file
+    async fn transcribe(&self, file_path: &Path) -> Result<String, String>;
+
+    /// Provider name
+    fn name(&self) -> &str;
+}
+
+/// Mime type of an audio file, from its extension
+fn audio_mime(file_path: &Path) -> &'static str {
+    let extension = file_path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "mp3" | "mpga" | "mpeg" => "audio/mpeg",
+        "m4a" | "mp4" => "audio/mp4",
+        "wav" => "audio/wav",
+        "webm" => "audio/webm",
+        "flac" => "audio/flac",
+        "amr" => "audio/amr",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Upload a file to an OpenAI-compatible `/audio/transcriptions` endpoint
+async fn transcribe_file(api_url: &str, api_key: &str, model: &str, file_path: &Path) -> Result<String, String> {
+    if !file_path.exists() {
+        error!("Audio file not found: {:?}", file_path);
+        return Err(format!("Audio file not found: {:?}", file_path));
+    }
+
+    debug!("Transcribing audio file: {:?}", file_path);
+
+    // Read file content
+    let file_content = tokio::fs::read(file_path)
+        .await
+        .map_err(|e| format!("Failed to read audio file: {}", e))?;
+
+    let file_name = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| "audio.wav".to_string());
+
+    // Create multipart form
+    let file_part = reqwest::multipart::Part::bytes(file_content)
+        .file_name(file_name)
+        .mime_str(audio_mime(file_path))
+        .map_err(|e| format!("Failed to create multipart part: {}", e))?;
+
+    let form = reqwest::multipart::Form::new()
+        .part("file", file_part)
+        .text("model", model.to_string());
+
+    let mut request = reqwest::Client::new()
+        .post(api_url)
+        .multipart(form)
+        .timeout(std::time::Duration::from_secs(60));
+    // Local whisper servers usually need no key
+    if !api_key.is_empty() {
+        request = request.header("Authorization", format!("Bearer {}", api_key));
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("HTTP request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        error!("Transcription API error: {} - {}", status, body);
+        return Err(format!("API error {}: {}", status, body));
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Response {
+        text: String,
+    }
+
+    let response_data: Response = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse response: {}", e))?;
+
+    debug!("Transcription complete: {} chars", response_data.text.len());
+    Ok(response_data.text.trim().to_string())
+}
+
 /// Groq Whisper transcription provider
 #[derive(Debug, Clone)]
 pub struct GroqTranscriptionProvider {
@@ -28,87 +124,70 @@ impl GroqTranscriptionProvider {
         }
     }
 
+    /// Use another Whisper model, such as `whisper-large-v3-turbo`
+    pub fn with_model(mut self, model: &str) -> Self {
+        if !model.is_empty() {
+            self.model = model.to_string();
+        }
+        self
+    }
+
     /// Check if the provider is configured
     pub fn is_configured(&self) -> bool {
         !self.api_key.is_empty()
     }
+}
 
-    /// Transcribe an audio file
-    ///
-    /// Args:
-    ///     file_path: Path to the audio file
-    ///
-    /// Returns:
-    ///     Transcribed text, or error message if transcription fails
-    pub async fn transcribe(&self, file_path: &Path) -> Result<String, String> {
+#[async_trait::async_trait]
+impl TranscriptionProvider for GroqTranscriptionProvider {
+    async fn transcribe(&self, file_path: &Path) -> Result<String, String> {
         if !self.is_configured() {
             warn!("Groq API key not configured for transcription");
             return Err("Groq API key not configured".to_string());
         }
+        transcribe_file(&self.api_url, &self.api_key, &self.model, file_path).await
+    }
 
-        if !file_path.exists() {
-            error!("Audio file not found: {:?}", file_path);
-            return Err(format!("Audio file not found: {:?}", file_path));
-        }
+    fn name(&self) -> &str {
+        "groq"
+    }
+}
 
-        debug!("Transcribing audio file: {:?}", file_path);
-
-        // Read file content
-        let file_content = tokio::fs::read(file_path)
-            .await
-            .map_err(|e| format!("Failed to read audio file: {}", e))?;
-
-        // Get file name with owned string to avoid lifetime issues
-        let file_name = file_path.file_name()
-            .and_then(|n| n.to_str())
-            .map(|s| s.to_string())
-            .unwrap_or_else(|| "audio.wav".to_string());
-
-        // Create multipart form
-        let file_part = reqwest::multipart::Part::bytes(file_content)
-            .file_name(file_name)
-            .mime_str("audio/wav")
-            .map_err(|e| format!("Failed to create multipart part: {}", e))?;
-
-        let form = reqwest::multipart::Form::new()
-            .part("file", file_part)
-            .text("model", self.model.clone());
-
-        let client = reqwest::Client::new();
-        let response = client
-            .post(&self.api_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .multipart(form)
-            .timeout(std::time::Duration::from_secs(60))
-            .send()
-            .await
-            .map_err(|e| format!("HTTP request failed: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            error!("Transcription API error: {} - {}", status, body);
-            return Err(format!("API error {}: {}", status, body));
-        }
+/// Transcription through OpenAI or any OpenAI-compatible server
+#[derive(Debug, Clone)]
+pub struct OpenAITranscriptionProvider {
+    api_key: String,
+    api_url: String,
+    model: String,
+}
 
-        #[derive(serde::Deserialize)]
-        struct Response {
-            text: String,
+impl OpenAITranscriptionProvider {
+    /// `api_base` is the API root, such as `https://api.openai.com/v1`
+    pub fn new(api_base: &str, api_key: String, model: &str) -> Self {
+        Self {
+            api_key,
+            api_url: format!("{}/audio/transcriptions", api_base.trim_end_matches('/')),
+            model: if model.is_empty() { "whisper-1" } else { model }.to_string(),
         }
+    }
+}
 
-        let response_data: Response = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+#[async_trait::async_trait]
+impl TranscriptionProvider for OpenAITranscriptionProvider {
+    async fn transcribe(&self, file_path: &Path) -> Result<String, String> {
+        transcribe_file(&self.api_url, &self.api_key, &self.model, file_path).await
+    }
 
-        debug!("Transcription complete: {} chars", response_data.text.len());
-        Ok(response_data.text)
+    fn name(&self) -> &str {
+        "openai"
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
 
     #[test]
     fn test_transcription_provider_default() {
@@ -142,4 +221,51 @@ mod tests {
         assert!(provider.is_configured());
         assert_eq!(provider.api_key, "test-key");
     }
+
+    #[test]
+    fn test_audio_mime() {
+        assert_eq!(audio_mime(Path::new("voice.OGG")), "audio/ogg");
+        assert_eq!(audio_mime(Path::new("memo.m4a")), "audio/mp4");
+        assert_eq!(audio_mime(Path::new("clip.wav")), "audio/wav");
+        assert_eq!(audio_mime(Path::new("noextension")), "application/octet-stream");
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_upload() {
+        // A local whisper server that expects an ogg upload
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = vec![0u8; 64 * 1024];
+            while !String::from_utf8_lossy(&request).contains("whisper-1\r\n--") {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"text":" Remind me to call Sam. "}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("voice.ogg");
+        std::fs::write(&path, "voice-note").unwrap();
+        let provider = OpenAITranscriptionProvider::new(&format!("http://{}/v1/", addr), String::new(), "");
+        assert_eq!(provider.transcribe(&path).await.unwrap(), "Remind me to call Sam.");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /v1/audio/transcriptions"));
+        assert!(request.contains("Content-Type: audio/ogg"));
+        assert!(request.contains("whisper-1"));
+        assert!(!request.to_lowercase().contains("authorization"));
+    }
 }
@@ -2,7 +2,7 @@
 
 use crate::llm::providers::openai_compat::OpenAICompatConfig;
 use crate::types::LLMResponse;
-use crate::llm::providers::LLMProvider;
+use crate::llm::providers::{LLMProvider, ProviderError};
 use serde_json::Value;
 
 /// VLLM provider - for local LLM serving with OpenAI-compatible API
@@ -32,7 +32,7 @@ impl LLMProvider for VLLMProvider {
         messages: &[Value],
         model: &str,
         tools: &[Value],
-    ) -> Result<LLMResponse, String> {
+    ) -> Result<LLMResponse, ProviderError> {
         let model_name = if model.is_empty() {
             &self.default_model
         } else {
@@ -42,7 +42,7 @@ impl LLMProvider for VLLMProvider {
     }
 
     fn name(&self) -> &str {
-        self.config.name
+        &self.config.name
     }
 
     fn api_base(&self) -> &str {
@@ -11,7 +11,7 @@ fn init_logging() {
         .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("openat=info"));
 
     let subscriber = tracing_subscriber::registry()
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .with(env_filter);
 
     tracing::subscriber::set_global_default(subscriber)
@@ -38,6 +38,8 @@ enum Commands {
     ChannelStatus,
     /// Login/link a channel
     ChannelLogin { channel: Option<String> },
+    /// Check the config file for errors and unknown keys
+    ConfigValidate,
     /// List scheduled jobs
     CronList { all: bool },
     /// Add a scheduled job
@@ -49,6 +51,9 @@ enum Commands {
         deliver: bool,
         to: Option<String>,
         channel: Option<String>,
+        /// Model for this job, e.g. `deepseek/deepseek-chat`
+        #[arg(long)]
+        model: Option<String>,
     },
     /// Remove a job
     CronRemove { job_id: String },
@@ -59,6 +64,63 @@ enum Commands {
         channel_id: String,
         message: Option<String>,
     },
+    /// List long-term memory entries
+    MemoryList,
+    /// Search long-term memory
+    MemorySearch {
+        query: String,
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+    },
+    /// Add a long-term memory entry
+    MemoryAdd {
+        content: String,
+        #[arg(long)]
+        tag: Vec<String>,
+    },
+    /// Remove a long-term memory entry
+    MemoryRemove { id: String },
+    /// Prune long-term memory entries
+    MemoryPrune {
+        #[arg(long)]
+        older_than_days: Option<u64>,
+        #[arg(long)]
+        keep: Option<usize>,
+    },
+    /// List the models the configured providers offer
+    Models,
+    /// Create a pairing code that lets a new chat user talk to the bot
+    Pair {
+        /// Channel the code is for (telegram, discord, qq, whatsapp)
+        #[arg(long)]
+        channel: String,
+        /// Role granted to the user
+        #[arg(long, default_value = "member")]
+        role: String,
+        /// Minutes until the code expires
+        #[arg(long, default_value_t = 15)]
+        minutes: i64,
+    },
+    /// Install a skill from a directory or tarball
+    SkillInstall {
+        source: String,
+        #[arg(long)]
+        force: bool,
+    },
+    /// List installed skills
+    SkillList,
+    /// Enable a skill
+    SkillEnable { name: String },
+    /// Disable a skill
+    SkillDisable { name: String },
+    /// Remove a skill
+    SkillRemove { name: String },
+    /// Serve openat tools and memory over MCP (stdio)
+    McpServe {
+        /// Gateway used by the send_message tool
+        #[arg(long, default_value = "http://127.0.0.1:18790")]
+        gateway_url: String,
+    },
     /// Show status
     Status,
 }
@@ -81,9 +143,10 @@ async fn main() -> Result<()> {
         }
         Commands::ChannelStatus => cli::channel_status()?,
         Commands::ChannelLogin { channel } => cli::channel_login(channel.as_deref()).await?,
+        Commands::ConfigValidate => cli::config_validate()?,
         Commands::CronList { all } => cli::cron_list(all)?,
-        Commands::CronAdd { name, message, every, cron, deliver, to, channel } => {
-            cli::cron_add(&name, &message, every, cron, deliver, to.as_deref(), channel.as_deref())?
+        Commands::CronAdd { name, message, every, cron, deliver, to, channel, model } => {
+            cli::cron_add(&name, &message, every, cron, deliver, to.as_deref(), channel.as_deref(), model)?
         }
         Commands::CronRemove { job_id } => cli::cron_remove(&job_id)?,
         Commands::CronEnable { job_id, disable } => cli::cron_enable(&job_id, disable)?,
@@ -91,7 +154,20 @@ async fn main() -> Result<()> {
             let content = message.unwrap_or_else(|| "Test message from openat!".to_string());
             cli::discord_test(&channel_id, &content).await?
         }
-        Commands::Status => cli::status()?,
+        Commands::MemoryList => cli::memory_list()?,
+        Commands::MemorySearch { query, limit } => cli::memory_search(&query, limit).await?,
+        Commands::MemoryAdd { content, tag } => cli::memory_add(&content, tag)?,
+        Commands::MemoryRemove { id } => cli::memory_remove(&id)?,
+        Commands::MemoryPrune { older_than_days, keep } => cli::memory_prune(older_than_days, keep)?,
+        Commands::Models => cli::models().await?,
+        Commands::Pair { channel, role, minutes } => cli::pair(&channel, &role, minutes)?,
+        Commands::SkillInstall { source, force } => cli::skill_install(&source, force)?,
+        Commands::SkillList => cli::skill_list()?,
+        Commands::SkillEnable { name } => cli::skill_enable(&name, false)?,
+        Commands::SkillDisable { name } => cli::skill_enable(&name, true)?,
+        Commands::SkillRemove { name } => cli::skill_remove(&name)?,
+        Commands::McpServe { gateway_url } => cli::mcp_serve(&gateway_url).await?,
+        Commands::Status => cli::status().await?,
     }
 
     Ok(())
@@ -103,5 +179,6 @@ mod config;
 mod core;
 mod heartbeat;
 mod llm;
+mod mcp;
 mod tools;
 mod types;
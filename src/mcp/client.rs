@@ -0,0 +1,201 @@
+//! MCP client: handshake, tool and resource discovery, and calls.
+
+use crate::config::McpServerConfig;
+use crate::mcp::transport::{HttpTransport, StdioTransport, Transport};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+/// A tool advertised by an MCP server
+#[derive(Debug, Clone)]
+pub struct McpToolInfo {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+}
+
+/// A resource advertised by an MCP server
+#[derive(Debug, Clone)]
+pub struct McpResourceInfo {
+    pub uri: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// Connected, initialized MCP client
+pub struct McpClient {
+    name: String,
+    transport: Box<dyn Transport>,
+}
+
+impl McpClient {
+    /// Connect to a configured server and perform the initialize handshake
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<Self, String> {
+        let timeout = Duration::from_secs(config.timeout_secs.max(1));
+        let transport: Box<dyn Transport> = if !config.command.is_empty() {
+            Box::new(StdioTransport::spawn(&config.command, &config.args, &config.env, timeout)?)
+        } else if !config.url.is_empty() {
+            Box::new(HttpTransport::new(&config.url, config.headers.clone(), timeout)?)
+        } else {
+            return Err(format!("MCP server '{}' needs either `command` or `url`", name));
+        };
+
+        Self::initialize(name, transport).await
+    }
+
+    /// Perform the handshake over an existing transport
+    pub async fn initialize(name: &str, transport: Box<dyn Transport>) -> Result<Self, String> {
+        let result = transport
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": "openat", "version": env!("CARGO_PKG_VERSION")}
+                }),
+            )
+            .await?;
+        transport.notify("notifications/initialized", json!({})).await?;
+
+        tracing::info!(
+            "Connected to MCP server '{}' ({} {})",
+            name,
+            result["serverInfo"]["name"].as_str().unwrap_or("unknown"),
+            result["serverInfo"]["version"].as_str().unwrap_or("")
+        );
+
+        Ok(Self {
+            name: name.to_string(),
+            transport,
+        })
+    }
+
+    /// Configured server name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Collect every page of a paginated list method
+    async fn list_all(&self, method: &str, key: &str) -> Result<Vec<Value>, String> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(c) => json!({"cursor": c}),
+                None => json!({}),
+            };
+            let result = self.transport.request(method, params).await?;
+            if let Some(page) = result[key].as_array() {
+                items.extend(page.iter().cloned());
+            }
+            cursor = result["nextCursor"].as_str().map(|s| s.to_string());
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// List the server's tools
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>, String> {
+        Ok(self
+            .list_all("tools/list", "tools")
+            .await?
+            .into_iter()
+            .filter_map(|tool| {
+                Some(McpToolInfo {
+                    name: tool["name"].as_str()?.to_string(),
+                    description: tool["description"].as_str().unwrap_or_default().to_string(),
+                    input_schema: tool
+                        .get("inputSchema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                })
+            })
+            .collect())
+    }
+
+    /// List the server's resources; servers without resource support yield none
+    pub async fn list_resources(&self) -> Result<Vec<McpResourceInfo>, String> {
+        let resources = match self.list_all("resources/list", "resources").await {
+            Ok(resources) => resources,
+            Err(e) if e.contains("-32601") => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        Ok(resources
+            .into_iter()
+            .filter_map(|resource| {
+                Some(McpResourceInfo {
+                    uri: resource["uri"].as_str()?.to_string(),
+                    name: resource["name"].as_str().unwrap_or_default().to_string(),
+                    description: resource["description"].as_str().unwrap_or_default().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Call a tool, returning its text content
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<String, String> {
+        let result = self
+            .transport
+            .request("tools/call", json!({"name": name, "arguments": arguments}))
+            .await?;
+
+        let text = content_to_text(&result["content"]);
+        if result["isError"].as_bool().unwrap_or(false) {
+            Err(text)
+        } else {
+            Ok(text)
+        }
+    }
+
+    /// Read a resource, returning its text contents
+    pub async fn read_resource(&self, uri: &str) -> Result<String, String> {
+        let result = self
+            .transport
+            .request("resources/read", json!({"uri": uri}))
+            .await?;
+
+        let parts: Vec<String> = result["contents"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|c| match c["text"].as_str() {
+                Some(text) => text.to_string(),
+                None => format!(
+                    "[binary resource {} ({})]",
+                    c["uri"].as_str().unwrap_or(uri),
+                    c["mimeType"].as_str().unwrap_or("unknown type")
+                ),
+            })
+            .collect();
+        Ok(parts.join("\n"))
+    }
+}
+
+/// Flatten MCP content blocks into text for the model
+fn content_to_text(content: &Value) -> String {
+    let parts: Vec<String> = content
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|block| match block["type"].as_str() {
+            Some("text") => block["text"].as_str().unwrap_or_default().to_string(),
+            Some("resource") => block["resource"]["text"]
+                .as_str()
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| format!("[resource {}]", block["resource"]["uri"].as_str().unwrap_or(""))),
+            Some(other) => format!(
+                "[{} content ({})]",
+                other,
+                block["mimeType"].as_str().unwrap_or("unknown type")
+            ),
+            None => String::new(),
+        })
+        .collect();
+    if parts.is_empty() {
+        "(no output)".to_string()
+    } else {
+        parts.join("\n")
+    }
+}
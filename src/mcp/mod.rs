@@ -0,0 +1,155 @@
+//! Model Context Protocol (MCP) support.
+//!
+//! # Components
+//!
+//! - `McpClient`: Connects to an MCP server and lists/calls its tools and resources
+//! - `transport`: stdio (child process) and streamable HTTP transports
+//! - `McpTool` / `McpResourceTool`: Adapters into the `tools::Tool` trait
+//! - `McpServer`: Serves openat's own tools and resources (`openat mcp-serve`)
+//!
+//! Servers are configured under `tools.mcp_servers` in config.json.
+
+pub mod client;
+pub mod server;
+pub mod tool;
+pub mod transport;
+
+pub use client::McpClient;
+pub use server::{McpServer, SendMessageTool};
+pub use tool::{McpResourceTool, McpTool};
+
+use crate::config::McpServerConfig;
+use crate::tools::Tool;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Whether a tool passes a server's allow/deny lists
+pub fn is_tool_allowed(config: &McpServerConfig, tool: &str) -> bool {
+    let matches = |pattern: &String| match pattern.strip_suffix('*') {
+        Some(prefix) => tool.starts_with(prefix),
+        None => pattern == tool,
+    };
+    if config.deny_tools.iter().any(matches) {
+        return false;
+    }
+    config.allow_tools.is_empty() || config.allow_tools.iter().any(matches)
+}
+
+/// Discover the tools and resources of a connected server
+pub async fn load_tools(client: Arc<McpClient>, config: &McpServerConfig) -> Result<Vec<Arc<dyn Tool>>, String> {
+    let mut tools: Vec<Arc<dyn Tool>> = Vec::new();
+
+    for info in client.list_tools().await? {
+        if is_tool_allowed(config, &info.name) {
+            tools.push(Arc::new(McpTool::new(client.clone(), info)));
+        }
+    }
+
+    let resources = client.list_resources().await?;
+    if !resources.is_empty() && is_tool_allowed(config, "read_resource") {
+        tools.push(Arc::new(McpResourceTool::new(client.clone(), &resources)));
+    }
+
+    Ok(tools)
+}
+
+/// Connect to every enabled server. Servers that fail to start are logged and skipped.
+pub async fn connect_all(servers: &BTreeMap<String, McpServerConfig>) -> Vec<Arc<dyn Tool>> {
+    let mut tools = Vec::new();
+    for (name, config) in servers.iter().filter(|(_, c)| c.enabled) {
+        let client = match McpClient::connect(name, config).await {
+            Ok(client) => Arc::new(client),
+            Err(e) => {
+                warn!("MCP server '{}' unavailable: {}", name, e);
+                continue;
+            }
+        };
+        match load_tools(client, config).await {
+            Ok(server_tools) => {
+                info!("MCP server '{}': {} tools", name, server_tools.len());
+                tools.extend(server_tools);
+            }
+            Err(e) => warn!("MCP server '{}': failed to list tools: {}", name, e),
+        }
+    }
+    tools
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::transport::StdioTransport;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    /// Minimal MCP server speaking newline-delimited JSON-RPC over a duplex pipe
+    fn spawn_test_server() -> StdioTransport {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let (server_read, mut server_write) = tokio::io::split(server_side);
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(server_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let request: Value = serde_json::from_str(&line).unwrap();
+                let Some(id) = request.get("id").cloned() else { continue };
+                let params = &request["params"];
+                let result = match request["method"].as_str().unwrap() {
+                    "initialize" => json!({"protocolVersion": "2025-03-26", "capabilities": {"tools": {}}, "serverInfo": {"name": "test", "version": "1"}}),
+                    "tools/list" if params.get("cursor").is_none() => json!({
+                        "tools": [{"name": "echo", "description": "Echo text", "inputSchema": {"type": "object", "properties": {"text": {"type": "string"}}}}],
+                        "nextCursor": "page2"
+                    }),
+                    "tools/list" => json!({"tools": [
+                        {"name": "fail", "inputSchema": {"type": "object"}},
+                        {"name": "admin_delete", "inputSchema": {"type": "object"}}
+                    ]}),
+                    "tools/call" if params["name"] == "echo" => json!({"content": [{"type": "text", "text": params["arguments"]["text"]}]}),
+                    "tools/call" => json!({"content": [{"type": "text", "text": "boom"}], "isError": true}),
+                    "resources/list" => json!({"resources": [{"uri": "mem://notes", "name": "notes"}]}),
+                    "resources/read" => json!({"contents": [{"uri": params["uri"], "text": "note body"}]}),
+                    _ => json!(null),
+                };
+                let response = json!({"jsonrpc": "2.0", "id": id, "result": result});
+                server_write.write_all(format!("{}\n", response).as_bytes()).await.unwrap();
+            }
+        });
+
+        StdioTransport::from_streams(client_read, client_write, Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tools_roundtrip() {
+        let client = Arc::new(McpClient::initialize("test", Box::new(spawn_test_server())).await.unwrap());
+        let config = McpServerConfig::default();
+        let tools = load_tools(client, &config).await.unwrap();
+
+        let names: Vec<&str> = tools.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["test__echo", "test__fail", "test__admin_delete", "test__read_resource"]);
+
+        assert_eq!(tools[0].execute(r#"{"text":"hello"}"#).await.unwrap(), "hello");
+        assert_eq!(tools[1].execute("{}").await.unwrap_err(), "boom");
+        assert_eq!(tools[3].execute(r#"{"uri":"mem://notes"}"#).await.unwrap(), "note body");
+        assert_eq!(tools[3].definition().parameters["properties"]["uri"]["enum"][0], "mem://notes");
+    }
+
+    #[tokio::test]
+    async fn test_mcp_allow_deny() {
+        let client = Arc::new(McpClient::initialize("test", Box::new(spawn_test_server())).await.unwrap());
+        let config = McpServerConfig {
+            deny_tools: vec!["admin_*".to_string()],
+            allow_tools: vec!["echo".to_string(), "admin_delete".to_string()],
+            ..Default::default()
+        };
+        let tools = load_tools(client, &config).await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t.name()).collect();
+        assert_eq!(names, vec!["test__echo"]);
+    }
+
+    #[test]
+    fn test_qualified_name() {
+        assert_eq!(tool::qualified_name("git hub", "search.issues"), "git_hub__search_issues");
+    }
+}
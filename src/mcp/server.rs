@@ -0,0 +1,319 @@
+//! MCP server: exposes openat tools, memory files and skills over stdio.
+//!
+//! - Tools: every tool in the `ToolRegistry` passed in, plus `send_message`
+//! - Resources: `memory://<file>` for files in `workspace/memory/`, and
+//!   `skill://<name>` for each available skill's SKILL.md
+
+use crate::core::agent::skills::SkillManager;
+use crate::core::bus::http::post_message;
+use crate::mcp::client::PROTOCOL_VERSION;
+use crate::tools::{Tool, ToolRegistry};
+use crate::types::ToolDefinition;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::debug;
+
+/// Publishes a message to a chat channel through a running gateway
+pub struct SendMessageTool {
+    gateway_url: String,
+    token: String,
+}
+
+impl SendMessageTool {
+    pub fn new(gateway_url: &str, token: &str) -> Self {
+        Self {
+            gateway_url: gateway_url.to_string(),
+            token: token.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for SendMessageTool {
+    fn name(&self) -> &str {
+        "send_message"
+    }
+
+    fn description(&self) -> &str {
+        "Send a message to a chat (Telegram, Discord, ...) through the openat gateway."
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            "send_message",
+            self.description(),
+            json!({
+                "type": "object",
+                "properties": {
+                    "channel": {
+                        "type": "string",
+                        "description": "Target channel, e.g. telegram or discord"
+                    },
+                    "chat_id": {
+                        "type": "string",
+                        "description": "Target chat/user ID on that channel"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "The message content to send"
+                    }
+                },
+                "required": ["channel", "chat_id", "content"]
+            }),
+        )
+    }
+
+    async fn execute(&self, args: &str) -> Result<String, String> {
+        #[derive(Deserialize)]
+        struct Args {
+            channel: String,
+            chat_id: String,
+            content: String,
+        }
+
+        let args: Args = serde_json::from_str(args)
+            .map_err(|e| format!("Invalid arguments: {}", e))?;
+        post_message(&self.gateway_url, &self.token, &args.channel, &args.chat_id, &args.content).await?;
+        Ok(format!("Message sent to {}:{}", args.channel, args.chat_id))
+    }
+}
+
+/// MCP server over a line-delimited JSON-RPC stream
+pub struct McpServer {
+    tools: ToolRegistry,
+    workspace: PathBuf,
+}
+
+impl McpServer {
+    pub fn new(tools: ToolRegistry, workspace: PathBuf) -> Self {
+        Self { tools, workspace }
+    }
+
+    /// Serve requests until the input stream closes
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> std::io::Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(&message).await,
+                Err(e) => Some(error_response(Value::Null, -32700, &format!("Parse error: {}", e))),
+            };
+
+            if let Some(response) = response {
+                writer.write_all(response.to_string().as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one message; notifications produce no response
+    async fn handle(&self, message: &Value) -> Option<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let Some(id) = message.get("id").cloned() else {
+            debug!("MCP notification: {}", method);
+            return None;
+        };
+        let params = &message["params"];
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": params["protocolVersion"].as_str().unwrap_or(PROTOCOL_VERSION),
+                "capabilities": {"tools": {}, "resources": {}},
+                "serverInfo": {"name": "openat", "version": env!("CARGO_PKG_VERSION")}
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => Ok(self.call_tool(params).await),
+            "resources/list" => Ok(json!({"resources": self.list_resources().await})),
+            "resources/read" => self.read_resource(params["uri"].as_str().unwrap_or_default()).await,
+            _ => Err((-32601, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err((code, message)) => error_response(id, code, &message),
+        })
+    }
+
+    fn list_tools(&self) -> Value {
+        let tools: Vec<Value> = self
+            .tools
+            .definitions()
+            .into_iter()
+            .map(|def| {
+                json!({
+                    "name": def.name,
+                    "description": def.description,
+                    "inputSchema": def.parameters,
+                })
+            })
+            .collect();
+        json!({"tools": tools})
+    }
+
+    async fn call_tool(&self, params: &Value) -> Value {
+        let name = params["name"].as_str().unwrap_or_default();
+        let (text, is_error) = match self.tools.get(name) {
+            Some(tool) => {
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                match tool.execute(&arguments.to_string()).await {
+                    Ok(output) => (output, false),
+                    Err(e) => (e, true),
+                }
+            }
+            None => (format!("Unknown tool: {}", name), true),
+        };
+        json!({
+            "content": [{"type": "text", "text": text}],
+            "isError": is_error,
+        })
+    }
+
+    fn memory_dir(&self) -> PathBuf {
+        self.workspace.join("memory")
+    }
+
+    async fn load_skills(&self) -> SkillManager {
+        let mut skills = SkillManager::new(&self.workspace);
+        skills.load_all().await;
+        skills
+    }
+
+    async fn list_resources(&self) -> Vec<Value> {
+        let mut resources = Vec::new();
+
+        let mut files: Vec<String> = fs::read_dir(self.memory_dir())
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|e| e.path().is_file())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        for file in files {
+            let mime = if file.ends_with(".jsonl") { "application/x-ndjson" } else { "text/markdown" };
+            resources.push(json!({
+                "uri": format!("memory://{}", file),
+                "name": file,
+                "mimeType": mime,
+            }));
+        }
+
+        for skill in self.load_skills().await.get_available() {
+            resources.push(json!({
+                "uri": format!("skill://{}", skill.name),
+                "name": skill.name,
+                "description": skill.description,
+                "mimeType": "text/markdown",
+            }));
+        }
+
+        resources
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Value, (i64, String)> {
+        let path = if let Some(file) = uri.strip_prefix("memory://") {
+            // Only plain file names inside the memory directory
+            if file.is_empty() || file.contains('/') || file.contains('\\') || file.starts_with('.') {
+                None
+            } else {
+                Some(self.memory_dir().join(file))
+            }
+        } else if let Some(name) = uri.strip_prefix("skill://") {
+            self.load_skills()
+                .await
+                .get_available()
+                .into_iter()
+                .find(|s| s.name == name)
+                .map(|s| s.path.join("SKILL.md"))
+        } else {
+            None
+        };
+
+        let text = path
+            .and_then(|p| fs::read_to_string(p).ok())
+            .ok_or_else(|| (-32002, format!("Resource not found: {}", uri)))?;
+        Ok(json!({"contents": [{"uri": uri, "text": text}]}))
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::client::McpClient;
+    use crate::mcp::transport::StdioTransport;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    struct UpperTool;
+
+    #[async_trait]
+    impl Tool for UpperTool {
+        fn name(&self) -> &str {
+            "upper"
+        }
+
+        fn description(&self) -> &str {
+            "Uppercase text"
+        }
+
+        fn definition(&self) -> ToolDefinition {
+            ToolDefinition::new("upper", "Uppercase text", json!({"type": "object", "properties": {"text": {"type": "string"}}}))
+        }
+
+        async fn execute(&self, args: &str) -> Result<String, String> {
+            let args: Value = serde_json::from_str(args).map_err(|e| e.to_string())?;
+            Ok(args["text"].as_str().unwrap_or_default().to_uppercase())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_with_client() {
+        let workspace = tempfile::tempdir().unwrap();
+        fs::create_dir_all(workspace.path().join("memory")).unwrap();
+        fs::write(workspace.path().join("memory").join("MEMORY.md"), "likes tea").unwrap();
+        let skill_dir = workspace.path().join("skills").join("demo");
+        fs::create_dir_all(&skill_dir).unwrap();
+        fs::write(skill_dir.join("SKILL.md"), "---\nname: demo\n---\nDemo body").unwrap();
+
+        let mut tools = ToolRegistry::new();
+        tools.register(Arc::new(UpperTool));
+        let server = McpServer::new(tools, workspace.path().to_path_buf());
+
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        tokio::spawn(async move { server.serve(server_read, server_write).await });
+
+        let transport = StdioTransport::from_streams(client_read, client_write, Duration::from_secs(5));
+        let client = McpClient::initialize("openat", Box::new(transport)).await.unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "upper");
+        assert_eq!(client.call_tool("upper", json!({"text": "hi"})).await.unwrap(), "HI");
+        assert!(client.call_tool("missing", json!({})).await.is_err());
+
+        let uris: Vec<String> = client.list_resources().await.unwrap().into_iter().map(|r| r.uri).collect();
+        assert_eq!(uris, vec!["memory://MEMORY.md", "skill://demo"]);
+        assert_eq!(client.read_resource("memory://MEMORY.md").await.unwrap(), "likes tea");
+        assert!(client.read_resource("skill://demo").await.unwrap().contains("Demo body"));
+        assert!(client.read_resource("memory://../config.json").await.is_err());
+    }
+}
@@ -0,0 +1,133 @@
+//! Adapters exposing MCP tools and resources through the `tools::Tool` trait.
+
+use crate::mcp::client::{McpClient, McpResourceInfo, McpToolInfo};
+use crate::tools::Tool;
+use crate::types::ToolDefinition;
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+/// Tool name seen by the model: `<server>__<tool>`, restricted to the
+/// characters LLM APIs accept in function names.
+pub fn qualified_name(server: &str, tool: &str) -> String {
+    let name: String = format!("{}__{}", server, tool)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    name.chars().take(64).collect()
+}
+
+/// A single tool on an MCP server
+pub struct McpTool {
+    client: Arc<McpClient>,
+    info: McpToolInfo,
+    qualified_name: String,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: McpToolInfo) -> Self {
+        let qualified_name = qualified_name(client.name(), &info.name);
+        Self {
+            client,
+            info,
+            qualified_name,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpTool {
+    fn name(&self) -> &str {
+        &self.qualified_name
+    }
+
+    fn description(&self) -> &str {
+        &self.info.description
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            &self.qualified_name,
+            &format!("[{}] {}", self.client.name(), self.info.description),
+            self.info.input_schema.clone(),
+        )
+    }
+
+    async fn execute(&self, args: &str) -> Result<String, String> {
+        let arguments: Value = if args.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(args).map_err(|e| format!("Invalid arguments: {}", e))?
+        };
+        self.client.call_tool(&self.info.name, arguments).await
+    }
+}
+
+/// Reads any resource advertised by an MCP server
+pub struct McpResourceTool {
+    client: Arc<McpClient>,
+    qualified_name: String,
+    description: String,
+    uris: Vec<String>,
+}
+
+impl McpResourceTool {
+    pub fn new(client: Arc<McpClient>, resources: &[McpResourceInfo]) -> Self {
+        let listing: Vec<String> = resources
+            .iter()
+            .map(|r| {
+                if r.description.is_empty() {
+                    format!("- {} ({})", r.uri, r.name)
+                } else {
+                    format!("- {} ({}): {}", r.uri, r.name, r.description)
+                }
+            })
+            .collect();
+        let description = format!(
+            "Read a resource from the {} MCP server. Available resources:\n{}",
+            client.name(),
+            listing.join("\n")
+        );
+        Self {
+            qualified_name: qualified_name(client.name(), "read_resource"),
+            client,
+            description,
+            uris: resources.iter().map(|r| r.uri.clone()).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for McpResourceTool {
+    fn name(&self) -> &str {
+        &self.qualified_name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::new(
+            &self.qualified_name,
+            &self.description,
+            json!({
+                "type": "object",
+                "properties": {
+                    "uri": {
+                        "type": "string",
+                        "enum": self.uris,
+                        "description": "The resource URI"
+                    }
+                },
+                "required": ["uri"]
+            }),
+        )
+    }
+
+    async fn execute(&self, args: &str) -> Result<String, String> {
+        let args: Value = serde_json::from_str(args).map_err(|e| format!("Invalid arguments: {}", e))?;
+        let uri = args["uri"].as_str().ok_or("uri parameter required")?;
+        self.client.read_resource(uri).await
+    }
+}
@@ -0,0 +1,306 @@
+//! MCP transports: newline-delimited JSON-RPC over stdio, and streamable HTTP.
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+/// A JSON-RPC connection to an MCP server
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Send a request and wait for its result
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String>;
+
+    /// Send a notification (no response expected)
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String>;
+}
+
+/// Turn a JSON-RPC response into its result or an error message
+fn into_result(response: Value) -> Result<Value, String> {
+    if let Some(error) = response.get("error") {
+        let code = error["code"].as_i64().unwrap_or(0);
+        let message = error["message"].as_str().unwrap_or("unknown error");
+        return Err(format!("MCP error {}: {}", code, message));
+    }
+    Ok(response.get("result").cloned().unwrap_or(Value::Null))
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// Newline-delimited JSON-RPC over a pair of byte streams, usually a child
+/// process's stdout and stdin.
+pub struct StdioTransport {
+    outgoing: mpsc::UnboundedSender<String>,
+    pending: Pending,
+    next_id: AtomicU64,
+    timeout: Duration,
+    _child: Option<tokio::process::Child>,
+    reader: tokio::task::JoinHandle<()>,
+    writer: tokio::task::JoinHandle<()>,
+}
+
+impl StdioTransport {
+    /// Launch a server process and talk to it over stdio
+    pub fn spawn(
+        command: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<Self, String> {
+        let mut child = tokio::process::Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start MCP server '{}': {}", command, e))?;
+
+        let stdout = child.stdout.take().ok_or("MCP server has no stdout")?;
+        let stdin = child.stdin.take().ok_or("MCP server has no stdin")?;
+
+        let mut transport = Self::from_streams(stdout, stdin, timeout);
+        transport._child = Some(child);
+        Ok(transport)
+    }
+
+    /// Use an existing reader/writer pair
+    pub fn from_streams<R, W>(reader: R, writer: W, timeout: Duration) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let (outgoing, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+
+        let writer = tokio::spawn(async move {
+            let mut writer = writer;
+            while let Some(line) = outgoing_rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err()
+                    || writer.write_all(b"\n").await.is_err()
+                    || writer.flush().await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let reader_pending = pending.clone();
+        let reply = outgoing.clone();
+        let reader = tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let message: Value = match serde_json::from_str(&line) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        warn!("Ignoring invalid MCP message: {}", e);
+                        continue;
+                    }
+                };
+
+                match (message.get("id"), message.get("method")) {
+                    // Request from the server; we only answer pings
+                    (Some(id), Some(method)) => {
+                        let response = if method == "ping" {
+                            json!({"jsonrpc": "2.0", "id": id, "result": {}})
+                        } else {
+                            json!({"jsonrpc": "2.0", "id": id, "error": {"code": -32601, "message": "Method not found"}})
+                        };
+                        let _ = reply.send(response.to_string());
+                    }
+                    (Some(id), None) => {
+                        let sender = id
+                            .as_u64()
+                            .and_then(|id| reader_pending.lock().unwrap().remove(&id));
+                        if let Some(sender) = sender {
+                            let _ = sender.send(message);
+                        }
+                    }
+                    (None, Some(method)) => debug!("MCP notification: {}", method),
+                    (None, None) => {}
+                }
+            }
+            // Connection closed: fail every outstanding request
+            reader_pending.lock().unwrap().clear();
+        });
+
+        Self {
+            outgoing,
+            pending,
+            next_id: AtomicU64::new(1),
+            timeout,
+            _child: None,
+            reader,
+            writer,
+        }
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+        self.writer.abort();
+    }
+}
+
+#[async_trait]
+impl Transport for StdioTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        if self.outgoing.send(message.to_string()).is_err() {
+            self.pending.lock().unwrap().remove(&id);
+            return Err("MCP connection closed".to_string());
+        }
+
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(response)) => into_result(response),
+            Ok(Err(_)) => Err("MCP connection closed".to_string()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(format!("MCP request '{}' timed out", method))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        self.outgoing
+            .send(message.to_string())
+            .map_err(|_| "MCP connection closed".to_string())
+    }
+}
+
+/// Streamable HTTP transport: every message is POSTed to a single endpoint,
+/// and the server answers with JSON or a short SSE stream.
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: HashMap<String, String>,
+    session_id: Mutex<Option<String>>,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, headers: HashMap<String, String>, timeout: Duration) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            headers,
+            session_id: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    async fn post(&self, message: &Value) -> Result<reqwest::Response, String> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(message);
+        for (key, value) in &self.headers {
+            request = request.header(key, value);
+        }
+        if let Some(session) = self.session_id.lock().unwrap().clone() {
+            request = request.header("Mcp-Session-Id", session);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("MCP HTTP request failed: {}", e))?;
+
+        if let Some(session) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+            *self.session_id.lock().unwrap() = Some(session.to_string());
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("MCP HTTP error {}: {}", status, body));
+        }
+        Ok(response)
+    }
+}
+
+/// Find the JSON-RPC response with the given id in an SSE body
+fn parse_sse_response(body: &str, id: u64) -> Option<Value> {
+    body.split("\n\n")
+        .filter_map(|event| {
+            let data: Vec<&str> = event
+                .lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|d| d.strip_prefix(' ').unwrap_or(d))
+                .collect();
+            serde_json::from_str::<Value>(&data.join("\n")).ok()
+        })
+        .find(|message| message["id"].as_u64() == Some(id))
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = self.post(&message).await?;
+
+        let is_sse = response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.starts_with("text/event-stream"))
+            .unwrap_or(false);
+        let body = response
+            .text()
+            .await
+            .map_err(|e| format!("MCP HTTP read failed: {}", e))?;
+
+        let response = if is_sse {
+            parse_sse_response(&body, id).ok_or("MCP server sent no response")?
+        } else {
+            serde_json::from_str(&body).map_err(|e| format!("Invalid MCP response: {}", e))?
+        };
+        into_result(response)
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+        self.post(&message).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sse_response() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\nevent: message\ndata: {\"jsonrpc\":\"2.0\",\"id\":7,\"result\":{\"ok\":true}}\n\n";
+        let response = parse_sse_response(body, 7).unwrap();
+        assert_eq!(response["result"]["ok"], true);
+        assert!(parse_sse_response(body, 8).is_none());
+    }
+
+    #[test]
+    fn test_into_result_error() {
+        let err = into_result(json!({"id": 1, "error": {"code": -32602, "message": "bad params"}})).unwrap_err();
+        assert!(err.contains("-32602") && err.contains("bad params"));
+    }
+}
@@ -0,0 +1,281 @@
+//! Tool approval - pause the tool loop until a human confirms a call.
+//!
+//! `tools.approval` maps tool names to `allow`, `ask` or `deny`, with
+//! overrides per `permissions` role. When a call needs approval, the broker
+//! posts a prompt to the chat the request came from and waits for the
+//! requesting user to answer:
+//!
+//! - Discord and Telegram render Approve/Deny buttons. A press comes back
+//!   as an inbound message carrying the `approval_id` metadata key.
+//! - Every other channel falls back to a plain "yes"/"no" reply.
+//!
+//! Answers are taken off the inbound stream by `try_answer` before they
+//! reach the agent, which is blocked waiting for them.
+
+use crate::config::{ApprovalConfig, ApprovalMode};
+use crate::core::bus::MessageBus;
+use crate::types::{InboundMessage, OutboundMessage};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
+
+/// Metadata key linking prompts and button presses to a pending request
+pub const APPROVAL_ID: &str = "approval_id";
+
+/// Longest argument summary shown in a prompt
+const MAX_SUMMARY: usize = 500;
+
+/// Outcome of an approval request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    Approved,
+    Denied,
+    TimedOut,
+}
+
+#[derive(Debug)]
+struct Pending {
+    channel: String,
+    chat_id: String,
+    user_id: String,
+    tool: String,
+    tx: oneshot::Sender<bool>,
+}
+
+/// Decides which calls need approval and tracks the ones waiting for an answer
+#[derive(Debug, Clone)]
+pub struct ApprovalBroker {
+    config: ApprovalConfig,
+    bus: MessageBus,
+    pending: Arc<Mutex<HashMap<String, Pending>>>,
+}
+
+impl ApprovalBroker {
+    pub fn new(config: ApprovalConfig, bus: MessageBus) -> Self {
+        Self {
+            config,
+            bus,
+            pending: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Mode for a tool called by a user with `role`: the role's rules first, then the tool defaults
+    pub fn mode(&self, role: &str, tool: &str) -> ApprovalMode {
+        self.config
+            .roles
+            .get(role)
+            .and_then(|rules| lookup(rules, tool))
+            .or_else(|| lookup(&self.config.tools, tool))
+            .unwrap_or_default()
+    }
+
+    /// Post a prompt to the chat of `msg` and wait for its sender to answer
+    pub async fn request(&self, msg: &InboundMessage, tool: &str, arguments: &serde_json::Value) -> Approval {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().await.insert(
+            id.clone(),
+            Pending {
+                channel: msg.channel.clone(),
+                chat_id: msg.chat_id.clone(),
+                user_id: msg.sender_id.clone(),
+                tool: tool.to_string(),
+                tx,
+            },
+        );
+
+        let mut prompt = OutboundMessage::new(
+            &msg.channel,
+            &msg.chat_id,
+            format!(
+                "Approval needed to run `{}`:\n```\n{}\n```\nReply yes to run it or no to cancel.",
+                tool,
+                summarize(arguments)
+            ),
+        );
+        prompt.metadata.insert(APPROVAL_ID.to_string(), id.clone());
+        self.bus.publish_outbound(prompt).await;
+
+        match tokio::time::timeout(Duration::from_secs(self.config.timeout_secs), rx).await {
+            Ok(Ok(true)) => Approval::Approved,
+            Ok(Ok(false)) | Ok(Err(_)) => Approval::Denied,
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                self.notify(&msg.channel, &msg.chat_id, &format!("Approval for `{}` timed out.", tool)).await;
+                Approval::TimedOut
+            }
+        }
+    }
+
+    /// Resolve a pending request if `msg` answers one. Returns true when the message was consumed.
+    pub async fn try_answer(&self, msg: &InboundMessage) -> bool {
+        let mut pending = self.pending.lock().await;
+
+        let (id, approved) = match msg.metadata.get(APPROVAL_ID) {
+            Some(id) => match parse_answer(&msg.content) {
+                Some(approved) => (id.clone(), approved),
+                None => return true,
+            },
+            None => {
+                let Some(approved) = parse_answer(&msg.content) else { return false };
+                let found = pending.iter().find(|(_, p)| {
+                    p.channel == msg.channel && p.chat_id == msg.chat_id && p.user_id == msg.sender_id
+                });
+                match found {
+                    Some((id, _)) => (id.clone(), approved),
+                    None => return false,
+                }
+            }
+        };
+
+        // Buttons can be pressed by anyone in a group; only the requester decides
+        let Some(request) = pending.get(&id) else { return true };
+        if request.channel != msg.channel || request.user_id != msg.sender_id {
+            return true;
+        }
+        let request = pending.remove(&id).unwrap();
+        drop(pending);
+
+        let _ = request.tx.send(approved);
+        let verdict = if approved { "Approved" } else { "Denied" };
+        self.notify(&request.channel, &request.chat_id, &format!("{} `{}`.", verdict, request.tool)).await;
+        true
+    }
+
+    async fn notify(&self, channel: &str, chat_id: &str, content: &str) {
+        self.bus.publish_outbound(OutboundMessage::new(channel, chat_id, content)).await;
+    }
+}
+
+/// Rule for `tool`: an exact entry wins over the longest matching `prefix*` entry
+fn lookup(rules: &BTreeMap<String, ApprovalMode>, tool: &str) -> Option<ApprovalMode> {
+    if let Some(mode) = rules.get(tool) {
+        return Some(*mode);
+    }
+    rules
+        .iter()
+        .filter_map(|(pattern, mode)| {
+            let prefix = pattern.strip_suffix('*')?;
+            tool.starts_with(prefix).then_some((prefix.len(), *mode))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, mode)| mode)
+}
+
+fn parse_answer(content: &str) -> Option<bool> {
+    match content.trim().trim_end_matches(['.', '!']).to_lowercase().as_str() {
+        "yes" | "y" | "approve" | "ok" => Some(true),
+        "no" | "n" | "deny" | "cancel" => Some(false),
+        _ => None,
+    }
+}
+
+/// Short, readable form of a tool call's arguments
+fn summarize(arguments: &serde_json::Value) -> String {
+    let text = match arguments {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Object(map) if map.len() == 1 => match map.values().next() {
+            Some(serde_json::Value::String(s)) => s.clone(),
+            _ => arguments.to_string(),
+        },
+        _ => serde_json::to_string_pretty(arguments).unwrap_or_default(),
+    };
+    if text.chars().count() > MAX_SUMMARY {
+        format!("{}...", text.chars().take(MAX_SUMMARY).collect::<String>())
+    } else {
+        text
+    }
+}
+
+/// Callback data for an Approve/Deny button
+pub fn button_data(id: &str, approve: bool) -> String {
+    format!("approval:{}:{}", id, if approve { "yes" } else { "no" })
+}
+
+/// Inbound message for a button press, or `None` if `data` is not an approval button
+pub fn button_answer(channel: &str, sender_id: &str, chat_id: &str, data: &str) -> Option<InboundMessage> {
+    let (id, answer) = data.strip_prefix("approval:")?.split_once(':')?;
+    let mut msg = InboundMessage::new(channel, sender_id, chat_id, answer);
+    msg.metadata.insert(APPROVAL_ID.to_string(), id.to_string());
+    Some(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn broker(timeout_secs: u64) -> ApprovalBroker {
+        let config = ApprovalConfig {
+            tools: BTreeMap::from([
+                ("exec".to_string(), ApprovalMode::Ask),
+                ("write_*".to_string(), ApprovalMode::Ask),
+                ("github__*".to_string(), ApprovalMode::Deny),
+            ]),
+            roles: BTreeMap::from([(
+                "admin".to_string(),
+                BTreeMap::from([("exec".to_string(), ApprovalMode::Allow)]),
+            )]),
+            timeout_secs,
+        };
+        ApprovalBroker::new(config, MessageBus::new())
+    }
+
+    #[test]
+    fn test_mode_lookup() {
+        let broker = broker(1);
+        assert_eq!(broker.mode("member", "exec"), ApprovalMode::Ask);
+        assert_eq!(broker.mode("admin", "exec"), ApprovalMode::Allow);
+        assert_eq!(broker.mode("admin", "write_file"), ApprovalMode::Ask);
+        assert_eq!(broker.mode("member", "github__delete_repo"), ApprovalMode::Deny);
+        assert_eq!(broker.mode("member", "read_file"), ApprovalMode::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_text_answer() {
+        let broker = broker(5);
+        let mut outbound = broker.bus.subscribe_outbound();
+        let msg = InboundMessage::new("qq", "7", "room", "clean up");
+
+        let waiting = tokio::spawn({
+            let broker = broker.clone();
+            let msg = msg.clone();
+            async move { broker.request(&msg, "exec", &json!({"cmd": "rm -r build"})).await }
+        });
+
+        let prompt = outbound.recv().await.unwrap();
+        assert!(prompt.content.contains("rm -r build"));
+        assert!(prompt.metadata.contains_key(APPROVAL_ID));
+
+        // Unrelated chatter and other users do not answer
+        assert!(!broker.try_answer(&InboundMessage::new("qq", "7", "room", "what?")).await);
+        assert!(!broker.try_answer(&InboundMessage::new("qq", "8", "room", "yes")).await);
+        assert!(broker.try_answer(&InboundMessage::new("qq", "7", "room", "Yes")).await);
+        assert_eq!(waiting.await.unwrap(), Approval::Approved);
+        assert!(!broker.try_answer(&InboundMessage::new("qq", "7", "room", "yes")).await);
+    }
+
+    #[tokio::test]
+    async fn test_button_answer_and_timeout() {
+        let broker = broker(1);
+        let mut outbound = broker.bus.subscribe_outbound();
+        let msg = InboundMessage::new("telegram", "7", "42", "deploy");
+
+        let waiting = tokio::spawn({
+            let broker = broker.clone();
+            let msg = msg.clone();
+            async move { broker.request(&msg, "exec", &json!({"cmd": "make deploy"})).await }
+        });
+        let id = outbound.recv().await.unwrap().metadata[APPROVAL_ID].clone();
+
+        let stranger = button_answer("telegram", "9", "42", &button_data(&id, true)).unwrap();
+        assert!(broker.try_answer(&stranger).await);
+        let press = button_answer("telegram", "7", "42", &button_data(&id, false)).unwrap();
+        assert!(broker.try_answer(&press).await);
+        assert_eq!(waiting.await.unwrap(), Approval::Denied);
+
+        assert_eq!(broker.request(&msg, "exec", &json!({})).await, Approval::TimedOut);
+        assert!(button_answer("telegram", "7", "42", "other:data").is_none());
+    }
+}
@@ -138,6 +138,7 @@ impl CronTool {
             deliver_response: false,
             deliver_to: Some(chat_id),
             deliver_channel: Some(channel),
+            model: None,
             created_at: chrono::Utc::now(),
             last_run: None,
             next_run: None,
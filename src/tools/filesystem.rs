@@ -3,38 +3,18 @@
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::json;
-use std::path::PathBuf;
-
+use crate::tools::path_policy::PathPolicy;
 use crate::types::ToolDefinition;
 
-/// Resolve path with optional directory restriction
-fn resolve_path(path: &str, allowed_dir: Option<&PathBuf>) -> Result<PathBuf, String> {
-    let resolved = PathBuf::from(path)
-        .canonicalize()
-        .map_err(|_| format!("Path not found: {}", path))?;
-
-    if let Some(dir) = allowed_dir {
-        let allowed = dir.canonicalize().map_err(|_| "Invalid allowed directory")?;
-        if !resolved.starts_with(&allowed) {
-            return Err(format!(
-                "Path {} is outside allowed directory",
-                path
-            ));
-        }
-    }
-
-    Ok(resolved)
-}
-
 /// Read file tool
 #[derive(Debug, Clone)]
 pub struct ReadFileTool {
-    allowed_dir: Option<PathBuf>,
+    paths: PathPolicy,
 }
 
 impl ReadFileTool {
-    pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+    pub fn new(paths: PathPolicy) -> Self {
+        Self { paths }
     }
 }
 
@@ -74,7 +54,7 @@ impl crate::tools::Tool for ReadFileTool {
         let args: Args = serde_json::from_str(args)
             .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let file_path = resolve_path(&args.path, self.allowed_dir.as_ref())?;
+        let file_path = self.paths.check(&args.path)?;
 
         if !file_path.exists() {
             return Err(format!("File not found: {}", args.path));
@@ -93,12 +73,12 @@ impl crate::tools::Tool for ReadFileTool {
 /// Write file tool
 #[derive(Debug, Clone)]
 pub struct WriteFileTool {
-    allowed_dir: Option<PathBuf>,
+    paths: PathPolicy,
 }
 
 impl WriteFileTool {
-    pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+    pub fn new(paths: PathPolicy) -> Self {
+        Self { paths }
     }
 }
 
@@ -143,7 +123,7 @@ impl crate::tools::Tool for WriteFileTool {
         let args: Args = serde_json::from_str(args)
             .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let file_path = resolve_path(&args.path, self.allowed_dir.as_ref())?;
+        let file_path = self.paths.check(&args.path)?;
 
         // Create parent directories
         if let Some(parent) = file_path.parent() {
@@ -167,12 +147,12 @@ impl crate::tools::Tool for WriteFileTool {
 /// Edit file tool (replace text)
 #[derive(Debug, Clone)]
 pub struct EditFileTool {
-    allowed_dir: Option<PathBuf>,
+    paths: PathPolicy,
 }
 
 impl EditFileTool {
-    pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+    pub fn new(paths: PathPolicy) -> Self {
+        Self { paths }
     }
 }
 
@@ -222,7 +202,7 @@ impl crate::tools::Tool for EditFileTool {
         let args: Args = serde_json::from_str(args)
             .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let file_path = resolve_path(&args.path, self.allowed_dir.as_ref())?;
+        let file_path = self.paths.check(&args.path)?;
 
         if !file_path.exists() {
             return Err(format!("File not found: {}", args.path));
@@ -258,12 +238,12 @@ impl crate::tools::Tool for EditFileTool {
 /// List directory tool
 #[derive(Debug, Clone)]
 pub struct ListDirTool {
-    allowed_dir: Option<PathBuf>,
+    paths: PathPolicy,
 }
 
 impl ListDirTool {
-    pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+    pub fn new(paths: PathPolicy) -> Self {
+        Self { paths }
     }
 }
 
@@ -303,7 +283,7 @@ impl crate::tools::Tool for ListDirTool {
         let args: Args = serde_json::from_str(args)
             .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        let dir_path = resolve_path(&args.path, self.allowed_dir.as_ref())?;
+        let dir_path = self.paths.check(&args.path)?;
 
         if !dir_path.exists() {
             return Err(format!("Directory not found: {}", args.path));
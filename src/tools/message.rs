@@ -1,4 +1,8 @@
 //! Message tool for sending messages to users on chat channels.
+//!
+//! Messages can carry files from the workspace, such as a chart or report
+//! the agent wrote. Channels upload them natively, and name files they
+//! cannot upload in the message text instead.
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -6,11 +10,14 @@ use serde_json::json;
 
 use crate::types::{OutboundMessage, ToolDefinition};
 use crate::core::bus::MessageBus;
+use crate::tools::path_policy::PathPolicy;
 
 /// Tool to send messages to users on chat channels.
 #[derive(Debug, Clone)]
 pub struct MessageTool {
     bus: MessageBus,
+    /// Resolves attachment paths, which must be inside the workspace
+    paths: PathPolicy,
     /// Default channel from session context
     default_channel: Option<String>,
     /// Default chat_id from session context
@@ -18,9 +25,10 @@ pub struct MessageTool {
 }
 
 impl MessageTool {
-    pub fn new(bus: &MessageBus) -> Self {
+    pub fn new(bus: &MessageBus, paths: PathPolicy) -> Self {
         Self {
             bus: bus.clone(),
+            paths,
             default_channel: None,
             default_chat_id: None,
         }
@@ -31,6 +39,18 @@ impl MessageTool {
         self.default_channel = Some(channel);
         self.default_chat_id = Some(chat_id);
     }
+
+    /// Resolve an attachment path to an existing file inside the workspace
+    fn attachment(&self, path: &str) -> Result<String, String> {
+        let resolved = self.paths.check(path)?;
+        if !resolved.starts_with(self.paths.workspace()) {
+            return Err(format!("Attachment {} is outside the workspace", path));
+        }
+        if !resolved.is_file() {
+            return Err(format!("Attachment not found: {}", path));
+        }
+        Ok(resolved.display().to_string())
+    }
 }
 
 #[async_trait]
@@ -61,6 +81,11 @@ impl crate::tools::Tool for MessageTool {
                     "chat_id": {
                         "type": "string",
                         "description": "Optional: target chat/user ID"
+                    },
+                    "attachments": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional: paths of workspace files to send, such as images or reports"
                     }
                 },
                 "required": ["content"]
@@ -74,6 +99,8 @@ impl crate::tools::Tool for MessageTool {
             content: String,
             channel: Option<String>,
             chat_id: Option<String>,
+            #[serde(default)]
+            attachments: Vec<String>,
         }
 
         let args: Args = serde_json::from_str(args)
@@ -84,10 +111,50 @@ impl crate::tools::Tool for MessageTool {
         let chat_id = args.chat_id.or(self.default_chat_id.clone())
             .ok_or("Error: No target chat_id specified")?;
 
-        let msg = OutboundMessage::new(&channel, &chat_id, &args.content);
+        let mut msg = OutboundMessage::new(&channel, &chat_id, &args.content);
+        for path in &args.attachments {
+            msg.media.push(self.attachment(path)?);
+        }
+        let attached = msg.media.len();
 
         self.bus.publish_outbound(msg).await;
 
-        Ok(format!("Message sent to {}:{}", channel, chat_id))
+        match attached {
+            0 => Ok(format!("Message sent to {}:{}", channel, chat_id)),
+            n => Ok(format!("Message sent to {}:{} with {} attachment(s)", channel, chat_id, n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Tool;
+
+    #[tokio::test]
+    async fn test_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("ws");
+        std::fs::create_dir(&workspace).unwrap();
+        std::fs::write(workspace.join("chart.png"), b"\x89PNG").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "key").unwrap();
+
+        // Attachments must be inside the workspace even when other paths are allowed
+        let bus = MessageBus::new();
+        let mut rx = bus.subscribe_outbound();
+        let mut tool = MessageTool::new(&bus, PathPolicy::new(&Default::default(), workspace.clone()));
+        tool.set_context("telegram".to_string(), "42".to_string());
+
+        let sent = tool.execute(r#"{"content": "Weekly chart", "attachments": ["chart.png"]}"#).await.unwrap();
+        assert_eq!(sent, "Message sent to telegram:42 with 1 attachment(s)");
+        let msg = rx.recv().await.unwrap();
+        assert_eq!(msg.content, "Weekly chart");
+        assert_eq!(msg.media, vec![workspace.join("chart.png").canonicalize().unwrap().display().to_string()]);
+
+        let outside = tool.execute(r#"{"content": "x", "attachments": ["../secret.txt"]}"#).await;
+        assert!(outside.unwrap_err().contains("outside the workspace"));
+        let missing = tool.execute(r#"{"content": "x", "attachments": ["nope.pdf"]}"#).await;
+        assert!(missing.unwrap_err().contains("not found"));
+        assert!(rx.try_recv().is_err());
     }
 }
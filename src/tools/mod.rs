@@ -4,8 +4,9 @@
 //!
 //! - Web search (Brave Search API)
 //! - Web fetch (URL content extraction)
-//! - Shell execution (with safety guards)
+//! - Shell execution (sandboxed, with safety guards)
 //! - File operations (read, write, list)
+//! - Skill tools (commands shipped in skill directories)
 //!
 //! # Adding New Tools
 //!
@@ -14,17 +15,23 @@
 pub mod web_search;
 pub mod fetch;
 pub mod shell;
+pub mod sandbox;
+pub mod path_policy;
+pub mod approval;
 pub mod filesystem;
 pub mod cron_tool;
 pub mod message;
 pub mod spawn;
 pub mod html;
 pub mod macros;
+pub mod skill_tool;
 
 pub use web_search::{BraveSearch, SearchResult};
 
 use crate::types::ToolDefinition;
 use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Tool trait for extensibility
 #[async_trait::async_trait]
@@ -42,6 +49,40 @@ pub trait Tool: Send + Sync {
     async fn execute(&self, args: &str) -> Result<String, String>;
 }
 
+/// Registry of tools looked up by name
+#[derive(Default, Clone)]
+pub struct ToolRegistry {
+    tools: HashMap<String, Arc<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool. Returns false if a tool with the same name exists.
+    pub fn register(&mut self, tool: Arc<dyn Tool>) -> bool {
+        let name = tool.name().to_string();
+        if self.tools.contains_key(&name) {
+            return false;
+        }
+        self.tools.insert(name, tool);
+        true
+    }
+
+    /// Look up a tool by name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.get(name).cloned()
+    }
+
+    /// Definitions of all registered tools, sorted by name
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        let mut defs: Vec<ToolDefinition> = self.tools.values().map(|t| t.definition()).collect();
+        defs.sort_by(|a, b| a.name.cmp(&b.name));
+        defs
+    }
+}
+
 /// Get all built-in tool definitions
 pub fn get_builtin_tool_definitions() -> Vec<ToolDefinition> {
     vec![
@@ -150,6 +191,11 @@ pub fn get_builtin_tool_definitions() -> Vec<ToolDefinition> {
                     "chat_id": {
                         "type": "string",
                         "description": "Optional: target chat/user ID"
+                    },
+                    "attachments": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "Optional: paths of workspace files to send"
                     }
                 },
                 "required": ["content"]
@@ -0,0 +1,351 @@
+//! Path policy - the single place file tools and `exec` decide which paths they may touch.
+//!
+//! Paths are resolved before they are checked: `~` is expanded, relative
+//! paths are taken from the workspace, and symlinks and `..` are followed
+//! component by component. The components that do not exist yet are kept
+//! as they are, so a write can still target a new file. The resolved path
+//! is then checked like this:
+//!
+//! 1. It is rejected if it matches a `tools.deny_paths` glob, or is the
+//!    config file or the pairing file (either would let the agent grant roles),
+//!    or a secret file named by a `*_file` config key.
+//! 2. When `tools.restrict_to_workspace` is set, it must be inside the
+//!    workspace or match a `tools.allow_paths` glob.
+//!
+//! `exec` checks its working directory like this, and runs step 1 on each
+//! word of the command (`check_arg`).
+//!
+//! Globs support `*` (within one path component), `**` (any depth) and `?`.
+//! Relative globs are taken from the workspace, except ones starting with
+//! `**`, which match anywhere.
+//! A glob also matches everything below a path it matches, so
+//! `~/projects/shared` allows the whole directory.
+
+use regex::Regex;
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Tools;
+use crate::core::permissions::pairing::PairingStore;
+
+/// Symlink hops followed before giving up on a path
+const MAX_SYMLINKS: usize = 40;
+
+#[derive(Debug, Clone)]
+struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    fn new(pattern: &str, base: &Path) -> Result<Self, String> {
+        let expanded = shellexpand::tilde(pattern).into_owned();
+        let absolute = if Path::new(&expanded).is_absolute() || expanded.starts_with("**") {
+            PathBuf::from(expanded)
+        } else {
+            base.join(expanded)
+        };
+        let absolute = absolute.to_string_lossy().trim_end_matches('/').to_string();
+
+        let mut regex = String::from("^");
+        let mut chars = absolute.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' if chars.peek() == Some(&'*') => {
+                    chars.next();
+                    // `**/` also matches zero directories
+                    if chars.peek() == Some(&'/') {
+                        chars.next();
+                        regex.push_str("(?:.*/)?");
+                    } else {
+                        regex.push_str(".*");
+                    }
+                }
+                '*' => regex.push_str("[^/]*"),
+                '?' => regex.push_str("[^/]"),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+
+        let regex = Regex::new(&regex).map_err(|e| format!("Invalid path pattern '{}': {}", pattern, e))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    /// Whether the path or one of its ancestors matches
+    fn matches(&self, path: &Path) -> bool {
+        path.ancestors()
+            .any(|p| self.regex.is_match(&p.to_string_lossy()))
+    }
+}
+
+/// Resolves tool paths and checks them against the workspace and allow/deny globs
+#[derive(Debug, Clone)]
+pub struct PathPolicy {
+    workspace: PathBuf,
+    restrict: bool,
+    allow: Vec<Glob>,
+    deny: Vec<Glob>,
+    protected: Vec<PathBuf>,
+}
+
+impl PathPolicy {
+    /// Policy from `tools` config. Invalid globs are logged and skipped.
+    pub fn new(tools: &Tools, workspace: PathBuf) -> Self {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+        let workspace = resolve(&workspace, &cwd).unwrap_or(workspace);
+        let globs = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|p| match Glob::new(p, &workspace) {
+                    Ok(glob) => Some(glob),
+                    Err(e) => {
+                        tracing::warn!("{}", e);
+                        None
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let allow = globs(&tools.allow_paths);
+        let deny = globs(&tools.deny_paths);
+        let protected = [crate::config::config_path(), PairingStore::path()]
+            .iter()
+            .filter_map(|p| resolve(p, &workspace).ok())
+            .collect();
+
+        Self {
+            restrict: tools.restrict_to_workspace,
+            workspace,
+            allow,
+            deny,
+            protected,
+        }
+    }
+
+    /// Also refuse `paths`, such as the config's secret files
+    pub fn with_protected(mut self, paths: &[PathBuf]) -> Self {
+        let workspace = &self.workspace;
+        self.protected.extend(paths.iter().filter_map(|p| resolve(p, workspace).ok()));
+        self
+    }
+
+    /// Resolved workspace root
+    pub fn workspace(&self) -> &Path {
+        &self.workspace
+    }
+
+    /// Resolve a path given to a tool and check it against the policy
+    pub fn check(&self, path: &str) -> Result<PathBuf, String> {
+        let expanded = shellexpand::tilde(path);
+        let resolved = resolve(Path::new(expanded.as_ref()), &self.workspace)
+            .map_err(|e| format!("Invalid path {}: {}", path, e))?;
+
+        if self.protected.iter().any(|p| resolved.starts_with(p)) {
+            return Err(format!("Access to {} is denied", path));
+        }
+        if let Some(glob) = self.deny.iter().find(|g| g.matches(&resolved)) {
+            return Err(format!("Access to {} is denied by '{}'", path, glob.pattern));
+        }
+        if self.restrict
+            && !resolved.starts_with(&self.workspace)
+            && !self.allow.iter().any(|g| g.matches(&resolved))
+        {
+            return Err(format!("Path {} is outside the workspace", path));
+        }
+        Ok(resolved)
+    }
+
+    /// Refuse an `exec` argument naming a protected or denied path.
+    ///
+    /// Relative words are taken from `cwd`, and a glob word is refused when
+    /// it would match a protected file. Only what is written in the command
+    /// is seen: `grep -r key ~/.openat` or a path built from variables at
+    /// run time still gets through, so bubblewrap mode is the boundary.
+    pub fn check_arg(&self, word: &str, cwd: &Path) -> Result<(), String> {
+        // `--config=~/.openat/config.json` and `<~/.openat/config.json`
+        let value = word.split_once('=').map_or(word, |(_, v)| v);
+        for candidate in [word, value.trim_start_matches(['<', '>'])] {
+            let expanded = shellexpand::tilde(candidate);
+            if let Some(wildcard) = expanded.find(['*', '?']) {
+                // Resolve the directory before the first wildcard, so `../*.key` is matched from the real parent
+                let (dir, pattern) = match expanded[..wildcard].rfind('/') {
+                    Some(0) => ("/", &expanded[1..]),
+                    Some(slash) => (&expanded[..slash], &expanded[slash + 1..]),
+                    None => (".", expanded.as_ref()),
+                };
+                let Ok(base) = resolve(Path::new(dir), cwd) else {
+                    continue;
+                };
+                let glob = Glob::new(pattern, &base)?;
+                if self.protected.iter().any(|p| glob.regex.is_match(&p.to_string_lossy())) {
+                    return Err(format!("Access to {} is denied", word));
+                }
+                continue;
+            }
+            let Ok(resolved) = resolve(Path::new(expanded.as_ref()), cwd) else {
+                continue;
+            };
+            if self.protected.iter().any(|p| resolved.starts_with(p)) {
+                return Err(format!("Access to {} is denied", word));
+            }
+            if let Some(glob) = self.deny.iter().find(|g| g.matches(&resolved)) {
+                return Err(format!("Access to {} is denied by '{}'", word, glob.pattern));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like `check`, but the path must be an existing directory
+    pub fn check_dir(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = self.check(path)?;
+        if !resolved.is_dir() {
+            return Err(format!("Not a directory: {}", path));
+        }
+        Ok(resolved)
+    }
+}
+
+/// Make `path` absolute against `base`, following symlinks and `..`.
+/// Components that do not exist are appended as they are.
+fn resolve(path: &Path, base: &Path) -> Result<PathBuf, String> {
+    let mut hops = 0;
+    resolve_inner(path, base, &mut hops)
+}
+
+fn resolve_inner(path: &Path, base: &Path, hops: &mut usize) -> Result<PathBuf, String> {
+    let mut resolved = if path.is_absolute() {
+        PathBuf::from("/")
+    } else {
+        base.to_path_buf()
+    };
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => resolved = PathBuf::from(component.as_os_str()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::Normal(name) => {
+                let next = resolved.join(name);
+                match std::fs::symlink_metadata(&next) {
+                    Ok(meta) if meta.file_type().is_symlink() => {
+                        *hops += 1;
+                        if *hops > MAX_SYMLINKS {
+                            return Err("too many levels of symbolic links".to_string());
+                        }
+                        let target = std::fs::read_link(&next).map_err(|e| e.to_string())?;
+                        resolved = resolve_inner(&target, &resolved, hops)?;
+                    }
+                    _ => resolved = next,
+                }
+            }
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(dir: &Path, allow: &[&str], deny: &[&str]) -> PathPolicy {
+        let tools = Tools {
+            restrict_to_workspace: true,
+            allow_paths: allow.iter().map(|s| s.to_string()).collect(),
+            deny_paths: deny.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        PathPolicy::new(&tools, dir.join("ws"))
+    }
+
+    #[test]
+    fn test_resolve_new_paths_and_dotdot() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("ws")).unwrap();
+        let policy = policy(dir.path(), &[], &[]);
+        let ws = policy.workspace().to_path_buf();
+
+        assert_eq!(policy.check("notes/new.md").unwrap(), ws.join("notes/new.md"));
+        assert_eq!(policy.check("a/../b.txt").unwrap(), ws.join("b.txt"));
+        assert!(policy.check("../outside.txt").is_err());
+        assert!(policy.check("missing/../../../etc/passwd").is_err());
+        assert!(policy.check("/etc/passwd").is_err());
+        assert!(policy.check("~/.openat/config.json").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_are_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path().join("ws");
+        std::fs::create_dir(&ws).unwrap();
+        std::fs::create_dir(dir.path().join("secret")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret"), ws.join("escape")).unwrap();
+        // Dangling link: writing through it would create a file outside
+        std::os::unix::fs::symlink(dir.path().join("new.txt"), ws.join("dangling")).unwrap();
+        std::os::unix::fs::symlink("sub/..", ws.join("inside")).unwrap();
+        std::fs::create_dir(ws.join("sub")).unwrap();
+
+        let policy = policy(dir.path(), &[], &[]);
+        assert!(policy.check("escape/key").is_err());
+        assert!(policy.check("dangling").is_err());
+        assert!(policy.check("inside/file").is_ok());
+    }
+
+    #[test]
+    fn test_allow_and_deny_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("ws")).unwrap();
+        std::fs::create_dir(dir.path().join("shared")).unwrap();
+        let shared = dir.path().canonicalize().unwrap().join("shared");
+        let policy = policy(
+            dir.path(),
+            &[shared.to_str().unwrap()],
+            &["**/*.pem", "private"],
+        );
+
+        assert!(policy.check(shared.join("docs/a.txt").to_str().unwrap()).is_ok());
+        assert!(policy.check(shared.join("keys/server.pem").to_str().unwrap()).is_err());
+        assert!(policy.check("private/diary.md").is_err());
+        assert!(policy.check("public/diary.md").is_ok());
+    }
+
+    #[test]
+    fn test_unrestricted_still_denies() {
+        let dir = tempfile::tempdir().unwrap();
+        let tools = Tools {
+            deny_paths: vec!["/etc/shadow".to_string()],
+            ..Default::default()
+        };
+        let policy = PathPolicy::new(&tools, dir.path().to_path_buf());
+        assert!(policy.check("/etc/hostname").is_ok());
+        assert!(policy.check("/etc/shadow").is_err());
+        assert!(policy.check(crate::config::config_path().to_str().unwrap()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_secret_files_are_protected() {
+        use crate::config::source::Format;
+        use crate::tools::filesystem::ReadFileTool;
+        use crate::tools::Tool;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("openrouter.key"), "sk-or-secret").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+        let content = r#"{"providers": {"openrouter": {"api_key_file": "openrouter.key"}}}"#;
+        let (config, _) = crate::config::validate::parse(content, Format::Json, dir.path()).unwrap();
+        assert_eq!(config.providers.openrouter.api_key, "sk-or-secret");
+
+        // Unrestricted, so only the protected set keeps the key file out
+        let policy = PathPolicy::new(&Tools::default(), dir.path().join("ws")).with_protected(&config.secret_files);
+        let read = ReadFileTool::new(policy);
+        let secret = dir.path().join("openrouter.key");
+        let refused = read.execute(&serde_json::json!({"path": secret}).to_string()).await.unwrap_err();
+        assert!(refused.contains("denied"), "{}", refused);
+        let notes = dir.path().join("notes.txt");
+        assert_eq!(read.execute(&serde_json::json!({"path": notes}).to_string()).await.unwrap(), "hello");
+    }
+}
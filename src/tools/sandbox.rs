@@ -0,0 +1,640 @@
+//! Sandboxed command runner shared by every tool that executes shell commands.
+//!
+//! - Policy: a deny list applied per simple command (so `rm -r -f` and
+//!   `find -delete` are caught), and an optional allowlist of programs.
+//!   Allowlisted names are looked up on PATH; a program given as a path
+//!   must match an absolute entry exactly. A program name the shell would
+//!   expand (`$x`, `$'rm'`, globs, braces) is unknown, so it is refused.
+//!   The deny list stops mistakes, not a determined caller: arguments can
+//!   still come from variables, so only the allowlist or bubblewrap mode is
+//!   a security boundary
+//! - Limits: wall-clock timeout, per-stream output caps, and rlimits for
+//!   CPU time, address space and process count
+//! - Isolation: optional bubblewrap mode with a read-only root, a writable
+//!   workspace and a private /tmp
+
+use crate::config::{ExecConfig, SandboxMode};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+/// Patterns checked against the raw command line
+const RAW_DENY_PATTERNS: &[&str] = &[
+    r"\bdel\s+/[fq]\b",             // del /f, del /q (Windows)
+    r"\brmdir\s+/s\b",              // rmdir /s (Windows)
+    r">\s*/dev/sd",                 // write to disk devices
+    r">\s*/dev/nvme",               // write to nvme devices
+    r":\(\)\s*\{.*\};\s*:",         // fork bomb
+];
+
+/// Programs that are never allowed
+const DENIED_PROGRAMS: &[&str] = &[
+    "mkfs", "fdisk", "sfdisk", "parted", "diskpart", "format", "wipefs", "shred",
+    "shutdown", "reboot", "poweroff", "halt", "su",
+];
+
+/// Wrappers that run their arguments as another command
+const WRAPPERS: &[&str] = &["sudo", "env", "nice", "nohup", "time", "command", "exec", "xargs", "timeout", "stdbuf", "ionice"];
+
+/// Shell keywords that may precede a command
+const KEYWORDS: &[&str] = &["{", "}", "!", "if", "then", "else", "elif", "do", "while", "until"];
+
+/// Split a shell command line into simple commands (lists of words).
+///
+/// Handles quoting and escapes, and treats `;`, `&`, `|`, newlines,
+/// parentheses, backticks and `$(` as command boundaries so that
+/// commands inside substitutions and subshells are checked too.
+pub fn split_commands(command: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    let end_word = |word: &mut String, in_word: &mut bool, words: &mut Vec<String>| {
+        if *in_word {
+            words.push(std::mem::take(word));
+            *in_word = false;
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(next) = chars.next() {
+                                word.push(next);
+                            }
+                        }
+                        _ => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+            }
+            ';' | '&' | '|' | '\n' | '(' | ')' | '`' => {
+                end_word(&mut word, &mut in_word, &mut words);
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            '$' if chars.peek() == Some(&'(') => {
+                end_word(&mut word, &mut in_word, &mut words);
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            c if c.is_whitespace() => end_word(&mut word, &mut in_word, &mut words),
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    end_word(&mut word, &mut in_word, &mut words);
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    commands
+}
+
+fn basename(word: &str) -> &str {
+    word.rsplit('/').next().unwrap_or(word)
+}
+
+/// Skip environment assignments, redirections and wrappers; return the program
+/// as written and its arguments
+fn program_and_args(words: &[String]) -> Option<(&str, &[String])> {
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].as_str();
+        let name = basename(word);
+        let assignment = word.contains('=') && !word.starts_with('-') && !word.starts_with('=');
+        let redirect = word.starts_with('>')
+            || word.starts_with('<')
+            || (word.chars().next().is_some_and(|c| c.is_ascii_digit()) && word.contains('>'));
+        if KEYWORDS.contains(&word) || assignment || redirect {
+            i += 1;
+        } else if WRAPPERS.contains(&name) {
+            i += 1;
+            // Skip wrapper options and durations like `timeout 5` or `nice -n 10`
+            while i < words.len() && (words[i].starts_with('-') || words[i].parse::<f64>().is_ok()) {
+                i += 1;
+            }
+        } else {
+            return Some((word, &words[i + 1..]));
+        }
+    }
+    None
+}
+
+/// Whether the shell would expand `program` into a name only known at run time
+fn is_expanded(program: &str) -> bool {
+    if program == "[" || program == "[[" {
+        return false;
+    }
+    program.contains(['$', '*', '?', '[', '{', '}'])
+}
+
+/// Reason a single simple command is blocked, if any
+fn deny_reason(program: &str, args: &[String]) -> Option<&'static str> {
+    let has_short_flag = |flags: &str| {
+        args.iter().any(|a| {
+            a.starts_with('-') && !a.starts_with("--") && a[1..].chars().any(|c| flags.contains(c))
+        })
+    };
+
+    if DENIED_PROGRAMS.contains(&program) || program.starts_with("mkfs.") {
+        return Some("system or disk administration command");
+    }
+
+    match program {
+        "rm" if has_short_flag("rR") || args.iter().any(|a| a == "--recursive") => {
+            Some("recursive delete")
+        }
+        "find" => {
+            let deletes = args.iter().any(|a| a == "-delete");
+            let execs_rm = args.windows(2).any(|w| {
+                matches!(w[0].as_str(), "-exec" | "-execdir" | "-ok" | "-okdir") && basename(&w[1]) == "rm"
+            });
+            (deletes || execs_rm).then_some("find with delete")
+        }
+        "dd" if args.iter().any(|a| a.starts_with("of=/dev/")) => Some("raw disk write"),
+        "chmod" if args.iter().any(|a| a == "777" || a.ends_with("=rwx") && a.starts_with('a')) => {
+            Some("world-writable permissions")
+        }
+        "chown" if args.iter().any(|a| a == "root" || a.starts_with("root:") || a.ends_with(":root")) => {
+            Some("chown to root")
+        }
+        "init" | "telinit" if args.iter().any(|a| a == "0" || a == "6") => Some("system power command"),
+        "systemctl" if args.iter().any(|a| matches!(a.as_str(), "poweroff" | "reboot" | "halt" | "kexec")) => {
+            Some("system power command")
+        }
+        _ => None,
+    }
+}
+
+/// A path with its directory canonicalized. The file itself is not
+/// followed, so `/bin/ls` stays distinct from other links to a multi-call binary.
+fn canonical(path: &Path) -> Option<PathBuf> {
+    let dir = std::fs::canonicalize(path.parent()?).ok()?;
+    Some(dir.join(path.file_name()?))
+}
+
+/// Where the shell finds a program name, from the absolute directories on PATH
+fn resolve_program(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .filter(|dir| dir.is_absolute())
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+        .and_then(|candidate| canonical(&candidate))
+}
+
+/// Whether the allowlist admits `program`, as written in the command
+fn is_allowed(program: &str, allowed_commands: &[String]) -> bool {
+    if program.contains('/') {
+        // `./ls` or a copied binary is not `ls`
+        return Path::new(program).is_absolute() && allowed_commands.iter().any(|a| a == program);
+    }
+    allowed_commands.iter().any(|entry| {
+        if entry.contains('/') {
+            // `/usr/bin/git` admits `git` only when PATH finds that very file
+            let found = resolve_program(program);
+            found.is_some() && found == canonical(Path::new(entry))
+        } else {
+            entry == program
+        }
+    })
+}
+
+/// Check a command against the deny list and, when configured, the allowlist.
+pub fn check_command(command: &str, allowed_commands: &[String]) -> Result<(), String> {
+    let lowered = command.trim().to_lowercase();
+    for pattern in RAW_DENY_PATTERNS {
+        if regex::Regex::new(pattern).unwrap().is_match(&lowered) {
+            return Err("Error: Command blocked by safety guard (dangerous pattern detected)".to_string());
+        }
+    }
+
+    if !allowed_commands.is_empty() && ["$(", "`", "<(", ">(", "eval "].iter().any(|p| command.contains(p)) {
+        return Err("Error: Command blocked by allowlist (command substitution is not allowed)".to_string());
+    }
+
+    for words in split_commands(command) {
+        let Some((word, args)) = program_and_args(&words) else {
+            continue;
+        };
+        if is_expanded(word) {
+            return Err(format!("Error: Command blocked by safety guard ('{}' is expanded by the shell)", word));
+        }
+        let program = basename(word);
+        if let Some(reason) = deny_reason(program, args) {
+            return Err(format!("Error: Command blocked by safety guard ({})", reason));
+        }
+        // Check scripts passed to a nested shell, e.g. `sh -c 'rm -rf /'`
+        if program == "eval" {
+            check_command(&args.join(" "), allowed_commands)?;
+        }
+        if matches!(program, "sh" | "bash" | "dash" | "zsh" | "ksh")
+            && let Some(pos) = args.iter().position(|a| a == "-c")
+            && let Some(script) = args.get(pos + 1)
+        {
+            check_command(script, allowed_commands)?;
+        }
+        if !allowed_commands.is_empty() {
+            // A PATH assignment would change which file a name runs
+            if words.iter().any(|w| w.starts_with("PATH=")) {
+                return Err("Error: Command blocked by allowlist (PATH cannot be changed)".to_string());
+            }
+            if !is_allowed(word, allowed_commands) {
+                return Err(format!("Error: Command blocked by allowlist ('{}' is not allowed)", word));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Result of a sandboxed command
+#[derive(Debug, Clone, Default)]
+pub struct ExecOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: Option<i32>,
+    pub truncated: bool,
+}
+
+impl ExecOutput {
+    pub fn success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// Format for the model: stdout, then stderr and the exit code if relevant
+    pub fn to_text(&self) -> String {
+        let mut result = self.stdout.clone();
+
+        if !self.stderr.trim().is_empty() {
+            if !result.is_empty() {
+                result.push_str("\nSTDERR:\n");
+            }
+            result.push_str(&self.stderr);
+        }
+
+        if !self.success() {
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            match self.exit_code {
+                Some(code) => result.push_str(&format!("Exit code: {}", code)),
+                None => result.push_str("Killed by signal (resource limit exceeded?)"),
+            }
+        }
+
+        if self.truncated {
+            result.push_str("\n... (output truncated)");
+        }
+
+        if result.is_empty() {
+            result = "(no output)".to_string();
+        }
+        result
+    }
+}
+
+/// Read a stream to the end, keeping at most `cap` bytes
+async fn read_capped<R: AsyncRead + Unpin>(mut reader: R, cap: usize) -> (Vec<u8>, bool) {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buf = [0u8; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let room = cap.saturating_sub(kept.len());
+                kept.extend_from_slice(&buf[..n.min(room)]);
+                truncated |= n > room;
+            }
+        }
+    }
+    (kept, truncated)
+}
+
+/// Runs shell commands under the configured limits and isolation
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    config: ExecConfig,
+    workspace: PathBuf,
+}
+
+impl Sandbox {
+    pub fn new(config: &ExecConfig, workspace: PathBuf) -> Self {
+        Self {
+            config: config.clone(),
+            workspace,
+        }
+    }
+
+    /// Same sandbox with a different wall-clock timeout
+    pub fn with_timeout(&self, timeout_secs: u64) -> Self {
+        let mut sandbox = self.clone();
+        sandbox.config.timeout_secs = timeout_secs;
+        sandbox
+    }
+
+    /// Check a command against the policy without running it
+    pub fn check(&self, command: &str) -> Result<(), String> {
+        check_command(command, &self.config.allowed_commands)
+    }
+
+    fn build_command(&self, command: &str, cwd: &Path) -> tokio::process::Command {
+        let mut cmd = match self.config.sandbox {
+            SandboxMode::None => tokio::process::Command::new("sh"),
+            SandboxMode::Bwrap => {
+                let mut cmd = tokio::process::Command::new("bwrap");
+                cmd.args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc", "--tmpfs", "/tmp"]);
+                cmd.arg("--bind").arg(&self.workspace).arg(&self.workspace);
+                cmd.args(["--unshare-all", "--die-with-parent"]);
+                if self.config.network {
+                    cmd.arg("--share-net");
+                }
+                cmd.arg("--chdir").arg(cwd);
+                cmd.arg("sh");
+                cmd
+            }
+        };
+        cmd.arg("-c").arg(command).current_dir(cwd);
+        cmd
+    }
+
+    /// Run a command, optionally feeding `stdin`, with extra environment variables
+    pub async fn run(
+        &self,
+        command: &str,
+        cwd: &Path,
+        stdin: Option<&str>,
+        envs: &[(&str, &str)],
+    ) -> Result<ExecOutput, String> {
+        self.check(command)?;
+
+        let mut cmd = self.build_command(command, cwd);
+        cmd.envs(envs.iter().copied())
+            .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        #[cfg(unix)]
+        {
+            let (cpu, memory, processes) = (self.config.cpu_secs, self.config.memory_mb, self.config.max_processes);
+            // SAFETY: only async-signal-safe libc calls between fork and exec
+            unsafe {
+                cmd.pre_exec(move || {
+                    // New process group, so a timeout kills the whole tree
+                    libc::setsid();
+                    set_limit(libc::RLIMIT_CPU, cpu);
+                    set_limit(libc::RLIMIT_AS, memory.saturating_mul(1024 * 1024));
+                    set_limit(libc::RLIMIT_NPROC, processes);
+                    Ok(())
+                });
+            }
+        }
+
+        let mut child = cmd.spawn().map_err(|e| match self.config.sandbox {
+            SandboxMode::Bwrap => format!("Failed to start bwrap (is bubblewrap installed?): {}", e),
+            SandboxMode::None => format!("Failed to execute command: {}", e),
+        })?;
+
+        // Input is written alongside reading the output and under the timeout,
+        // so a command that never reads it cannot hang the tool
+        let pipe = child.stdin.take();
+        let feed = async move {
+            if let (Some(input), Some(mut pipe)) = (stdin, pipe) {
+                // A command that ignores stdin may exit before reading it
+                let _ = pipe.write_all(input.as_bytes()).await;
+            }
+        };
+
+        let cap = self.config.max_output_bytes;
+        let stdout = child.stdout.take().ok_or("No stdout")?;
+        let stderr = child.stderr.take().ok_or("No stderr")?;
+        let pid = child.id();
+
+        let run = async {
+            let (status, (out, out_cut), (err, err_cut), ()) =
+                tokio::join!(child.wait(), read_capped(stdout, cap), read_capped(stderr, cap), feed);
+            (status, out, err, out_cut || err_cut)
+        };
+
+        match tokio::time::timeout(Duration::from_secs(self.config.timeout_secs), run).await {
+            Ok((status, out, err, truncated)) => {
+                let status = status.map_err(|e| format!("Failed to execute command: {}", e))?;
+                Ok(ExecOutput {
+                    stdout: String::from_utf8_lossy(&out).to_string(),
+                    stderr: String::from_utf8_lossy(&err).to_string(),
+                    exit_code: status.code(),
+                    truncated,
+                })
+            }
+            Err(_) => {
+                #[cfg(unix)]
+                if let Some(pid) = pid {
+                    // SAFETY: signalling the process group created by setsid above
+                    unsafe {
+                        libc::kill(-(pid as i32), libc::SIGKILL);
+                    }
+                }
+                Err(format!("Error: Command timed out after {} seconds", self.config.timeout_secs))
+            }
+        }
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type RlimitResource = libc::c_int;
+
+#[cfg(unix)]
+fn set_limit(resource: RlimitResource, value: u64) {
+    if value == 0 {
+        return;
+    }
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    // SAFETY: setrlimit only reads the struct we pass
+    unsafe {
+        libc::setrlimit(resource, &limit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox(config: ExecConfig) -> (Sandbox, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        (Sandbox::new(&config, dir.path().to_path_buf()), dir)
+    }
+
+    #[test]
+    fn test_split_commands() {
+        let commands = split_commands(r#"echo "a; b" && ls -la | grep 'x y'; $(whoami)"#);
+        assert_eq!(commands, vec![
+            vec!["echo", "a; b"],
+            vec!["ls", "-la"],
+            vec!["grep", "x y"],
+            vec!["whoami"],
+        ]);
+    }
+
+    #[test]
+    fn test_deny_list_catches_bypasses() {
+        let blocked = [
+            "rm -rf /",
+            "rm -r -f ~/x",
+            "rm --recursive dir",
+            "/bin/rm -R dir",
+            "find . -delete",
+            "find / -exec rm {} \\;",
+            "echo ok; sudo rm -fr /",
+            "if true; then rm -r x; fi",
+            "{ rm -r x; }",
+            "bash -c 'rm -r -f /'",
+            "echo $(rm -r -f /tmp/x)",
+            "FOO=1 rm -r x",
+            "mkfs.ext4 /dev/sda1",
+            "dd if=/dev/zero of=/dev/sda",
+            "chmod 777 file",
+            "systemctl reboot",
+            ":(){ :|:& };:",
+        ];
+        for command in blocked {
+            assert!(check_command(command, &[]).is_err(), "should block: {}", command);
+        }
+
+        let allowed = ["rm file.txt", "find . -name '*.rs'", "echo 'rm -rf /'", "ls -la", "git status", "echo ${HOME}"];
+        for command in allowed {
+            assert!(check_command(command, &[]).is_ok(), "should allow: {}", command);
+        }
+    }
+
+    #[test]
+    fn test_deny_list_catches_expanded_programs() {
+        let blocked = [
+            "x=rm; $x -rf /",
+            "$'rm' -rf /",
+            "\"r\"\"m\" -rf /",
+            "\\rm -rf /",
+            "${x} -rf /",
+            "\"$x\" -rf /",
+            "/bin/r? -rf /",
+            "/bin/r[m] -rf /",
+            "r{m,} -rf /",
+            "eval 'rm -rf /'",
+            "sudo $x -rf /",
+        ];
+        for command in blocked {
+            assert!(check_command(command, &[]).is_err(), "should block: {}", command);
+        }
+
+        let allowed = ["echo $HOME", "ls *.rs", "[ -f Cargo.toml ] && echo yes", "eval echo hi"];
+        for command in allowed {
+            assert!(check_command(command, &[]).is_ok(), "should allow: {}", command);
+        }
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let allowed = vec!["ls".to_string(), "/usr/bin/git".to_string()];
+        assert!(check_command("ls -la | git log", &allowed).is_ok());
+        assert!(check_command("ls ${HOME}", &allowed).is_ok());
+        assert!(check_command("ls; curl example.com", &allowed).is_err());
+        assert!(check_command("ls $(curl example.com)", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_allowlist_compares_resolved_programs() {
+        let allowed = vec!["ls".to_string(), "/usr/bin/git".to_string()];
+        // A program the agent wrote, or a copy elsewhere, is not the allowlisted one
+        assert!(check_command("./ls", &allowed).is_err());
+        assert!(check_command("/tmp/x/git status", &allowed).is_err());
+        assert!(check_command("PATH=/tmp/x git status", &allowed).is_err());
+        assert!(check_command("/usr/bin/git status", &allowed).is_ok());
+        assert!(!is_allowed("/usr/bin/ls", &allowed));
+
+        let dir = tempfile::tempdir().unwrap();
+        let git = dir.path().join("git");
+        std::fs::write(&git, "").unwrap();
+        let entry = vec![git.display().to_string()];
+        // `git` on PATH is not the file in the allowlist
+        assert!(!is_allowed("git", &entry));
+        assert!(is_allowed(&git.display().to_string(), &entry));
+    }
+
+    #[tokio::test]
+    async fn test_run_output_and_exit_code() {
+        let (sandbox, dir) = sandbox(ExecConfig::default());
+        let output = sandbox.run("echo hi; echo err >&2; exit 3", dir.path(), None, &[]).await.unwrap();
+        assert_eq!(output.stdout, "hi\n");
+        assert_eq!(output.stderr, "err\n");
+        assert_eq!(output.exit_code, Some(3));
+        assert!(output.to_text().contains("Exit code: 3"));
+
+        let output = sandbox.run("cat; echo $EXTRA", dir.path(), Some("in "), &[("EXTRA", "env")]).await.unwrap();
+        assert_eq!(output.stdout, "in env\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_timeout_and_output_cap() {
+        let (sandbox, dir) = sandbox(ExecConfig {
+            timeout_secs: 1,
+            max_output_bytes: 100,
+            ..Default::default()
+        });
+        let err = sandbox.run("sleep 5", dir.path(), None, &[]).await.unwrap_err();
+        assert!(err.contains("timed out"));
+
+        // More input than a pipe holds, for a command that never reads it
+        let started = std::time::Instant::now();
+        let input = "x".repeat(1024 * 1024);
+        let err = sandbox.run("sleep 5", dir.path(), Some(&input), &[]).await.unwrap_err();
+        assert!(err.contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let output = sandbox.run("head -c 100000 /dev/zero | tr '\\0' a", dir.path(), None, &[]).await.unwrap();
+        assert_eq!(output.stdout.len(), 100);
+        assert!(output.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_run_cpu_limit() {
+        let (sandbox, dir) = sandbox(ExecConfig {
+            cpu_secs: 1,
+            timeout_secs: 10,
+            ..Default::default()
+        });
+        let output = sandbox.run("while :; do :; done", dir.path(), None, &[]).await.unwrap();
+        assert!(!output.success());
+        assert_eq!(output.exit_code, None);
+    }
+}
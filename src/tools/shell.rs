@@ -1,61 +1,29 @@
 //! Shell execution tool.
+//!
+//! The working directory goes through the `PathPolicy` like any file tool
+//! path. Arguments naming a protected or denied file are refused too, but
+//! that only covers paths written out in the command; see
+//! `PathPolicy::check_arg`.
 
 use async_trait::async_trait;
 use serde::Deserialize;
 use serde_json::json;
-
+use crate::tools::path_policy::PathPolicy;
+use crate::tools::sandbox::{split_commands, Sandbox};
 use crate::types::ToolDefinition;
 
 /// Shell execution tool with safety guards.
 #[derive(Debug, Clone)]
 pub struct ShellTool {
-    /// Timeout in seconds
-    timeout: u64,
-    /// Working directory
-    working_dir: Option<String>,
-    /// Maximum output length
-    max_output: usize,
+    /// Runner enforcing timeout, output caps, rlimits and command policy
+    sandbox: Sandbox,
+    /// Policy for the working directory and path arguments; commands run in the workspace by default
+    paths: PathPolicy,
 }
 
 impl ShellTool {
-    pub fn new(timeout: u64, working_dir: Option<String>) -> Self {
-        Self {
-            timeout,
-            working_dir,
-            max_output: 10000,
-        }
-    }
-
-    /// Check if command contains dangerous patterns
-    fn guard_command(&self, command: &str) -> Option<String> {
-        let cmd = command.trim().to_lowercase();
-
-        // Dangerous patterns that should be blocked
-        let deny_patterns = [
-            r"\brm\s+-[rf]{1,2}\b",        // rm -r, rm -rf, rm -fr
-            r"\bdel\s+/[fq]\b",             // del /f, del /q (Windows)
-            r"\brmdir\s+/s\b",              // rmdir /s (Windows)
-            r"\b(format|mkfs|diskpart)\b",  // disk formatting
-            r"\bdd\s+if=",                  // dd disk operations
-            r">\s*/dev/sd",                 // write to disk devices
-            r">\s*/dev/nvme",               // write to nvme devices
-            r"\b(shutdown|reboot|poweroff)\b", // system power commands
-            r":\(\)\s*\{.*\};\s*:",         // fork bomb
-            r"\bsudo\s+su\b",               // sudo to root
-            r"\bchmod\s+777\b",             // overly permissive permissions
-            r"\bchown\s+.*:\s*root",        // chown to root
-        ];
-
-        for pattern in &deny_patterns {
-            if regex::Regex::new(pattern)
-                .unwrap()
-                .is_match(&cmd)
-            {
-                return Some("Error: Command blocked by safety guard (dangerous pattern detected)".to_string());
-            }
-        }
-
-        None
+    pub fn new(sandbox: Sandbox, paths: PathPolicy) -> Self {
+        Self { sandbox, paths }
     }
 }
 
@@ -100,58 +68,47 @@ impl crate::tools::Tool for ShellTool {
         let args: Args = serde_json::from_str(args)
             .map_err(|e| format!("Invalid arguments: {}", e))?;
 
-        // Safety guard
-        if let Some(error) = self.guard_command(&args.cmd) {
-            return Err(error);
-        }
-
-        let cwd = args.working_dir.or(self.working_dir.clone());
-
-        let output = tokio::process::Command::new("sh")
-            .arg("-c")
-            .arg(&args.cmd)
-            .current_dir(cwd.unwrap_or_else(|| ".".to_string()))
-            .kill_on_drop(true)
-            .output()
-            .await
-            .map_err(|e| format!("Failed to execute command: {}", e))?;
-
-        let mut result = String::new();
-
-        if !output.stdout.is_empty() {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            result.push_str(&stdout);
-        }
-
-        if !output.stderr.is_empty() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            if !stderr.trim().is_empty() {
-                if !result.is_empty() {
-                    result.push_str("\nSTDERR:\n");
-                }
-                result.push_str(&stderr);
-            }
-        }
-
-        if output.status.code() != Some(0) {
-            let code = output.status.code().unwrap_or(-1);
-            if !result.is_empty() {
-                result.push_str("\n");
-            }
-            result.push_str(&format!("Exit code: {}", code));
-        }
-
-        if result.is_empty() {
-            result = "(no output)".to_string();
+        let cwd = match &args.working_dir {
+            Some(dir) => self.paths.check_dir(dir)?,
+            None => self.paths.workspace().to_path_buf(),
+        };
+        for word in split_commands(&args.cmd).iter().flatten() {
+            self.paths.check_arg(word, &cwd)?;
         }
+        let output = self.sandbox.run(&args.cmd, &cwd, None, &[]).await?;
+        Ok(output.to_text())
+    }
+}
 
-        // Truncate very long output
-        if result.len() > self.max_output {
-            let truncated = result.len() - self.max_output;
-            result.truncate(self.max_output);
-            result.push_str(&format!("\n... (truncated, {} more chars)", truncated));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ExecConfig, Tools};
+    use crate::tools::Tool;
+
+    #[tokio::test]
+    async fn test_protected_paths_in_arguments() {
+        let dir = tempfile::tempdir().unwrap();
+        let ws = dir.path().join("ws");
+        std::fs::create_dir(&ws).unwrap();
+        std::fs::write(dir.path().join("openrouter.key"), "sk-or-secret").unwrap();
+        std::fs::write(ws.join("notes.txt"), "hello").unwrap();
+        let paths = PathPolicy::new(&Tools::default(), ws.clone()).with_protected(&[dir.path().join("openrouter.key")]);
+        let exec = ShellTool::new(Sandbox::new(&ExecConfig::default(), ws.clone()), paths);
+        let args = |cmd: &str| json!({"cmd": cmd}).to_string();
+
+        let config = crate::config::config_path();
+        for cmd in [
+            format!("cat {}", config.display()),
+            "cat ../openrouter.key".to_string(),
+            "cat ../*.key".to_string(),
+            "echo hi && head -c 5 <../openrouter.key".to_string(),
+            "grep --file=../openrouter.key notes.txt".to_string(),
+        ] {
+            let refused = exec.execute(&args(&cmd)).await.unwrap_err();
+            assert!(refused.contains("denied"), "{}: {}", cmd, refused);
         }
-
-        Ok(result)
+        assert_eq!(exec.execute(&args("cat notes.txt")).await.unwrap(), "hello");
+        assert_eq!(exec.execute(&args("ls ..")).await.unwrap().lines().count(), 2);
     }
 }
@@ -0,0 +1,196 @@
+//! Skill tools - executable tools shipped inside skill directories.
+//!
+//! A skill declares tools in its `SKILL.md` frontmatter:
+//!
+//! ```yaml
+//! tools:
+//!   - name: jira_search
+//!     description: Search Jira issues
+//!     command: ./search.sh
+//!     timeout: 30
+//!     parameters:
+//!       type: object
+//!       properties:
+//!         query: { type: string }
+//!       required: [query]
+//! ```
+//!
+//! The command runs in the exec sandbox inside the skill directory. Arguments
+//! are written to stdin as JSON and the tool result is read from stdout.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+use crate::tools::sandbox::Sandbox;
+use crate::types::ToolDefinition;
+
+/// Tool declaration in skill frontmatter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillToolSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// JSON schema of the arguments
+    #[serde(default = "default_parameters")]
+    pub parameters: Value,
+    /// Command to run, relative to the skill directory
+    pub command: String,
+    /// Timeout in seconds; defaults to `tools.exec.timeout_secs`
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+fn default_parameters() -> Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+impl SkillToolSpec {
+    /// Check the declaration, returning a reason when it cannot be used
+    pub fn validate(&self) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self.name.len() <= 64
+            && self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(format!("invalid tool name '{}'", self.name));
+        }
+        if self.command.trim().is_empty() {
+            return Err(format!("tool '{}' has no command", self.name));
+        }
+        if !self.parameters.is_object() {
+            return Err(format!("tool '{}' parameters must be a JSON schema object", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// A tool backed by a command inside a skill directory
+#[derive(Debug, Clone)]
+pub struct SkillTool {
+    spec: SkillToolSpec,
+    skill_name: String,
+    skill_dir: PathBuf,
+    sandbox: Sandbox,
+}
+
+impl SkillTool {
+    pub fn new(spec: SkillToolSpec, skill_name: &str, skill_dir: PathBuf, sandbox: &Sandbox) -> Self {
+        let sandbox = match spec.timeout {
+            Some(timeout) => sandbox.with_timeout(timeout),
+            None => sandbox.clone(),
+        };
+        Self {
+            spec,
+            skill_name: skill_name.to_string(),
+            skill_dir,
+            sandbox,
+        }
+    }
+}
+
+#[async_trait]
+impl crate::tools::Tool for SkillTool {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.spec.description
+    }
+
+    fn definition(&self) -> ToolDefinition {
+        let description = if self.spec.description.is_empty() {
+            format!("Tool provided by the {} skill.", self.skill_name)
+        } else {
+            self.spec.description.clone()
+        };
+        ToolDefinition::new(&self.spec.name, &description, self.spec.parameters.clone())
+    }
+
+    async fn execute(&self, args: &str) -> Result<String, String> {
+        let args = if args.trim().is_empty() { "{}" } else { args };
+        let skill_dir = self.skill_dir.display().to_string();
+
+        let output = self
+            .sandbox
+            .run(&self.spec.command, &self.skill_dir, Some(args), &[("SKILL_DIR", &skill_dir)])
+            .await
+            .map_err(|e| format!("Tool '{}': {}", self.spec.name, e))?;
+
+        if !output.success() {
+            let detail = if output.stderr.trim().is_empty() { &output.stdout } else { &output.stderr };
+            return Err(format!(
+                "Tool '{}' failed (exit code {}): {}",
+                self.spec.name,
+                output.exit_code.unwrap_or(-1),
+                detail.trim()
+            ));
+        }
+
+        let mut result = output.stdout.trim_end().to_string();
+        if output.truncated {
+            result.push_str("\n... (output truncated)");
+        }
+        if result.is_empty() {
+            result = "(no output)".to_string();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ExecConfig;
+    use crate::tools::Tool;
+
+    fn tool(command: &str, dir: &tempfile::TempDir) -> SkillTool {
+        let sandbox = Sandbox::new(&ExecConfig::default(), dir.path().to_path_buf());
+        SkillTool::new(spec(command), "test", dir.path().to_path_buf(), &sandbox)
+    }
+
+    fn spec(command: &str) -> SkillToolSpec {
+        SkillToolSpec {
+            name: "echo_args".to_string(),
+            description: String::new(),
+            parameters: default_parameters(),
+            command: command.to_string(),
+            timeout: Some(2),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skill_tool_reads_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = tool("cat", &dir);
+        let result = tool.execute(r#"{"q":"hi"}"#).await.unwrap();
+        assert_eq!(result, r#"{"q":"hi"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_skill_tool_failure_and_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let failing = tool("echo oops >&2; exit 3", &dir);
+        let err = failing.execute("{}").await.unwrap_err();
+        assert!(err.contains("exit code 3") && err.contains("oops"));
+
+        let slow = tool("sleep 5", &dir);
+        assert!(slow.execute("{}").await.unwrap_err().contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_skill_tool_guarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = tool("rm -rf /tmp/x", &dir);
+        assert!(tool.execute("{}").await.unwrap_err().contains("safety guard"));
+    }
+
+    #[test]
+    fn test_spec_validate() {
+        assert!(spec("cat").validate().is_ok());
+        let mut bad = spec("cat");
+        bad.name = "has space".to_string();
+        assert!(bad.validate().is_err());
+    }
+}
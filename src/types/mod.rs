@@ -20,11 +20,33 @@ pub enum MessageRole {
     Tool,
 }
 
+/// A non-text part of a message's content
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ContentPart {
+    /// An image the provider fetches from a URL
+    ImageUrl(String),
+    /// An image sent inline, base64-encoded
+    Image { mime_type: String, data: String },
+}
+
+impl ContentPart {
+    /// URL of the image; inline images become `data:` URLs
+    pub fn url(&self) -> String {
+        match self {
+            ContentPart::ImageUrl(url) => url.clone(),
+            ContentPart::Image { mime_type, data } => format!("data:{};base64,{}", mime_type, data),
+        }
+    }
+}
+
 /// A message in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: MessageRole,
     pub content: String,
+    /// Parts sent after the text, such as images for vision models
+    #[serde(default)]
+    pub parts: Vec<ContentPart>,
     pub name: Option<String>,
     pub tool_calls: Vec<ToolCall>,
     pub tool_call_id: Option<String>,
@@ -36,6 +58,7 @@ impl Message {
         Self {
             role: MessageRole::System,
             content: content.to_string(),
+            parts: vec![],
             name: None,
             tool_calls: vec![],
             tool_call_id: None,
@@ -47,6 +70,7 @@ impl Message {
         Self {
             role: MessageRole::User,
             content: content.to_string(),
+            parts: vec![],
             name: None,
             tool_calls: vec![],
             tool_call_id: None,
@@ -58,6 +82,7 @@ impl Message {
         Self {
             role: MessageRole::Assistant,
             content: content.to_string(),
+            parts: vec![],
             name: None,
             tool_calls: vec![],
             tool_call_id: None,
@@ -69,6 +94,7 @@ impl Message {
         Self {
             role: MessageRole::Tool,
             content: content.to_string(),
+            parts: vec![],
             name: Some(name.to_string()),
             tool_calls: vec![],
             tool_call_id: Some(tool_call_id.to_string()),
@@ -86,7 +112,15 @@ impl Message {
         };
         map.insert("role".to_string(), json!(role_str));
 
-        if !self.content.is_empty() {
+        // With parts, content is an array in the OpenAI format
+        if !self.parts.is_empty() {
+            let text = (!self.content.is_empty()).then(|| json!({"type": "text", "text": self.content}));
+            let images = self
+                .parts
+                .iter()
+                .map(|part| json!({"type": "image_url", "image_url": {"url": part.url()}}));
+            map.insert("content".to_string(), Value::Array(text.into_iter().chain(images).collect()));
+        } else if !self.content.is_empty() {
             map.insert("content".to_string(), serde_json::json!(self.content));
         }
 
@@ -135,12 +169,31 @@ impl ToolCall {
     }
 }
 
+/// Tokens used by one LLM request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// All prompt tokens, including those read from or written to the cache
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Prompt tokens served from the provider's prompt cache
+    pub cache_read_tokens: u64,
+    /// Prompt tokens written to the cache, where the provider charges for it
+    pub cache_write_tokens: u64,
+}
+
 /// Response from the LLM
 #[derive(Debug, Clone)]
 pub struct LLMResponse {
     pub content: Option<String>,
     pub tool_calls: Vec<ToolCall>,
     pub finish_reason: String,
+    /// Token counts, when the provider reports them
+    pub usage: Option<Usage>,
+    /// Reasoning trace of thinking models, kept apart from the answer
+    pub reasoning: Option<String>,
+    /// Provider thinking blocks as returned, such as Anthropic's signed
+    /// `thinking` blocks, which must be sent back with the tool calls
+    pub thinking_blocks: Vec<serde_json::Value>,
 }
 
 impl LLMResponse {
@@ -150,6 +203,9 @@ impl LLMResponse {
             content,
             tool_calls,
             finish_reason: finish_reason.to_string(),
+            usage: None,
+            reasoning: None,
+            thinking_blocks: vec![],
         }
     }
 
@@ -159,6 +215,9 @@ impl LLMResponse {
             content: Some(String::new()),
             tool_calls: vec![],
             finish_reason: "stop".to_string(),
+            usage: None,
+            reasoning: None,
+            thinking_blocks: vec![],
         }
     }
 }
@@ -258,6 +317,12 @@ pub enum Event {
     Disconnect { channel: String, chat_id: String },
     #[serde(rename = "error")]
     Error { channel: String, error: String },
+    /// The gateway applied a changed config
+    #[serde(rename = "config_reload")]
+    ConfigReload { changes: Vec<String> },
+    /// A changed config was rejected and the old one is still in use
+    #[serde(rename = "config_error")]
+    ConfigError { errors: Vec<String> },
 }
 
 impl Event {
@@ -292,6 +357,7 @@ impl Event {
             Event::Connect { channel, .. } => channel,
             Event::Disconnect { channel, .. } => channel,
             Event::Error { channel, .. } => channel,
+            Event::ConfigReload { .. } | Event::ConfigError { .. } => "config",
         }
     }
 }
@@ -401,6 +467,17 @@ mod tests {
         assert_eq!(json["content"], "Hello");
     }
 
+    #[test]
+    fn test_message_parts_to_json() {
+        let mut msg = Message::user("What is this?");
+        msg.parts.push(ContentPart::Image { mime_type: "image/png".to_string(), data: "iVBORw0K".to_string() });
+        msg.parts.push(ContentPart::ImageUrl("https://example.com/cat.jpg".to_string()));
+        let json = msg.to_json();
+        assert_eq!(json["content"][0]["text"], "What is this?");
+        assert_eq!(json["content"][1]["image_url"]["url"], "data:image/png;base64,iVBORw0K");
+        assert_eq!(json["content"][2]["image_url"]["url"], "https://example.com/cat.jpg");
+    }
+
     #[test]
     fn test_tool_call_new() {
         let tc = ToolCall::new("call_1", "read_file", json!({"path": "/test"}));